@@ -0,0 +1,362 @@
+use nu_protocol::{
+    ast::{Argument, Block, Expr, Expression, Pipeline, PipelineElement},
+    engine::{EngineState, StateWorkingSet},
+    Span,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::helpers::{parse_detached, parse_diagnostics, relative_span, ParseDiagnostic};
+
+/// A source parsed by [`parse_ast`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParsedSource {
+    /// The block of the whole source.
+    pub root: AstNode,
+    /// The problems the parser found, the nodes it couldn't make sense of are
+    /// [`AstKind::Garbage`].
+    pub diagnostics: Vec<ParseDiagnostic>,
+}
+
+/// A node of the tree of a parsed source, with the blocks of closures and subexpressions in it
+/// rather than the ids of nu's AST.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AstNode {
+    pub kind: AstKind,
+    /// The bytes of the source the node is parsed from.
+    pub span: Span,
+    /// The command a call runs, the flag, variable or operator the node is, the redirection like
+    /// `out>`, or the text of a string or a cell path.
+    pub name: Option<String>,
+    /// The type the parser inferred, like `int` or `list<string>`.
+    pub ty: String,
+    /// The nodes in it, in the order of the source.
+    pub children: Vec<AstNode>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AstKind {
+    /// A source, closure, block or subexpression, with a pipeline per statement.
+    Block,
+    /// Commands and expressions joined with `|`.
+    Pipeline,
+    /// Where the output of the pipeline element before it goes, like `out> file.txt`.
+    Redirection,
+    Call,
+    ExternalCall,
+    /// A `--flag` of a call, with its value if it has one.
+    Flag,
+    Bool,
+    Int,
+    Float,
+    Binary,
+    Range,
+    Variable,
+    VariableDeclaration,
+    Operator,
+    RowCondition,
+    Not,
+    BinaryOp,
+    Subexpression,
+    Closure,
+    Match,
+    MatchPattern,
+    List,
+    Table,
+    Record,
+    Keyword,
+    ValueWithUnit,
+    DateTime,
+    Filepath,
+    Directory,
+    GlobPattern,
+    String,
+    StringInterpolation,
+    CellPath,
+    FullCellPath,
+    ImportPattern,
+    Overlay,
+    Signature,
+    Nothing,
+    Garbage,
+}
+
+/// Parse `source` without evaluating it, into a tree of [`AstNode`]s for analysing,
+/// visualising or rewriting it. Spans are byte offsets into `source`.
+///
+/// ```
+/// use nu_app::ast::{parse_ast, AstKind};
+/// use nu_app::create_engine_state;
+///
+/// let engine_state = create_engine_state();
+/// let parsed = parse_ast(&engine_state, b"ls | where size > 1kb", "script.nu");
+///
+/// let pipeline = &parsed.root.children[0];
+/// assert_eq!(pipeline.kind, AstKind::Pipeline);
+/// assert_eq!(pipeline.children[1].name.as_deref(), Some("where"));
+/// ```
+pub fn parse_ast(engine_state: &EngineState, source: &[u8], fname: &str) -> ParsedSource {
+    parse_detached(
+        engine_state,
+        source,
+        fname,
+        |working_set, block, source_span| {
+            let mirror = AstMirror {
+                working_set,
+                source_span,
+            };
+
+            ParsedSource {
+                root: mirror.block(block, source_span),
+                diagnostics: parse_diagnostics(working_set, source_span),
+            }
+        },
+    )
+}
+
+/// Parse `source` without evaluating it and hand nu's own AST to `f`, with the working set the
+/// blocks it refers to by id are in. The spans are the working set's.
+pub fn parse_block<R>(
+    engine_state: &EngineState,
+    source: &[u8],
+    fname: &str,
+    f: impl FnOnce(&StateWorkingSet, &Block) -> R,
+) -> R {
+    parse_detached(engine_state, source, fname, |working_set, block, _| {
+        f(working_set, block)
+    })
+}
+
+struct AstMirror<'a, 'b> {
+    working_set: &'a StateWorkingSet<'b>,
+    source_span: Span,
+}
+
+impl AstMirror<'_, '_> {
+    fn node(
+        &self,
+        kind: AstKind,
+        span: Span,
+        name: Option<String>,
+        children: Vec<AstNode>,
+    ) -> AstNode {
+        AstNode {
+            kind,
+            span: relative_span(span, self.source_span),
+            name,
+            ty: String::new(),
+            children,
+        }
+    }
+
+    fn block(&self, block: &Block, span: Span) -> AstNode {
+        let pipelines = block
+            .pipelines
+            .iter()
+            .map(|pipeline| self.pipeline(pipeline))
+            .collect();
+
+        self.node(AstKind::Block, block.span.unwrap_or(span), None, pipelines)
+    }
+
+    fn pipeline(&self, pipeline: &Pipeline) -> AstNode {
+        let mut children = vec![];
+        for element in &pipeline.elements {
+            // redirections are elements of their own, after the command they redirect
+            match element {
+                PipelineElement::Redirection(span, _, target) => {
+                    children.push(self.redirection(*span, target))
+                }
+                PipelineElement::SeparateRedirection { out, err } => {
+                    children.push(self.redirection(out.0, &out.1));
+                    children.push(self.redirection(err.0, &err.1));
+                }
+                PipelineElement::SameTargetRedirection {
+                    cmd: (_, command),
+                    redirection,
+                } => {
+                    children.push(self.expression(command));
+                    children.push(self.redirection(redirection.0, &redirection.1));
+                }
+                element => children.push(self.expression(element.expression())),
+            }
+        }
+
+        let span = pipeline
+            .elements
+            .first()
+            .zip(pipeline.elements.last())
+            .map(|(first, last)| Span::new(first.span().start, last.span().end))
+            .unwrap_or_else(Span::unknown);
+
+        self.node(AstKind::Pipeline, span, None, children)
+    }
+
+    fn redirection(&self, span: Span, target: &Expression) -> AstNode {
+        let name = self.text(span);
+        let span = Span::new(span.start, target.span.end);
+
+        self.node(
+            AstKind::Redirection,
+            span,
+            Some(name),
+            vec![self.expression(target)],
+        )
+    }
+
+    fn expression(&self, expression: &Expression) -> AstNode {
+        let span = expression.span;
+        let expressions =
+            |expressions: &[Expression]| expressions.iter().map(|e| self.expression(e)).collect();
+
+        let (kind, name, children) = match &expression.expr {
+            Expr::Bool(_) => (AstKind::Bool, None, vec![]),
+            Expr::Int(_) => (AstKind::Int, None, vec![]),
+            Expr::Float(_) => (AstKind::Float, None, vec![]),
+            Expr::Binary(_) => (AstKind::Binary, None, vec![]),
+            Expr::Range(from, next, to, operator) => {
+                let children = [from, next, to]
+                    .into_iter()
+                    .flatten()
+                    .map(|e| self.expression(e))
+                    .collect();
+                (AstKind::Range, Some(operator.to_string()), children)
+            }
+            Expr::Var(_) => (AstKind::Variable, Some(self.text(span)), vec![]),
+            Expr::VarDecl(_) => (AstKind::VariableDeclaration, Some(self.text(span)), vec![]),
+            Expr::Call(call) => {
+                let name = self.working_set.get_decl(call.decl_id).name().to_string();
+                let children = call
+                    .arguments
+                    .iter()
+                    .map(|argument| match argument {
+                        Argument::Positional(e) | Argument::Unknown(e) => self.expression(e),
+                        Argument::Named((flag, _, value)) => {
+                            let end = value.as_ref().map_or(flag.span.end, |e| e.span.end);
+                            let value = value.iter().map(|e| self.expression(e)).collect();
+                            let span = Span::new(flag.span.start, end);
+                            self.node(AstKind::Flag, span, Some(flag.item.clone()), value)
+                        }
+                    })
+                    .collect();
+                (AstKind::Call, Some(name), children)
+            }
+            Expr::ExternalCall(head, args, _) => {
+                let mut children = vec![self.expression(head)];
+                children.extend(args.iter().map(|e| self.expression(e)));
+                (AstKind::ExternalCall, Some(self.text(head.span)), children)
+            }
+            Expr::Operator(operator) => (AstKind::Operator, Some(operator.to_string()), vec![]),
+            Expr::RowCondition(block_id) => (
+                AstKind::RowCondition,
+                None,
+                self.nested_block(*block_id, span),
+            ),
+            Expr::UnaryNot(e) => (AstKind::Not, None, vec![self.expression(e)]),
+            Expr::BinaryOp(lhs, op, rhs) => (
+                AstKind::BinaryOp,
+                Some(self.text(op.span)),
+                vec![
+                    self.expression(lhs),
+                    self.expression(op),
+                    self.expression(rhs),
+                ],
+            ),
+            Expr::Subexpression(block_id) => (
+                AstKind::Subexpression,
+                None,
+                self.nested_block(*block_id, span),
+            ),
+            Expr::Block(block_id) => (AstKind::Block, None, self.nested_block(*block_id, span)),
+            Expr::Closure(block_id) => (AstKind::Closure, None, self.nested_block(*block_id, span)),
+            Expr::MatchBlock(arms) => {
+                let mut children = vec![];
+                for (pattern, e) in arms {
+                    children.push(self.node(AstKind::MatchPattern, pattern.span, None, vec![]));
+                    children.push(self.expression(e));
+                }
+                (AstKind::Match, None, children)
+            }
+            Expr::List(items) => (AstKind::List, None, expressions(items)),
+            Expr::Table(headers, rows) => {
+                let mut children = vec![self.list(headers, span)];
+                children.extend(rows.iter().map(|row| self.list(row, span)));
+                (AstKind::Table, None, children)
+            }
+            Expr::Record(fields) => {
+                let children = fields
+                    .iter()
+                    .flat_map(|(key, value)| [self.expression(key), self.expression(value)])
+                    .collect();
+                (AstKind::Record, None, children)
+            }
+            Expr::Keyword(keyword, _, e) => (
+                AstKind::Keyword,
+                Some(String::from_utf8_lossy(keyword).into_owned()),
+                vec![self.expression(e)],
+            ),
+            Expr::ValueWithUnit(e, unit) => (
+                AstKind::ValueWithUnit,
+                Some(self.text(unit.span)),
+                vec![self.expression(e)],
+            ),
+            Expr::DateTime(_) => (AstKind::DateTime, None, vec![]),
+            Expr::Filepath(path) => (AstKind::Filepath, Some(path.clone()), vec![]),
+            Expr::Directory(path) => (AstKind::Directory, Some(path.clone()), vec![]),
+            Expr::GlobPattern(pattern) => (AstKind::GlobPattern, Some(pattern.clone()), vec![]),
+            Expr::String(string) => (AstKind::String, Some(string.clone()), vec![]),
+            Expr::StringInterpolation(parts) => {
+                (AstKind::StringInterpolation, None, expressions(parts))
+            }
+            Expr::CellPath(cell_path) => (AstKind::CellPath, Some(cell_path.into_string()), vec![]),
+            Expr::FullCellPath(full_cell_path) => {
+                let tail = nu_protocol::ast::CellPath {
+                    members: full_cell_path.tail.clone(),
+                }
+                .into_string();
+                let name = (!tail.is_empty()).then_some(tail);
+                (
+                    AstKind::FullCellPath,
+                    name,
+                    vec![self.expression(&full_cell_path.head)],
+                )
+            }
+            Expr::ImportPattern(_) => (AstKind::ImportPattern, Some(self.text(span)), vec![]),
+            Expr::Overlay(_) => (AstKind::Overlay, Some(self.text(span)), vec![]),
+            Expr::Signature(signature) => {
+                let name = (!signature.name.is_empty()).then(|| signature.name.clone());
+                (AstKind::Signature, name, vec![])
+            }
+            Expr::MatchPattern(_) => (AstKind::MatchPattern, None, vec![]),
+            Expr::Nothing => (AstKind::Nothing, None, vec![]),
+            Expr::Garbage => (AstKind::Garbage, None, vec![]),
+        };
+
+        AstNode {
+            ty: expression.ty.to_string(),
+            ..self.node(kind, span, name, children)
+        }
+    }
+
+    // the pipelines of a closure, block or subexpression
+    fn nested_block(&self, block_id: usize, span: Span) -> Vec<AstNode> {
+        self.block(self.working_set.get_block(block_id), span)
+            .children
+    }
+
+    // a row or the headers of a table
+    fn list(&self, items: &[Expression], table_span: Span) -> AstNode {
+        let span = items
+            .first()
+            .zip(items.last())
+            .map(|(first, last)| Span::new(first.span.start, last.span.end))
+            .unwrap_or(table_span);
+        let children = items.iter().map(|e| self.expression(e)).collect();
+
+        self.node(AstKind::List, span, None, children)
+    }
+
+    fn text(&self, span: Span) -> String {
+        String::from_utf8_lossy(self.working_set.get_span_contents(span)).into_owned()
+    }
+}
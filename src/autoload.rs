@@ -0,0 +1,107 @@
+use std::path::{Path, PathBuf};
+
+use nu_protocol::{
+    engine::{EngineState, Stack},
+    PipelineData, ShellError,
+};
+
+use crate::helpers::{eval_source, report_error_new};
+
+/// The directories packages install startup scripts into, in the order they're sourced.
+///
+/// On unix that's `nu_app/vendor/autoload` in every `$XDG_DATA_DIRS` entry (`/usr/local/share` and
+/// `/usr/share` by default), the most important one last so it can override the others. On
+/// windows it's `%ProgramData%\nu_app\vendor\autoload`. Packagers can add one more by building
+/// with `NU_APP_VENDOR_AUTOLOAD_DIR` set.
+pub fn vendor_autoload_dirs() -> Vec<PathBuf> {
+    let mut dirs = vec![];
+
+    #[cfg(unix)]
+    {
+        let data_dirs = std::env::var("XDG_DATA_DIRS")
+            .ok()
+            .filter(|dirs| !dirs.is_empty())
+            .unwrap_or_else(|| "/usr/local/share:/usr/share".into());
+        let data_dirs: Vec<PathBuf> = std::env::split_paths(&data_dirs).collect();
+        dirs.extend(
+            data_dirs
+                .into_iter()
+                .rev()
+                .map(|dir| dir.join("nu_app").join("vendor").join("autoload")),
+        );
+    }
+
+    #[cfg(windows)]
+    if let Some(program_data) = std::env::var_os("ProgramData") {
+        dirs.push(
+            PathBuf::from(program_data)
+                .join("nu_app")
+                .join("vendor")
+                .join("autoload"),
+        );
+    }
+
+    if let Some(dir) = option_env!("NU_APP_VENDOR_AUTOLOAD_DIR") {
+        dirs.push(PathBuf::from(dir));
+    }
+
+    // a directory listed twice is sourced where it comes last
+    let mut unique = vec![];
+    for dir in dirs.into_iter().rev() {
+        if !unique.contains(&dir) {
+            unique.push(dir);
+        }
+    }
+    unique.reverse();
+
+    unique
+}
+
+/// The directory of the user's startup scripts, `<config dir>/nu_app/scripts.d`.
+pub fn user_autoload_dir() -> Option<PathBuf> {
+    nu_path::config_dir().map(|dir| dir.join("nu_app").join("scripts.d"))
+}
+
+/// The `.nu` files in `dir`, ordered by name so `10-env.nu` runs before `20-aliases.nu`.
+///
+/// A missing directory has no files.
+pub fn autoload_files(dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return vec![];
+    };
+
+    let mut files: Vec<PathBuf> = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.is_file() && path.extension().is_some_and(|ext| ext == "nu"))
+        .collect();
+    files.sort();
+
+    files
+}
+
+/// Source the `.nu` files in `dir` one after another, keeping their definitions and environment.
+///
+/// A file that fails is reported and skipped, the ones after it still run.
+pub fn source_autoload_dir(engine_state: &mut EngineState, stack: &mut Stack, dir: &Path) {
+    for file in autoload_files(dir) {
+        match std::fs::read(&file) {
+            Ok(source) => {
+                crate::hooks::without_hooks(|| {
+                    eval_source(
+                        engine_state,
+                        stack,
+                        &source,
+                        &file.to_string_lossy(),
+                        PipelineData::Empty,
+                        false,
+                    )
+                });
+            }
+            Err(err) => report_error_new(
+                engine_state,
+                &ShellError::IOError(format!("could not read {}: {err}", file.display())),
+            ),
+        }
+    }
+}
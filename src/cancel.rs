@@ -0,0 +1,62 @@
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+
+use nu_protocol::engine::EngineState;
+
+/// Stops what an engine is evaluating from another thread, e.g. when the user of a GUI hits a
+/// stop button.
+///
+/// It sets the engine's ctrl-c flag, so the evaluation stops like it would on ctrl-c and
+/// [`eval_source_to_value`](crate::eval_source_to_value) returns an interrupted error. Clones
+/// stop the same engine.
+///
+/// ```no_run
+/// use nu_app::{cancel::CancellationHandle, create_engine_state, create_stack, eval_source};
+/// use nu_app::nu_protocol::PipelineData;
+///
+/// let mut engine_state = create_engine_state();
+/// let mut stack = create_stack();
+///
+/// let handle = CancellationHandle::new(&mut engine_state);
+/// let stopper = handle.clone();
+/// std::thread::spawn(move || {
+///     std::thread::sleep(std::time::Duration::from_secs(1));
+///     stopper.cancel();
+/// });
+///
+/// eval_source(&mut engine_state, &mut stack, b"sleep 1min", "app", PipelineData::Empty, true);
+/// assert!(handle.is_cancelled());
+/// ```
+#[derive(Debug, Clone)]
+pub struct CancellationHandle {
+    ctrlc: Arc<AtomicBool>,
+}
+
+impl CancellationHandle {
+    /// A handle for the evaluations of `engine_state`, giving it a ctrl-c flag if it doesn't
+    /// have one, see [`install_ctrlc_handler`](crate::install_ctrlc_handler).
+    pub fn new(engine_state: &mut EngineState) -> Self {
+        let ctrlc = engine_state
+            .ctrlc
+            .get_or_insert_with(|| Arc::new(AtomicBool::new(false)))
+            .clone();
+
+        CancellationHandle { ctrlc }
+    }
+
+    /// Stop the evaluation that's running, and the ones after it until [`reset`](Self::reset).
+    pub fn cancel(&self) {
+        self.ctrlc.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.ctrlc.load(Ordering::SeqCst)
+    }
+
+    /// Let the engine evaluate again after it was cancelled.
+    pub fn reset(&self) {
+        self.ctrlc.store(false, Ordering::SeqCst);
+    }
+}
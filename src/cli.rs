@@ -0,0 +1,257 @@
+use std::time::Duration;
+
+use crate::deprecation::RemovedCommands;
+use crate::errors::ErrorFormat;
+use crate::helpers::StdinFormat;
+use crate::ide::IdeRequest;
+
+/// The address `nu_app serve` listens on when it isn't given one.
+pub const DEFAULT_SERVE_ADDR: &str = "127.0.0.1:8080";
+
+/// The command line arguments `nu_app` was started with.
+#[derive(Debug, Default)]
+pub struct CliArgs {
+    /// Commands, or paths of files with commands, to run in order before the script or the REPL.
+    pub execute: Vec<String>,
+    /// Commands to run instead of a script.
+    pub commands: Option<String>,
+    /// Stay in the REPL after running the script.
+    pub interactive: bool,
+    pub help: bool,
+    /// How parse and runtime errors are written to stderr.
+    pub error_format: ErrorFormat,
+    /// What piped stdin is parsed as before it's the input of the script or commands.
+    pub stdin_format: StdinFormat,
+    /// Whether stdin is the input of the script or commands: `Some(true)` even when it's a
+    /// terminal, `Some(false)` never, and `None` when it's piped in.
+    pub stdin: Option<bool>,
+    /// The timezone dates are shown in, instead of the machine's.
+    pub timezone: Option<String>,
+    /// Register the experimental commands too.
+    pub experimental: bool,
+    /// What running a command nu has removed does.
+    pub removed_commands: RemovedCommands,
+    /// The `config.nu` to read instead of the default one.
+    pub config_file: Option<String>,
+    /// The `env.nu` to read instead of the default one.
+    pub env_file: Option<String>,
+    /// The plugin registry to read and write instead of the default one.
+    pub plugin_file: Option<String>,
+    /// Don't read the default `env.nu`, `config.nu` and plugin registry.
+    pub no_config_file: bool,
+    /// Print tables and errors without colors, like when `NO_COLOR` is set.
+    pub no_color: bool,
+    /// Leave the sources evaluated before a crash out of the crash report.
+    pub no_crash_history: bool,
+    /// Print what an editor asks about the script as JSON instead of running it.
+    pub ide: Option<IdeRequest>,
+    /// Serve editors with the Language Server Protocol over stdin and stdout.
+    pub lsp: bool,
+    /// The Unix socket to evaluate the sources clients send to, keeping the engine warm.
+    pub daemon: Option<String>,
+    /// The address the `serve` subcommand answers HTTP requests on.
+    pub serve: Option<String>,
+    /// The token `serve` only answers requests with.
+    pub token: Option<String>,
+    /// How long `serve` and the daemon let a source run.
+    pub timeout: Option<Duration>,
+    /// Give the sources `serve` evaluates all the commands, not only the sandboxed ones.
+    pub unsandboxed: bool,
+    /// The script file to run.
+    pub script: Option<String>,
+    /// Everything after the script path, passed on to the script's `main`.
+    pub script_args: Vec<String>,
+}
+
+pub const USAGE: &str = r#"Usage: nu_app [OPTIONS] [SCRIPT [ARGS]...]
+       nu_app serve [OPTIONS] [ADDR]
+
+Arguments:
+  [SCRIPT]   a script file to run
+  [ARGS]...  arguments for the script's main command
+
+Commands:
+  serve      evaluate the sources POSTed to /eval on ADDR (127.0.0.1:8080 by default)
+             and answer with JSON, or NDJSON for Accept: application/x-ndjson,
+             when nu_app is built with the serve feature; the sources only get the
+             sandboxed commands, and requests from web pages are refused
+
+Serve options:
+      --token <TOKEN>       only answer requests with Authorization: Bearer <TOKEN>
+      --unsandboxed         give the sources all the commands, to touch files, the
+                            network and the system, and run externals with
+
+Options:
+  -c, --commands <COMMANDS>
+                            run commands, with stdin as their input, then exit
+  -e, --execute <COMMANDS>  run commands (or the file at the given path), then start the REPL,
+                            can be given more than once to run each in order
+  -i, --interactive         start the REPL once the script or commands have run
+      --error-format <FORMAT>
+                            print errors as human (the default) or json
+      --stdin               make stdin the input of the script or commands, also when
+                            it's a terminal, which is read until EOF (ctrl-d)
+      --no-stdin            don't make piped stdin the input of the script or commands
+      --stdin-format <FORMAT>
+                            read piped stdin as raw text (the default), a json
+                            document, ndjson with a json document per line, or
+                            binary that's passed on byte for byte
+      --timezone <TZ>       show dates in this timezone, like UTC or Europe/Berlin, not
+                            on windows
+      --experimental        add the commands that may still change, like view source
+      --removed-commands <HANDLING>
+                            make removed commands like let-env error (the default),
+                            warn and run their replacement, or hide
+      --config <FILE>       read this config.nu, also for scripts and commands
+      --env-config <FILE>   read this env.nu, also for scripts and commands
+      --plugin-config <FILE>
+                            load the plugins registered in this file, and register new
+                            ones in it, instead of <config dir>/nu_app/plugin.nu
+  -n, --no-config-file      don't read the default env.nu, config.nu and plugin registry
+      --no-color            print without colors, which is the default when NO_COLOR
+                            is set or stdout isn't a terminal
+      --no-crash-history    only put the source that crashed into crash reports, not
+                            the ones evaluated before it
+      --ide-check           print the problems the parser finds in the script as JSON,
+                            a line for each, instead of running it
+      --ide-ast             print the tree of the parsed script as JSON
+      --ide-hover <OFFSET>  print what to show for the byte at OFFSET of the script, like
+                            the usage of a command or the type of a variable, as JSON
+      --lsp                 run a language server for editors on stdin and stdout
+      --daemon <SOCKET>     evaluate the sources clients send to the Unix socket SOCKET,
+                            a line of JSON for each, and answer them with JSON
+      --timeout <SECS>      stop a source of serve or --daemon after SECS seconds (30 by
+                            default)
+  -h, --help                print this help
+
+Without a script or commands to run, nu_app starts the REPL when stdin is a terminal.
+The REPL starts by reading env.nu and config.nu in <config dir>/nu_app. Then it sources
+the .nu files in the vendor autoload directories (nu_app/vendor/autoload in each
+$XDG_DATA_DIRS entry, %ProgramData% on windows), then the ones in
+<config dir>/nu_app/scripts.d, each directory in order of the file names.
+
+When nu_app crashes, it writes a report to <config dir>/nu_app/crashes."#;
+
+pub fn parse_args(args: impl IntoIterator<Item = String>) -> Result<CliArgs, String> {
+    let mut cli_args = CliArgs::default();
+    let mut args = args.into_iter().peekable();
+
+    // a script called serve can still be run as ./serve
+    if args.next_if(|arg| arg == "serve").is_some() {
+        cli_args.serve = Some(DEFAULT_SERVE_ADDR.into());
+    }
+
+    while let Some(arg) = args.next() {
+        let (flag, value) = match arg.split_once('=') {
+            Some((flag, value)) if flag.starts_with("--") => (flag.to_string(), Some(value.into())),
+            _ => (arg, None),
+        };
+
+        match flag.as_str() {
+            "-e" | "--execute" => {
+                let value = value.or_else(|| args.next());
+                let value = value.ok_or_else(|| format!("{flag} expects a value"))?;
+                cli_args.execute.push(value);
+            }
+            "-c" | "--commands" => {
+                let value = value.or_else(|| args.next());
+                cli_args.commands = Some(value.ok_or_else(|| format!("{flag} expects a value"))?);
+            }
+            "-i" | "--interactive" => cli_args.interactive = true,
+            "--error-format" => {
+                let value = value.or_else(|| args.next());
+                let value = value.ok_or_else(|| format!("{flag} expects a value"))?;
+                cli_args.error_format = value.parse()?;
+            }
+            "--stdin" => cli_args.stdin = Some(true),
+            "--no-stdin" => cli_args.stdin = Some(false),
+            "--stdin-format" => {
+                let value = value.or_else(|| args.next());
+                let value = value.ok_or_else(|| format!("{flag} expects a value"))?;
+                cli_args.stdin_format = value.parse()?;
+            }
+            "--timezone" => {
+                let value = value.or_else(|| args.next());
+                cli_args.timezone = Some(value.ok_or_else(|| format!("{flag} expects a value"))?);
+            }
+            "--experimental" => cli_args.experimental = true,
+            "--removed-commands" => {
+                let value = value.or_else(|| args.next());
+                let value = value.ok_or_else(|| format!("{flag} expects a value"))?;
+                cli_args.removed_commands = value.parse()?;
+            }
+            "--config" => {
+                let value = value.or_else(|| args.next());
+                cli_args.config_file =
+                    Some(value.ok_or_else(|| format!("{flag} expects a value"))?);
+            }
+            "--env-config" => {
+                let value = value.or_else(|| args.next());
+                cli_args.env_file = Some(value.ok_or_else(|| format!("{flag} expects a value"))?);
+            }
+            "--plugin-config" => {
+                let value = value.or_else(|| args.next());
+                cli_args.plugin_file =
+                    Some(value.ok_or_else(|| format!("{flag} expects a value"))?);
+            }
+            "-n" | "--no-config-file" => cli_args.no_config_file = true,
+            "--no-color" => cli_args.no_color = true,
+            "--no-crash-history" => cli_args.no_crash_history = true,
+            "--ide-check" => cli_args.ide = Some(IdeRequest::Check),
+            "--ide-ast" => cli_args.ide = Some(IdeRequest::Ast),
+            "--ide-hover" => {
+                let value = value.or_else(|| args.next());
+                let value = value.ok_or_else(|| format!("{flag} expects a value"))?;
+                let offset = value
+                    .parse()
+                    .map_err(|_| format!("{flag} expects a byte offset, not '{value}'"))?;
+                cli_args.ide = Some(IdeRequest::Hover(offset));
+            }
+            "--lsp" => cli_args.lsp = true,
+            "--daemon" => {
+                let value = value.or_else(|| args.next());
+                cli_args.daemon = Some(value.ok_or_else(|| format!("{flag} expects a value"))?);
+            }
+            "--token" => {
+                let value = value.or_else(|| args.next());
+                cli_args.token = Some(value.ok_or_else(|| format!("{flag} expects a value"))?);
+            }
+            "--timeout" => {
+                let value = value.or_else(|| args.next());
+                let value = value.ok_or_else(|| format!("{flag} expects a value"))?;
+                let secs = value
+                    .parse()
+                    .map_err(|_| format!("{flag} expects a number of seconds, not '{value}'"))?;
+                cli_args.timeout = Some(Duration::from_secs(secs));
+            }
+            "--unsandboxed" => cli_args.unsandboxed = true,
+            "-h" | "--help" => cli_args.help = true,
+            _ if !flag.starts_with('-') && cli_args.serve.is_some() => cli_args.serve = Some(flag),
+            // the script owns the rest of the arguments, even ones that look like our flags
+            _ if !flag.starts_with('-') => {
+                cli_args.script = Some(flag);
+                cli_args.script_args = args.collect();
+                break;
+            }
+            _ => return Err(format!("unexpected argument '{flag}'")),
+        }
+    }
+
+    if cli_args.commands.is_some() && cli_args.script.is_some() {
+        return Err("--commands and a script can't be run together".into());
+    }
+    if cli_args.serve.is_some() && (cli_args.commands.is_some() || !cli_args.execute.is_empty()) {
+        return Err("serve evaluates the sources it's sent, not --commands or --execute".into());
+    }
+    if (cli_args.token.is_some() || cli_args.unsandboxed) && cli_args.serve.is_none() {
+        return Err("--token and --unsandboxed are options of serve".into());
+    }
+    if cli_args.timeout.is_some() && cli_args.serve.is_none() && cli_args.daemon.is_none() {
+        return Err("--timeout is an option of serve and --daemon".into());
+    }
+    if cli_args.ide.is_some() && cli_args.script.is_none() {
+        return Err("the --ide flags need a script".into());
+    }
+
+    Ok(cli_args)
+}
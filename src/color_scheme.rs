@@ -0,0 +1,78 @@
+use nu_protocol::{engine::EngineState, Span, Value};
+
+/// The colors of the rendered output, for hosts that configure the engine from code instead of
+/// a `config.nu`.
+///
+/// The colors go into `$env.config.color_config`, which colors the values of tables by their
+/// type, the header and the index, and the highlighting of the REPL by `shape_*` keys. File
+/// names in `ls` are colored by `LS_COLORS`, which the scheme sets on the engine, so it's kept
+/// over the `LS_COLORS` of the process:
+///
+/// ```
+/// use nu_app::{color_scheme::ColorScheme, create_engine_state};
+///
+/// let mut engine_state = create_engine_state();
+/// ColorScheme::new()
+///     .color("header", "cyan_bold")
+///     .color("filesize", "#ff8800")
+///     .ls_colors("di=1;34:ln=36:*.rs=33")
+///     .apply(&mut engine_state);
+///
+/// let header = &engine_state.get_config().color_config["header"];
+/// assert_eq!(header.as_string().unwrap(), "cyan_bold");
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct ColorScheme {
+    colors: Vec<(String, String)>,
+    ls_colors: Option<String>,
+    file_name_colors: Option<bool>,
+}
+
+impl ColorScheme {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The style of a key of `$env.config.color_config`, like `header`, `row_index`, a type like
+    /// `string` or a shape like `shape_external`. The style is a color name like `green_bold`, an
+    /// abbreviation like `gb` or a hex color like `#ff8800`.
+    pub fn color(mut self, key: impl Into<String>, style: impl Into<String>) -> Self {
+        self.colors.push((key.into(), style.into()));
+        self
+    }
+
+    /// The colors of file names, in the format of `LS_COLORS`, e.g. `di=1;34:*.rs=33`.
+    pub fn ls_colors(mut self, ls_colors: impl Into<String>) -> Self {
+        self.ls_colors = Some(ls_colors.into());
+        self
+    }
+
+    /// Whether tables color the file names of `ls` by `LS_COLORS` at all.
+    pub fn file_name_colors(mut self, color: bool) -> Self {
+        self.file_name_colors = Some(color);
+        self
+    }
+
+    /// Change the engine's config and environment to render output with these colors.
+    pub fn apply(&self, engine_state: &mut EngineState) {
+        let mut config = engine_state.get_config().clone();
+
+        for (key, style) in &self.colors {
+            config
+                .color_config
+                .insert(key.clone(), Value::string(style, Span::unknown()));
+        }
+        if let Some(color) = self.file_name_colors {
+            config.use_ls_colors = color;
+        }
+
+        engine_state.set_config(&config);
+
+        if let Some(ls_colors) = &self.ls_colors {
+            engine_state.add_env_var(
+                "LS_COLORS".into(),
+                Value::string(ls_colors, Span::unknown()),
+            );
+        }
+    }
+}
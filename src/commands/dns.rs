@@ -0,0 +1,187 @@
+use std::{net::IpAddr, str::FromStr};
+
+use hickory_resolver::{
+    config::{NameServerConfigGroup, ResolverConfig, ResolverOpts},
+    error::ResolveErrorKind,
+    proto::rr::RecordType,
+    Resolver,
+};
+use nu_engine::{get_full_help, CallExt};
+use nu_protocol::{
+    ast::Call,
+    engine::{Command, EngineState, Stack},
+    Category, Example, IntoPipelineData, PipelineData, ShellError, Signature, Span, Spanned,
+    SyntaxShape, Type, Value,
+};
+
+#[derive(Clone)]
+pub struct Dns;
+
+impl Command for Dns {
+    fn name(&self) -> &str {
+        "dns"
+    }
+
+    fn signature(&self) -> Signature {
+        Signature::build("dns")
+            .input_output_types(vec![(Type::Nothing, Type::String)])
+            .category(Category::Network)
+    }
+
+    fn usage(&self) -> &str {
+        "Various commands for querying DNS."
+    }
+
+    fn extra_usage(&self) -> &str {
+        "You must use one of the following subcommands. Using this command as-is will only produce this help message."
+    }
+
+    fn search_terms(&self) -> Vec<&str> {
+        vec!["network", "resolve", "lookup"]
+    }
+
+    fn run(
+        &self,
+        engine_state: &EngineState,
+        stack: &mut Stack,
+        call: &Call,
+        _input: PipelineData,
+    ) -> Result<PipelineData, ShellError> {
+        Ok(Value::String {
+            val: get_full_help(
+                &Dns.signature(),
+                &Dns.examples(),
+                engine_state,
+                stack,
+                self.is_parser_keyword(),
+            ),
+            span: call.head,
+        }
+        .into_pipeline_data())
+    }
+}
+
+#[derive(Clone)]
+pub struct DnsQuery;
+
+impl Command for DnsQuery {
+    fn name(&self) -> &str {
+        "dns query"
+    }
+
+    fn signature(&self) -> Signature {
+        Signature::build("dns query")
+            .input_output_types(vec![(Type::Nothing, Type::Table(vec![]))])
+            .required("name", SyntaxShape::String, "the domain name to look up")
+            .named(
+                "type",
+                SyntaxShape::List(Box::new(SyntaxShape::String)),
+                "record types to query, like A, AAAA, MX or TXT (defaults to A and AAAA)",
+                Some('t'),
+            )
+            .named(
+                "server",
+                SyntaxShape::String,
+                "IP address of a name server to use instead of the system resolver",
+                Some('s'),
+            )
+            .category(Category::Network)
+    }
+
+    fn usage(&self) -> &str {
+        "Look up DNS records for a domain name."
+    }
+
+    fn search_terms(&self) -> Vec<&str> {
+        vec!["network", "resolve", "lookup", "dig", "nslookup"]
+    }
+
+    fn run(
+        &self,
+        engine_state: &EngineState,
+        stack: &mut Stack,
+        call: &Call,
+        _input: PipelineData,
+    ) -> Result<PipelineData, ShellError> {
+        let name: Spanned<String> = call.req(engine_state, stack, 0)?;
+        let types: Option<Vec<Spanned<String>>> = call.get_flag(engine_state, stack, "type")?;
+        let server: Option<Spanned<String>> = call.get_flag(engine_state, stack, "server")?;
+
+        let record_types = match types {
+            Some(types) => types
+                .into_iter()
+                .map(|ty| {
+                    RecordType::from_str(&ty.item.to_uppercase())
+                        .map_err(|_| dns_error("Unknown record type", &ty.item, ty.span))
+                })
+                .collect::<Result<Vec<_>, _>>()?,
+            None => vec![RecordType::A, RecordType::AAAA],
+        };
+
+        let resolver = match server {
+            Some(server) => {
+                let ip = IpAddr::from_str(&server.item)
+                    .map_err(|err| dns_error("Invalid name server", &err, server.span))?;
+                let config = ResolverConfig::from_parts(
+                    None,
+                    vec![],
+                    NameServerConfigGroup::from_ips_clear(&[ip], 53, true),
+                );
+                Resolver::new(config, ResolverOpts::default())
+            }
+            None => Resolver::from_system_conf(),
+        }
+        .map_err(|err| dns_error("Could not create DNS resolver", &err, call.head))?;
+
+        let span = call.head;
+        let mut rows = vec![];
+        for record_type in record_types {
+            let lookup = match resolver.lookup(name.item.as_str(), record_type) {
+                Ok(lookup) => lookup,
+                Err(err) if matches!(err.kind(), ResolveErrorKind::NoRecordsFound { .. }) => {
+                    continue
+                }
+                Err(err) => return Err(dns_error("DNS query failed", &err, name.span)),
+            };
+
+            for record in lookup.record_iter() {
+                let data = match record.data() {
+                    Some(data) => data.to_string(),
+                    None => continue,
+                };
+
+                rows.push(Value::Record {
+                    cols: vec!["name".into(), "type".into(), "ttl".into(), "data".into()],
+                    vals: vec![
+                        Value::string(record.name().to_string(), span),
+                        Value::string(record.record_type().to_string(), span),
+                        Value::int(record.ttl() as i64, span),
+                        Value::string(data, span),
+                    ],
+                    span,
+                });
+            }
+        }
+
+        Ok(Value::List { vals: rows, span }.into_pipeline_data())
+    }
+
+    fn examples(&self) -> Vec<Example<'_>> {
+        vec![
+            Example {
+                description: "Look up the IPv4 and IPv6 addresses of a host",
+                example: "dns query nushell.sh",
+                result: None,
+            },
+            Example {
+                description: "Look up mail and text records using a specific name server",
+                example: "dns query nushell.sh --type [MX TXT] --server 1.1.1.1",
+                result: None,
+            },
+        ]
+    }
+}
+
+fn dns_error(msg: &str, err: &impl ToString, span: Span) -> ShellError {
+    ShellError::GenericError(msg.into(), err.to_string(), Some(span), None, vec![])
+}
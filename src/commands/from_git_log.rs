@@ -0,0 +1,185 @@
+use chrono::DateTime;
+use nu_protocol::{
+    ast::Call,
+    engine::{Command, EngineState, Stack},
+    Category, Example, IntoPipelineData, PipelineData, ShellError, Signature, Span, Type, Value,
+};
+
+// the formats `git log` prints dates in by default, and with --date=iso, iso-strict or rfc
+const DATE_FORMATS: &[&str] = &[
+    "%a %b %e %H:%M:%S %Y %z",
+    "%Y-%m-%d %H:%M:%S %z",
+    "%Y-%m-%dT%H:%M:%S%:z",
+    "%a, %e %b %Y %H:%M:%S %z",
+];
+
+#[derive(Clone)]
+pub struct FromGitLog;
+
+impl Command for FromGitLog {
+    fn name(&self) -> &str {
+        "from git-log"
+    }
+
+    fn signature(&self) -> Signature {
+        Signature::build("from git-log")
+            .input_output_types(vec![(Type::String, Type::Table(vec![]))])
+            .category(Category::Formats)
+    }
+
+    fn usage(&self) -> &str {
+        "Parse the output of `git log` into a table of commits."
+    }
+
+    fn extra_usage(&self) -> &str {
+        "Expects the default `git log` layout; `--date=iso`, `--date=iso-strict` and `--date=rfc` dates are understood too."
+    }
+
+    fn search_terms(&self) -> Vec<&str> {
+        vec!["git", "commits", "history"]
+    }
+
+    fn run(
+        &self,
+        engine_state: &EngineState,
+        _stack: &mut Stack,
+        call: &Call,
+        input: PipelineData,
+    ) -> Result<PipelineData, ShellError> {
+        let span = call.head;
+        let config = engine_state.get_config();
+        let text = input.collect_string("", config)?;
+
+        let commits = parse_git_log(&text, span)?;
+
+        Ok(Value::List {
+            vals: commits,
+            span,
+        }
+        .into_pipeline_data())
+    }
+
+    fn examples(&self) -> Vec<Example<'_>> {
+        vec![
+            Example {
+                description: "Convert the history of the current repository into a table",
+                example: "git log | from git-log",
+                result: None,
+            },
+            Example {
+                description: "Count commits per author",
+                example: "git log | from git-log | group-by author | transpose author commits | update commits { length }",
+                result: None,
+            },
+        ]
+    }
+}
+
+struct Commit {
+    hash: String,
+    author: String,
+    email: String,
+    date: Option<String>,
+    message: Vec<String>,
+}
+
+impl Commit {
+    fn into_value(self, span: Span) -> Result<Value, ShellError> {
+        let date = match self.date {
+            Some(date) => parse_date(&date, span)?,
+            None => Value::nothing(span),
+        };
+
+        Ok(Value::Record {
+            cols: vec![
+                "hash".into(),
+                "author".into(),
+                "email".into(),
+                "date".into(),
+                "message".into(),
+            ],
+            vals: vec![
+                Value::string(self.hash, span),
+                Value::string(self.author, span),
+                Value::string(self.email, span),
+                date,
+                Value::string(self.message.join("\n").trim().to_string(), span),
+            ],
+            span,
+        })
+    }
+}
+
+fn parse_git_log(text: &str, span: Span) -> Result<Vec<Value>, ShellError> {
+    let mut commits = vec![];
+    let mut current: Option<Commit> = None;
+
+    for line in text.lines() {
+        if let Some(rest) = line.strip_prefix("commit ") {
+            if let Some(commit) = current.take() {
+                commits.push(commit.into_value(span)?);
+            }
+
+            // decorations like `(HEAD -> main, origin/main)` follow the hash
+            let hash = rest.split_whitespace().next().unwrap_or_default();
+            current = Some(Commit {
+                hash: hash.to_string(),
+                author: String::new(),
+                email: String::new(),
+                date: None,
+                message: vec![],
+            });
+            continue;
+        }
+
+        let Some(commit) = current.as_mut() else {
+            if line.trim().is_empty() {
+                continue;
+            }
+            return Err(ShellError::GenericError(
+                "Expected the output of `git log`".into(),
+                format!("unexpected line before the first commit: '{line}'"),
+                Some(span),
+                None,
+                vec![],
+            ));
+        };
+
+        if let Some(message) = line.strip_prefix("    ") {
+            commit.message.push(message.to_string());
+        } else if let Some(author) = line.strip_prefix("Author:") {
+            let author = author.trim();
+            match author.rsplit_once(" <") {
+                Some((name, email)) => {
+                    commit.author = name.to_string();
+                    commit.email = email.trim_end_matches('>').to_string();
+                }
+                None => commit.author = author.to_string(),
+            }
+        } else if let Some(date) = line
+            .strip_prefix("Date:")
+            .or_else(|| line.strip_prefix("AuthorDate:"))
+        {
+            commit.date = Some(date.trim().to_string());
+        }
+    }
+
+    if let Some(commit) = current {
+        commits.push(commit.into_value(span)?);
+    }
+
+    Ok(commits)
+}
+
+fn parse_date(date: &str, span: Span) -> Result<Value, ShellError> {
+    DATE_FORMATS
+        .iter()
+        .find_map(|format| DateTime::parse_from_str(date, format).ok())
+        .map(|date| Value::date(date, span))
+        .ok_or_else(|| ShellError::CantConvert {
+            to_type: "date".into(),
+            from_type: "string".into(),
+            span,
+            help: Some(format!("'{date}' is not in a date format `git log` prints")),
+        })
+}
@@ -0,0 +1,334 @@
+use std::path::Path;
+
+use nu_engine::{
+    env::{current_dir, get_config},
+    env_to_string,
+};
+use nu_protocol::{
+    ast::{Argument, Call},
+    engine::{Command, EngineState, Stack},
+    Example, ListStream, PipelineData, ShellError, Signature, Value,
+};
+use nu_utils::get_ls_colors;
+
+const DIRECTORY_ICON: char = '\u{f115}';
+const SYMLINK_ICON: char = '\u{f481}';
+const EXECUTABLE_ICON: char = '\u{f489}';
+const FILE_ICON: char = '\u{f016}';
+
+// icons for well known file names, checked before the extension
+const NAME_ICONS: &[(&str, char)] = &[
+    (".gitattributes", '\u{f1d3}'),
+    (".gitignore", '\u{f1d3}'),
+    (".gitmodules", '\u{f1d3}'),
+    ("Cargo.lock", '\u{e7a8}'),
+    ("Cargo.toml", '\u{e7a8}'),
+    ("Dockerfile", '\u{f308}'),
+    ("LICENSE", '\u{f718}'),
+    ("Makefile", '\u{f489}'),
+];
+
+const EXTENSION_ICONS: &[(&[&str], char)] = &[
+    (
+        &["7z", "bz2", "gz", "rar", "tar", "tgz", "xz", "zip", "zst"],
+        '\u{f410}',
+    ),
+    (
+        &["aac", "flac", "m4a", "mp3", "ogg", "opus", "wav"],
+        '\u{f001}',
+    ),
+    (&["avi", "mkv", "mov", "mp4", "webm"], '\u{f03d}'),
+    (
+        &["bmp", "gif", "ico", "jpeg", "jpg", "png", "svg", "webp"],
+        '\u{f1c5}',
+    ),
+    (&["bash", "fish", "nu", "sh", "zsh"], '\u{f489}'),
+    (&["c", "h"], '\u{e61e}'),
+    (&["cc", "cpp", "cxx", "hpp"], '\u{e61d}'),
+    (&["conf", "ini", "toml", "yaml", "yml"], '\u{e615}'),
+    (&["css", "scss"], '\u{e749}'),
+    (&["go"], '\u{e626}'),
+    (&["htm", "html"], '\u{f13b}'),
+    (&["java", "jar"], '\u{e204}'),
+    (&["js", "mjs"], '\u{e74e}'),
+    (&["json"], '\u{e60b}'),
+    (&["lock"], '\u{f023}'),
+    (&["log", "txt"], '\u{f15c}'),
+    (&["md", "markdown"], '\u{f48a}'),
+    (&["pdf"], '\u{f1c1}'),
+    (&["py"], '\u{e606}'),
+    (&["rb"], '\u{e21e}'),
+    (&["rs"], '\u{e7a8}'),
+    (&["ts", "tsx"], '\u{e628}'),
+];
+
+/// nu-command's `grid`, with file icons that can be asked for per call.
+///
+/// nu-command only draws icons when both `--color` and `$env.config.use_grid_icons` are set, and
+/// draws them even where the terminal shows boxes instead.
+#[derive(Clone)]
+pub struct Griddle;
+
+impl Command for Griddle {
+    fn name(&self) -> &str {
+        "grid"
+    }
+
+    fn signature(&self) -> Signature {
+        nu_command::Griddle.signature().switch(
+            "icons",
+            "put a nerd font icon before each name, or an `ls -F` style marker after it if the terminal can't show them",
+            Some('i'),
+        )
+    }
+
+    fn usage(&self) -> &str {
+        nu_command::Griddle.usage()
+    }
+
+    fn extra_usage(&self) -> &str {
+        r#"grid was built to give a concise gridded layout for ls. however,
+it determines what to put in the grid by looking for a column named
+'name'. this works great for tables and records but for lists we
+need to do something different. such as with '[one two three] | grid'
+it creates a fake column called 'name' for these values so that it
+prints out the list properly.
+
+With --color, names are colored by LS_COLORS, and get icons too when
+$env.config.use_grid_icons is set. They need a nerd font, so the linux
+console, dumb terminals and non-UTF-8 locales get a marker instead:
+/ for directories, @ for symlinks and * for executables."#
+    }
+
+    fn search_terms(&self) -> Vec<&str> {
+        vec!["ls", "icons", "nerd font", "ls_colors"]
+    }
+
+    fn run(
+        &self,
+        engine_state: &EngineState,
+        stack: &mut Stack,
+        call: &Call,
+        input: PipelineData,
+    ) -> Result<PipelineData, ShellError> {
+        let config = get_config(engine_state, stack);
+        let color = call.has_flag("color") && config.use_ansi_coloring;
+        let icons = call.has_flag("icons") || color && config.use_grid_icons;
+        if !icons {
+            return nu_command::Griddle.run(engine_state, stack, call, input);
+        }
+
+        let ls_colors = match color {
+            true => {
+                let env_str = match stack.get_env_var(engine_state, "LS_COLORS") {
+                    Some(v) => Some(env_to_string("LS_COLORS", &v, engine_state, stack)?),
+                    None => None,
+                };
+                Some(get_ls_colors(env_str))
+            }
+            false => None,
+        };
+        let decorator = Decorator {
+            cwd: current_dir(engine_state, stack)?,
+            ls_colors,
+            nerd_font: supports_nerd_font(),
+        };
+
+        let input = match input {
+            PipelineData::Value(Value::List { vals, span }, metadata) => PipelineData::Value(
+                Value::List {
+                    vals: vals.into_iter().map(|val| decorator.row(val)).collect(),
+                    span,
+                },
+                metadata,
+            ),
+            PipelineData::ListStream(stream, metadata) => {
+                let ctrlc = stream.ctrlc.clone();
+                PipelineData::ListStream(
+                    ListStream::from_stream(stream.map(move |val| decorator.row(val)), ctrlc),
+                    metadata,
+                )
+            }
+            PipelineData::Value(val @ Value::Record { .. }, metadata) => {
+                PipelineData::Value(decorator.row(val), metadata)
+            }
+            input => input,
+        };
+
+        // the names are colored already, nu-command would color them again without the icons
+        let mut call = call.clone();
+        call.arguments
+            .retain(|arg| !matches!(arg, Argument::Named((name, ..)) if name.item == "color"));
+
+        nu_command::Griddle.run(engine_state, stack, &call, input)
+    }
+
+    fn examples(&self) -> Vec<Example<'_>> {
+        vec![
+            Example {
+                description: "Show the files in the current directory with icons and LS_COLORS",
+                example: "ls | grid --color --icons",
+                result: None,
+            },
+            Example {
+                description: "Render a simple list to a grid",
+                example: "[1 2 3 a b c] | grid",
+                result: Some(Value::test_string("1 │ 2 │ 3 │ a │ b │ c\n")),
+            },
+        ]
+    }
+}
+
+struct Decorator {
+    cwd: std::path::PathBuf,
+    ls_colors: Option<lscolors::LsColors>,
+    nerd_font: bool,
+}
+
+impl Decorator {
+    // decorate the `name` column of a record, or the value itself like nu-command's fake `name`
+    fn row(&self, row: Value) -> Value {
+        match row {
+            Value::Record {
+                cols,
+                mut vals,
+                span,
+            } => {
+                if let Some(index) = cols.iter().position(|col| col == "name") {
+                    let name = std::mem::take(&mut vals[index]);
+                    vals[index] = self.name(name);
+                }
+                Value::Record { cols, vals, span }
+            }
+            row => self.name(row),
+        }
+    }
+
+    fn name(&self, name: Value) -> Value {
+        let Value::String { val, span } = name else {
+            return name;
+        };
+
+        let plain = nu_utils::strip_ansi_unlikely(&val).into_owned();
+        let path = self.cwd.join(&plain);
+        let kind = FileKind::of(&path);
+
+        let style = self
+            .ls_colors
+            .as_ref()
+            .and_then(|ls_colors| ls_colors.style_for_path(&path))
+            .map(lscolors::Style::to_nu_ansi_term_style)
+            .unwrap_or_default();
+
+        let decorated = if self.nerd_font {
+            let icon = kind.icon(Path::new(&plain));
+            format!("{} {}", style.paint(String::from(icon)), style.paint(val))
+        } else {
+            format!("{}{}", style.paint(val), kind.marker())
+        };
+
+        Value::String {
+            val: decorated,
+            span,
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+enum FileKind {
+    Directory,
+    Symlink,
+    Executable,
+    File,
+}
+
+impl FileKind {
+    fn of(path: &Path) -> Self {
+        let Ok(metadata) = path.symlink_metadata() else {
+            return FileKind::File;
+        };
+
+        if metadata.is_symlink() {
+            FileKind::Symlink
+        } else if metadata.is_dir() {
+            FileKind::Directory
+        } else if is_executable(&metadata) {
+            FileKind::Executable
+        } else {
+            FileKind::File
+        }
+    }
+
+    fn icon(self, path: &Path) -> char {
+        match self {
+            FileKind::Directory => return DIRECTORY_ICON,
+            FileKind::Symlink => return SYMLINK_ICON,
+            FileKind::Executable | FileKind::File => {}
+        }
+
+        let file_name = path.file_name().and_then(|name| name.to_str());
+        if let Some((_, icon)) = NAME_ICONS.iter().find(|(name, _)| Some(*name) == file_name) {
+            return *icon;
+        }
+
+        let extension = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .map(str::to_lowercase);
+        let by_extension = EXTENSION_ICONS
+            .iter()
+            .find(|(extensions, _)| extensions.contains(&extension.as_deref().unwrap_or_default()));
+
+        match (by_extension, self) {
+            (Some((_, icon)), _) => *icon,
+            (None, FileKind::Executable) => EXECUTABLE_ICON,
+            (None, _) => FILE_ICON,
+        }
+    }
+
+    fn marker(self) -> &'static str {
+        match self {
+            FileKind::Directory => "/",
+            FileKind::Symlink => "@",
+            FileKind::Executable => "*",
+            FileKind::File => "",
+        }
+    }
+}
+
+#[cfg(unix)]
+fn is_executable(metadata: &std::fs::Metadata) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+
+    metadata.is_file() && metadata.permissions().mode() & 0o111 != 0
+}
+
+#[cfg(not(unix))]
+fn is_executable(_metadata: &std::fs::Metadata) -> bool {
+    false
+}
+
+// nerd font glyphs show up as boxes in the linux console and without a UTF-8 locale, and in the
+// legacy windows console that isn't Windows Terminal
+fn supports_nerd_font() -> bool {
+    let var = |name| std::env::var(name).ok().filter(|val| !val.is_empty());
+
+    if matches!(var("TERM").as_deref(), Some("linux" | "dumb")) {
+        return false;
+    }
+
+    if cfg!(windows) {
+        return var("WT_SESSION").is_some() || var("TERM_PROGRAM").is_some();
+    }
+
+    match var("LC_ALL")
+        .or_else(|| var("LC_CTYPE"))
+        .or_else(|| var("LANG"))
+    {
+        Some(locale) => {
+            let locale = locale.to_lowercase();
+            locale.contains("utf-8") || locale.contains("utf8")
+        }
+        None => false,
+    }
+}
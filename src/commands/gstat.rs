@@ -0,0 +1,238 @@
+use git2::{BranchType, Repository, RepositoryState, Status, StatusOptions};
+use nu_engine::{current_dir, CallExt};
+use nu_protocol::{
+    ast::Call,
+    engine::{Command, EngineState, Stack},
+    Category, Example, IntoPipelineData, PipelineData, ShellError, Signature, Span, Spanned,
+    SyntaxShape, Type, Value,
+};
+
+#[derive(Clone)]
+pub struct GStat;
+
+impl Command for GStat {
+    fn name(&self) -> &str {
+        "gstat"
+    }
+
+    fn signature(&self) -> Signature {
+        Signature::build("gstat")
+            .input_output_types(vec![(Type::Nothing, Type::Record(vec![]))])
+            .optional(
+                "path",
+                SyntaxShape::Filepath,
+                "a path inside the repository (defaults to the current directory)",
+            )
+            .category(Category::Custom("git".into()))
+    }
+
+    fn usage(&self) -> &str {
+        "Get the status of a git repository as structured data."
+    }
+
+    fn extra_usage(&self) -> &str {
+        "The files column has one row per changed path, with its staged and unstaged state."
+    }
+
+    fn search_terms(&self) -> Vec<&str> {
+        vec!["git", "status", "branch", "prompt"]
+    }
+
+    fn run(
+        &self,
+        engine_state: &EngineState,
+        stack: &mut Stack,
+        call: &Call,
+        _input: PipelineData,
+    ) -> Result<PipelineData, ShellError> {
+        let path: Option<Spanned<String>> = call.opt(engine_state, stack, 0)?;
+        let cwd = current_dir(engine_state, stack)?;
+
+        let (path, path_span) = match path {
+            Some(path) => (nu_path::expand_path_with(path.item, cwd), path.span),
+            None => (cwd, call.head),
+        };
+
+        let mut repo = Repository::discover(&path).map_err(|err| git_error(err, path_span))?;
+
+        Ok(repo_status(&mut repo, call.head)
+            .map_err(|err| git_error(err, call.head))?
+            .into_pipeline_data())
+    }
+
+    fn examples(&self) -> Vec<Example<'_>> {
+        vec![
+            Example {
+                description: "Show the status of the repository in the current directory",
+                example: "gstat",
+                result: None,
+            },
+            Example {
+                description: "List the files with unstaged changes",
+                example: "gstat | get files | where unstaged != null | get path",
+                result: None,
+            },
+        ]
+    }
+}
+
+fn repo_status(repo: &mut Repository, span: Span) -> Result<Value, git2::Error> {
+    let head = repo.head().ok();
+    let branch = match &head {
+        Some(head) if head.is_branch() => head.shorthand().map(String::from),
+        // a freshly initialized repository has no commits for HEAD to resolve to yet
+        None => repo
+            .find_reference("HEAD")
+            .ok()
+            .and_then(|head| head.symbolic_target().map(String::from))
+            .map(|target| target.trim_start_matches("refs/heads/").to_string()),
+        Some(_) => None,
+    };
+
+    let mut upstream = None;
+    let mut ahead_behind = None;
+    if let Some(name) = &branch {
+        if let Ok(local) = repo.find_branch(name, BranchType::Local) {
+            if let Ok(remote) = local.upstream() {
+                upstream = remote.name().ok().flatten().map(String::from);
+                if let (Some(local), Some(remote)) = (local.get().target(), remote.get().target()) {
+                    ahead_behind = repo.graph_ahead_behind(local, remote).ok();
+                }
+            }
+        }
+    }
+
+    let commit = head
+        .and_then(|head| head.target())
+        .map(|oid| oid.to_string());
+
+    let mut options = StatusOptions::new();
+    options
+        .include_untracked(true)
+        .recurse_untracked_dirs(true)
+        .renames_head_to_index(true);
+
+    let files = repo
+        .statuses(Some(&mut options))?
+        .iter()
+        .map(|entry| {
+            let status = entry.status();
+            Value::Record {
+                cols: vec!["path".into(), "staged".into(), "unstaged".into()],
+                vals: vec![
+                    Value::string(entry.path().unwrap_or_default(), span),
+                    optional_string(staged_state(status), span),
+                    optional_string(unstaged_state(status), span),
+                ],
+                span,
+            }
+        })
+        .collect();
+
+    let state = repo_state(repo.state());
+
+    let mut stashes = 0;
+    repo.stash_foreach(|_, _, _| {
+        stashes += 1;
+        true
+    })?;
+
+    let optional_int = |val: Option<usize>| match val {
+        Some(val) => Value::int(val as i64, span),
+        None => Value::nothing(span),
+    };
+
+    Ok(Value::Record {
+        cols: vec![
+            "branch".into(),
+            "commit".into(),
+            "upstream".into(),
+            "ahead".into(),
+            "behind".into(),
+            "state".into(),
+            "stashes".into(),
+            "files".into(),
+        ],
+        vals: vec![
+            optional_string(branch.as_deref(), span),
+            optional_string(commit.as_deref(), span),
+            optional_string(upstream.as_deref(), span),
+            optional_int(ahead_behind.map(|(ahead, _)| ahead)),
+            optional_int(ahead_behind.map(|(_, behind)| behind)),
+            Value::string(state, span),
+            Value::int(stashes, span),
+            Value::List { vals: files, span },
+        ],
+        span,
+    })
+}
+
+fn staged_state(status: Status) -> Option<&'static str> {
+    if status.is_conflicted() {
+        Some("conflicted")
+    } else if status.is_index_new() {
+        Some("added")
+    } else if status.is_index_modified() {
+        Some("modified")
+    } else if status.is_index_deleted() {
+        Some("deleted")
+    } else if status.is_index_renamed() {
+        Some("renamed")
+    } else if status.is_index_typechange() {
+        Some("typechange")
+    } else {
+        None
+    }
+}
+
+fn unstaged_state(status: Status) -> Option<&'static str> {
+    if status.is_conflicted() {
+        Some("conflicted")
+    } else if status.is_wt_new() {
+        Some("untracked")
+    } else if status.is_wt_modified() {
+        Some("modified")
+    } else if status.is_wt_deleted() {
+        Some("deleted")
+    } else if status.is_wt_renamed() {
+        Some("renamed")
+    } else if status.is_wt_typechange() {
+        Some("typechange")
+    } else {
+        None
+    }
+}
+
+fn repo_state(state: RepositoryState) -> &'static str {
+    match state {
+        RepositoryState::Clean => "clean",
+        RepositoryState::Merge => "merge",
+        RepositoryState::Revert => "revert",
+        RepositoryState::RevertSequence => "revert-sequence",
+        RepositoryState::CherryPick => "cherry-pick",
+        RepositoryState::CherryPickSequence => "cherry-pick-sequence",
+        RepositoryState::Bisect => "bisect",
+        RepositoryState::Rebase => "rebase",
+        RepositoryState::RebaseInteractive => "rebase-interactive",
+        RepositoryState::RebaseMerge => "rebase-merge",
+        RepositoryState::ApplyMailbox => "apply-mailbox",
+        RepositoryState::ApplyMailboxOrRebase => "apply-mailbox-or-rebase",
+    }
+}
+
+fn optional_string(val: Option<&str>, span: Span) -> Value {
+    match val {
+        Some(val) => Value::string(val, span),
+        None => Value::nothing(span),
+    }
+}
+
+fn git_error(err: git2::Error, span: Span) -> ShellError {
+    ShellError::GenericError(
+        "Could not read git repository".into(),
+        err.message().to_string(),
+        Some(span),
+        None,
+        vec![],
+    )
+}
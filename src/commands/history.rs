@@ -0,0 +1,262 @@
+use nu_engine::{env::current_dir, CallExt};
+use nu_protocol::{
+    ast::Call,
+    engine::{Command, EngineState, Stack},
+    Category, Example, HistoryFileFormat, IntoPipelineData, PipelineData, ShellError, Signature,
+    Spanned, SyntaxShape, Type, Value,
+};
+
+use crate::history::{export_history, history_path, import_history, read_repl_history};
+
+// replaces nu-cli's version, which reads nushell's history instead of the REPL's
+#[derive(Clone)]
+pub struct History;
+
+impl Command for History {
+    fn name(&self) -> &str {
+        "history"
+    }
+
+    fn signature(&self) -> Signature {
+        Signature::build("history")
+            .input_output_types(vec![(Type::Nothing, Type::Table(vec![]))])
+            .switch("clear", "delete the history", Some('c'))
+            .category(Category::Misc)
+    }
+
+    fn usage(&self) -> &str {
+        "Get the history of the REPL."
+    }
+
+    fn extra_usage(&self) -> &str {
+        r#"The entries are oldest first, with the command and its index. A sqlite history, see
+$env.config.history.file_format, also has when the command was entered and the directory it ran in."#
+    }
+
+    fn run(
+        &self,
+        engine_state: &EngineState,
+        _stack: &mut Stack,
+        call: &Call,
+        _input: PipelineData,
+    ) -> Result<PipelineData, ShellError> {
+        let head = call.head;
+
+        if call.has_flag("clear") {
+            let format = engine_state.config.history_file_format;
+            if let Some(path) = history_path(format).filter(|path| path.exists()) {
+                std::fs::remove_file(&path).map_err(|err| {
+                    ShellError::IOError(format!("could not delete {}: {err}", path.display()))
+                })?;
+            }
+            return Ok(PipelineData::empty());
+        }
+
+        let sqlite = matches!(
+            engine_state.config.history_file_format,
+            HistoryFileFormat::Sqlite
+        );
+        let entries = read_repl_history(engine_state)?;
+        let rows = entries.into_iter().enumerate().map(|(index, entry)| {
+            let mut cols = vec![];
+            let mut vals = vec![];
+            if sqlite {
+                cols.push("start_timestamp".into());
+                vals.push(match entry.start_timestamp {
+                    Some(time) => Value::date(time.into(), head),
+                    None => Value::nothing(head),
+                });
+                cols.push("cwd".into());
+                vals.push(match entry.cwd {
+                    Some(cwd) => Value::string(cwd, head),
+                    None => Value::nothing(head),
+                });
+            }
+            cols.extend(["command".into(), "index".into()]);
+            vals.extend([
+                Value::string(entry.command_line, head),
+                Value::int(index as i64, head),
+            ]);
+
+            Value::record(cols, vals, head)
+        });
+
+        Ok(Value::list(rows.collect(), head).into_pipeline_data())
+    }
+
+    fn examples(&self) -> Vec<Example<'_>> {
+        vec![
+            Example {
+                description: "Show the last 5 commands entered",
+                example: "history | last 5",
+                result: None,
+            },
+            Example {
+                description: "Find the commands that ran cargo",
+                example: "history | where command =~ cargo",
+                result: None,
+            },
+        ]
+    }
+}
+
+#[derive(Clone)]
+pub struct HistoryImport;
+
+impl Command for HistoryImport {
+    fn name(&self) -> &str {
+        "history import"
+    }
+
+    fn signature(&self) -> Signature {
+        Signature::build("history import")
+            .input_output_types(vec![(Type::Nothing, Type::Int)])
+            .required(
+                "file",
+                SyntaxShape::Filepath,
+                "the history file of bash, zsh or nushell",
+            )
+            .category(Category::Misc)
+    }
+
+    fn usage(&self) -> &str {
+        "Add the entries of another shell's history to the history of the REPL."
+    }
+
+    fn extra_usage(&self) -> &str {
+        r#"Plain text and sqlite history files are both read, the format is told from the
+file. Timestamps of bash and zsh are kept when the history is a sqlite one, see
+$env.config.history.file_format. Returns how many entries were added."#
+    }
+
+    fn search_terms(&self) -> Vec<&str> {
+        vec!["migrate", "bash_history", "zsh_history"]
+    }
+
+    fn run(
+        &self,
+        engine_state: &EngineState,
+        stack: &mut Stack,
+        call: &Call,
+        _input: PipelineData,
+    ) -> Result<PipelineData, ShellError> {
+        let file: Spanned<String> = call.req(engine_state, stack, 0)?;
+        let path = nu_path::expand_path_with(&file.item, current_dir(engine_state, stack)?);
+
+        if !path.is_file() {
+            return Err(ShellError::FileNotFound(file.span));
+        }
+        let count = import_history(engine_state, &path)?;
+
+        Ok(Value::int(count as i64, call.head).into_pipeline_data())
+    }
+
+    fn examples(&self) -> Vec<Example<'_>> {
+        vec![
+            Example {
+                description: "Bring along the history of bash",
+                example: "history import ~/.bash_history",
+                result: None,
+            },
+            Example {
+                description: "Bring along the history of nushell",
+                example: "history import ~/.config/nushell/history.sqlite3",
+                result: None,
+            },
+        ]
+    }
+}
+
+#[derive(Clone)]
+pub struct HistoryExport;
+
+impl Command for HistoryExport {
+    fn name(&self) -> &str {
+        "history export"
+    }
+
+    fn signature(&self) -> Signature {
+        Signature::build("history export")
+            .input_output_types(vec![(Type::Nothing, Type::Int)])
+            .required("file", SyntaxShape::Filepath, "the file to write")
+            .named(
+                "format",
+                SyntaxShape::String,
+                "plaintext or sqlite, the default is told from the file's extension",
+                None,
+            )
+            .switch("force", "replace the file if there is one", Some('f'))
+            .category(Category::Misc)
+    }
+
+    fn usage(&self) -> &str {
+        "Write the history of the REPL to a file nushell can use."
+    }
+
+    fn extra_usage(&self) -> &str {
+        r#"Files ending in .sqlite3, .sqlite or .db are written as sqlite history, others as
+plain text. Returns how many entries were written."#
+    }
+
+    fn search_terms(&self) -> Vec<&str> {
+        vec!["migrate", "backup"]
+    }
+
+    fn run(
+        &self,
+        engine_state: &EngineState,
+        stack: &mut Stack,
+        call: &Call,
+        _input: PipelineData,
+    ) -> Result<PipelineData, ShellError> {
+        let file: Spanned<String> = call.req(engine_state, stack, 0)?;
+        let format: Option<Spanned<String>> = call.get_flag(engine_state, stack, "format")?;
+        let path = nu_path::expand_path_with(&file.item, current_dir(engine_state, stack)?);
+
+        let format = match format {
+            Some(format) => match format.item.as_str() {
+                "plaintext" => HistoryFileFormat::PlainText,
+                "sqlite" => HistoryFileFormat::Sqlite,
+                _ => {
+                    return Err(ShellError::IncorrectValue {
+                        msg: "expected plaintext or sqlite".into(),
+                        val_span: format.span,
+                        call_span: call.head,
+                    })
+                }
+            },
+            None => match path.extension().and_then(|ext| ext.to_str()) {
+                Some("sqlite3" | "sqlite" | "db") => HistoryFileFormat::Sqlite,
+                _ => HistoryFileFormat::PlainText,
+            },
+        };
+
+        if path.exists() && !call.has_flag("force") {
+            return Err(ShellError::GenericError(
+                "Destination file already exists".into(),
+                format!("{} already exists", path.display()),
+                Some(file.span),
+                Some("use --force to replace it".into()),
+                vec![],
+            ));
+        }
+        let count = export_history(engine_state, &path, format)?;
+
+        Ok(Value::int(count as i64, call.head).into_pipeline_data())
+    }
+
+    fn examples(&self) -> Vec<Example<'_>> {
+        vec![
+            Example {
+                description: "Write the history in the format of nushell's sqlite history",
+                example: "history export nu_app-history.sqlite3",
+                result: None,
+            },
+            Example {
+                description: "Keep a plain text copy of the history",
+                example: "history export --format plaintext history-backup",
+                result: None,
+            },
+        ]
+    }
+}
@@ -0,0 +1,151 @@
+use std::sync::Arc;
+
+use nu_engine::eval_expression;
+use nu_protocol::{
+    ast::Call,
+    engine::{Command, EngineState, Stack},
+    FromValue, IntoPipelineData, PipelineData, ShellError, Signature, Span, Value,
+};
+
+type Body = dyn Fn(&HostFnArgs, Value) -> Result<Value, ShellError> + Send + Sync;
+
+/// A command that runs a closure of the application, see [`register_fn`](crate::register_fn).
+#[derive(Clone)]
+pub struct HostFn {
+    signature: Signature,
+    body: Arc<Body>,
+}
+
+impl HostFn {
+    /// A command with the name, usage and parameters of `signature`, that calls `body` with its
+    /// arguments and its input collected into a value.
+    pub fn new(
+        signature: Signature,
+        body: impl Fn(&HostFnArgs, Value) -> Result<Value, ShellError> + Send + Sync + 'static,
+    ) -> Self {
+        HostFn {
+            signature,
+            body: Arc::new(body),
+        }
+    }
+}
+
+impl Command for HostFn {
+    fn name(&self) -> &str {
+        &self.signature.name
+    }
+
+    fn signature(&self) -> Signature {
+        self.signature.clone()
+    }
+
+    fn usage(&self) -> &str {
+        &self.signature.usage
+    }
+
+    fn extra_usage(&self) -> &str {
+        &self.signature.extra_usage
+    }
+
+    fn search_terms(&self) -> Vec<&str> {
+        self.signature
+            .search_terms
+            .iter()
+            .map(String::as_str)
+            .collect()
+    }
+
+    fn run(
+        &self,
+        engine_state: &EngineState,
+        stack: &mut Stack,
+        call: &Call,
+        input: PipelineData,
+    ) -> Result<PipelineData, ShellError> {
+        let args = HostFnArgs::evaluate(engine_state, stack, call)?;
+        let input = input.into_value(call.head);
+
+        (self.body)(&args, input).map(IntoPipelineData::into_pipeline_data)
+    }
+}
+
+/// The arguments a [`HostFn`] was called with, evaluated.
+#[derive(Debug, Clone)]
+pub struct HostFnArgs {
+    /// The span of the command's name, for the errors of the closure.
+    pub head: Span,
+    positional: Vec<Value>,
+    named: Vec<(String, Option<Value>)>,
+}
+
+impl HostFnArgs {
+    fn evaluate(
+        engine_state: &EngineState,
+        stack: &mut Stack,
+        call: &Call,
+    ) -> Result<HostFnArgs, ShellError> {
+        let positional = call
+            .positional_iter()
+            .map(|expr| eval_expression(engine_state, stack, expr))
+            .collect::<Result<Vec<Value>, ShellError>>()?;
+
+        let mut named = Vec::with_capacity(call.named_len());
+        for (name, _, expr) in call.named_iter() {
+            let value = match expr {
+                None => None,
+                Some(expr) => Some(eval_expression(engine_state, stack, expr)?),
+            };
+
+            named.push((name.item.clone(), value))
+        }
+
+        Ok(HostFnArgs {
+            head: call.head,
+            positional,
+            named,
+        })
+    }
+
+    /// The positional argument at `pos`, which the signature requires.
+    pub fn req<T: FromValue>(&self, pos: usize) -> Result<T, ShellError> {
+        match self.positional.get(pos) {
+            Some(value) => T::from_value(value),
+            None if self.positional.is_empty() => {
+                Err(ShellError::AccessEmptyContent { span: self.head })
+            }
+            None => Err(ShellError::AccessBeyondEnd {
+                max_idx: self.positional.len() - 1,
+                span: self.head,
+            }),
+        }
+    }
+
+    /// The positional argument at `pos`, if the call has it.
+    pub fn opt<T: FromValue>(&self, pos: usize) -> Result<Option<T>, ShellError> {
+        self.positional.get(pos).map(T::from_value).transpose()
+    }
+
+    /// The positional arguments from `pos` on, the rest parameter's.
+    pub fn rest<T: FromValue>(&self, pos: usize) -> Result<Vec<T>, ShellError> {
+        self.positional
+            .iter()
+            .skip(pos)
+            .map(T::from_value)
+            .collect()
+    }
+
+    /// Whether the call has the flag `--<name>`, without the dashes.
+    pub fn has_flag(&self, name: &str) -> bool {
+        self.named.iter().any(|(flag, _)| flag == name)
+    }
+
+    /// The value of the flag `--<name>`, if the call has it.
+    pub fn get_flag<T: FromValue>(&self, name: &str) -> Result<Option<T>, ShellError> {
+        self.named
+            .iter()
+            .find(|(flag, _)| flag == name)
+            .and_then(|(_, value)| value.as_ref())
+            .map(T::from_value)
+            .transpose()
+    }
+}
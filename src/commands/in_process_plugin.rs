@@ -0,0 +1,113 @@
+use std::marker::PhantomData;
+
+use nu_engine::eval_expression;
+use nu_plugin::{EvaluatedCall, Plugin};
+use nu_protocol::{
+    ast::Call,
+    engine::{Command, EngineState, Stack},
+    IntoPipelineData, PipelineData, PluginSignature, ShellError, Signature, Value,
+};
+
+/// Runs a plugin's commands inside this process instead of spawning the plugin binary,
+/// so plugin crates can be compiled straight into the default context.
+pub struct InProcessPlugin<P> {
+    signature: PluginSignature,
+    plugin: PhantomData<fn() -> P>,
+}
+
+impl<P: Plugin + Default + 'static> InProcessPlugin<P> {
+    /// One command per signature the plugin declares.
+    pub fn commands() -> Vec<Box<dyn Command>> {
+        P::default()
+            .signature()
+            .into_iter()
+            .map(|signature| {
+                Box::new(InProcessPlugin::<P> {
+                    signature,
+                    plugin: PhantomData,
+                }) as Box<dyn Command>
+            })
+            .collect()
+    }
+}
+
+impl<P> Clone for InProcessPlugin<P> {
+    fn clone(&self) -> Self {
+        InProcessPlugin {
+            signature: self.signature.clone(),
+            plugin: PhantomData,
+        }
+    }
+}
+
+impl<P: Plugin + Default + 'static> Command for InProcessPlugin<P> {
+    fn name(&self) -> &str {
+        &self.signature.sig.name
+    }
+
+    fn signature(&self) -> Signature {
+        self.signature.sig.clone()
+    }
+
+    fn usage(&self) -> &str {
+        &self.signature.sig.usage
+    }
+
+    fn extra_usage(&self) -> &str {
+        &self.signature.sig.extra_usage
+    }
+
+    fn search_terms(&self) -> Vec<&str> {
+        self.signature
+            .sig
+            .search_terms
+            .iter()
+            .map(String::as_str)
+            .collect()
+    }
+
+    fn run(
+        &self,
+        engine_state: &EngineState,
+        stack: &mut Stack,
+        call: &Call,
+        input: PipelineData,
+    ) -> Result<PipelineData, ShellError> {
+        let evaluated_call = evaluate_call(engine_state, stack, call)?;
+        let input = input.into_value(call.head);
+
+        // plugins have no state worth keeping between calls, so start from a fresh one each time
+        let mut plugin = P::default();
+        match plugin.run(&self.signature.sig.name, &evaluated_call, &input) {
+            Ok(value) => Ok(value.into_pipeline_data()),
+            Err(err) => Err(err.into()),
+        }
+    }
+}
+
+fn evaluate_call(
+    engine_state: &EngineState,
+    stack: &mut Stack,
+    call: &Call,
+) -> Result<EvaluatedCall, ShellError> {
+    let positional = call
+        .positional_iter()
+        .map(|expr| eval_expression(engine_state, stack, expr))
+        .collect::<Result<Vec<Value>, ShellError>>()?;
+
+    let mut named = Vec::with_capacity(call.named_len());
+    for (string, _, expr) in call.named_iter() {
+        let value = match expr {
+            None => None,
+            Some(expr) => Some(eval_expression(engine_state, stack, expr)?),
+        };
+
+        named.push((string.clone(), value))
+    }
+
+    Ok(EvaluatedCall {
+        head: call.head,
+        positional,
+        named,
+    })
+}
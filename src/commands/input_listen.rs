@@ -0,0 +1,350 @@
+use std::io::stdout;
+
+use crossterm::{
+    event::{
+        DisableBracketedPaste, DisableFocusChange, DisableMouseCapture, EnableBracketedPaste,
+        EnableFocusChange, EnableMouseCapture, Event, KeyCode, KeyEvent, KeyEventKind,
+        KeyModifiers, MouseButton, MouseEvent, MouseEventKind,
+    },
+    terminal,
+};
+use nu_engine::CallExt;
+use nu_protocol::{
+    ast::Call,
+    engine::{Command, EngineState, Stack},
+    Category, Example, IntoPipelineData, PipelineData, ShellError, Signature, Span, Spanned,
+    SyntaxShape, Type, Value,
+};
+
+const EVENT_TYPES: &[&str] = &["focus", "key", "mouse", "paste", "resize"];
+
+#[derive(Clone)]
+pub struct InputListen;
+
+impl Command for InputListen {
+    fn name(&self) -> &str {
+        "input listen"
+    }
+
+    fn signature(&self) -> Signature {
+        Signature::build("input listen")
+            .input_output_types(vec![(Type::Nothing, Type::Record(vec![]))])
+            .named(
+                "types",
+                SyntaxShape::List(Box::new(SyntaxShape::String)),
+                "event types to listen for: focus, key, mouse, paste or resize (defaults to all but mouse)",
+                Some('t'),
+            )
+            .switch(
+                "raw",
+                "add the numeric key code and modifier flags to the record",
+                Some('r'),
+            )
+            .category(Category::Platform)
+    }
+
+    fn usage(&self) -> &str {
+        "Wait for a terminal event and return it as a record."
+    }
+
+    fn extra_usage(&self) -> &str {
+        r#"Every record has a type column telling the events apart:
+    { type: focus, event: (gained|lost) }
+    { type: key, key_type: (f|char|media|modifier|other), code: <string>, modifiers: [<modifier> ...] }
+    { type: mouse, kind: (down|up|drag|moved|scroll_up|scroll_down), button: (left|right|middle|null), col: <int>, row: <int>, modifiers: [<modifier> ...] }
+    { type: paste, content: <string> }
+    { type: resize, col: <int>, row: <int> }
+Modifiers are shift, control, alt, super, hyper and meta.
+Mouse events have to be asked for, because capturing the mouse stops the terminal from selecting text while listening."#
+    }
+
+    fn search_terms(&self) -> Vec<&str> {
+        vec![
+            "prompt",
+            "interactive",
+            "keycode",
+            "mouse",
+            "click",
+            "scroll",
+            "tui",
+        ]
+    }
+
+    fn run(
+        &self,
+        engine_state: &EngineState,
+        stack: &mut Stack,
+        call: &Call,
+        _input: PipelineData,
+    ) -> Result<PipelineData, ShellError> {
+        let span = call.head;
+        let types: Option<Vec<Spanned<String>>> = call.get_flag(engine_state, stack, "types")?;
+        let raw = call.has_flag("raw");
+
+        let filter = match types {
+            Some(types) => EventFilter::from_types(types, span)?,
+            None => EventFilter {
+                mouse: false,
+                ..EventFilter::all()
+            },
+        };
+
+        let capture = Capture::enable(&filter)?;
+        let event = loop {
+            let event = crossterm::event::read().map_err(|err| {
+                ShellError::GenericError(
+                    "Error with user input".into(),
+                    err.to_string(),
+                    Some(span),
+                    None,
+                    vec![],
+                )
+            })?;
+
+            if let Some(event) = event_value(&event, &filter, raw, span) {
+                break event;
+            }
+        };
+        drop(capture);
+
+        Ok(event.into_pipeline_data())
+    }
+
+    fn examples(&self) -> Vec<Example<'_>> {
+        vec![
+            Example {
+                description: "Wait for a key press and show which key it was",
+                example: "input listen --types [key]",
+                result: None,
+            },
+            Example {
+                description: "Report where the mouse was clicked",
+                example:
+                    "input listen --types [mouse] | where kind == down | select button col row",
+                result: None,
+            },
+        ]
+    }
+}
+
+struct EventFilter {
+    focus: bool,
+    key: bool,
+    mouse: bool,
+    paste: bool,
+    resize: bool,
+}
+
+impl EventFilter {
+    fn all() -> Self {
+        EventFilter {
+            focus: true,
+            key: true,
+            mouse: true,
+            paste: true,
+            resize: true,
+        }
+    }
+
+    fn from_types(types: Vec<Spanned<String>>, call_span: Span) -> Result<Self, ShellError> {
+        let mut filter = EventFilter {
+            focus: false,
+            key: false,
+            mouse: false,
+            paste: false,
+            resize: false,
+        };
+
+        for ty in types {
+            match ty.item.as_str() {
+                "focus" => filter.focus = true,
+                "key" => filter.key = true,
+                "mouse" => filter.mouse = true,
+                "paste" => filter.paste = true,
+                "resize" => filter.resize = true,
+                _ => {
+                    return Err(ShellError::IncorrectValue {
+                        msg: format!("event types are {}", EVENT_TYPES.join(", ")),
+                        val_span: ty.span,
+                        call_span,
+                    })
+                }
+            }
+        }
+
+        Ok(filter)
+    }
+}
+
+// raw mode and the event reporting turned on for listening, undone when dropped
+struct Capture {
+    mouse: bool,
+    paste: bool,
+    focus: bool,
+}
+
+impl Capture {
+    fn enable(filter: &EventFilter) -> Result<Self, ShellError> {
+        terminal::enable_raw_mode()?;
+        let capture = Capture {
+            mouse: filter.mouse,
+            paste: filter.paste,
+            focus: filter.focus,
+        };
+
+        if capture.mouse {
+            crossterm::execute!(stdout(), EnableMouseCapture)?;
+        }
+        if capture.paste {
+            crossterm::execute!(stdout(), EnableBracketedPaste)?;
+        }
+        if capture.focus {
+            crossterm::execute!(stdout(), EnableFocusChange)?;
+        }
+
+        Ok(capture)
+    }
+}
+
+impl Drop for Capture {
+    fn drop(&mut self) {
+        if self.mouse {
+            let _ = crossterm::execute!(stdout(), DisableMouseCapture);
+        }
+        if self.paste {
+            let _ = crossterm::execute!(stdout(), DisableBracketedPaste);
+        }
+        if self.focus {
+            let _ = crossterm::execute!(stdout(), DisableFocusChange);
+        }
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+fn event_value(event: &Event, filter: &EventFilter, raw: bool, span: Span) -> Option<Value> {
+    let (cols, vals): (Vec<&str>, Vec<Value>) = match event {
+        Event::FocusGained | Event::FocusLost if filter.focus => {
+            let gained = matches!(event, Event::FocusGained);
+            (
+                vec!["type", "event"],
+                vec![
+                    Value::string("focus", span),
+                    Value::string(if gained { "gained" } else { "lost" }, span),
+                ],
+            )
+        }
+        Event::Key(event) if filter.key => return key_value(event, raw, span),
+        Event::Mouse(event) if filter.mouse => return Some(mouse_value(event, raw, span)),
+        Event::Paste(content) if filter.paste => (
+            vec!["type", "content"],
+            vec![Value::string("paste", span), Value::string(content, span)],
+        ),
+        Event::Resize(cols, rows) if filter.resize => (
+            vec!["type", "col", "row"],
+            vec![
+                Value::string("resize", span),
+                Value::int(*cols as i64, span),
+                Value::int(*rows as i64, span),
+            ],
+        ),
+        _ => return None,
+    };
+
+    Some(record(cols, vals, span))
+}
+
+fn key_value(event: &KeyEvent, raw: bool, span: Span) -> Option<Value> {
+    // only windows and the kitty keyboard protocol report releases and repeats reliably
+    if matches!(event.kind, KeyEventKind::Release | KeyEventKind::Repeat) {
+        return None;
+    }
+
+    let (key_type, code) = match event.code {
+        KeyCode::F(n) => ("f", n.to_string()),
+        KeyCode::Char(c) => ("char", c.to_string()),
+        KeyCode::Media(media) => ("media", format!("{media:?}").to_lowercase()),
+        KeyCode::Modifier(modifier) => ("modifier", format!("{modifier:?}").to_lowercase()),
+        code => ("other", format!("{code:?}").to_lowercase()),
+    };
+
+    let mut cols = vec!["type", "key_type", "code", "modifiers"];
+    let mut vals = vec![
+        Value::string("key", span),
+        Value::string(key_type, span),
+        Value::string(code, span),
+        modifiers_value(event.modifiers, span),
+    ];
+
+    if raw {
+        if let KeyCode::Char(c) = event.code {
+            cols.push("raw_code");
+            vals.push(Value::int(c as i64, span));
+        }
+        cols.push("raw_modifiers");
+        vals.push(Value::int(event.modifiers.bits() as i64, span));
+    }
+
+    Some(record(cols, vals, span))
+}
+
+fn mouse_value(event: &MouseEvent, raw: bool, span: Span) -> Value {
+    let (kind, button) = match event.kind {
+        MouseEventKind::Down(button) => ("down", Some(button)),
+        MouseEventKind::Up(button) => ("up", Some(button)),
+        MouseEventKind::Drag(button) => ("drag", Some(button)),
+        MouseEventKind::Moved => ("moved", None),
+        MouseEventKind::ScrollDown => ("scroll_down", None),
+        MouseEventKind::ScrollUp => ("scroll_up", None),
+    };
+    let button = match button {
+        Some(MouseButton::Left) => Value::string("left", span),
+        Some(MouseButton::Right) => Value::string("right", span),
+        Some(MouseButton::Middle) => Value::string("middle", span),
+        None => Value::nothing(span),
+    };
+
+    let mut cols = vec!["type", "kind", "button", "col", "row", "modifiers"];
+    let mut vals = vec![
+        Value::string("mouse", span),
+        Value::string(kind, span),
+        button,
+        Value::int(event.column as i64, span),
+        Value::int(event.row as i64, span),
+        modifiers_value(event.modifiers, span),
+    ];
+
+    if raw {
+        cols.push("raw_modifiers");
+        vals.push(Value::int(event.modifiers.bits() as i64, span));
+    }
+
+    record(cols, vals, span)
+}
+
+fn modifiers_value(modifiers: KeyModifiers, span: Span) -> Value {
+    let names = [
+        (KeyModifiers::SHIFT, "shift"),
+        (KeyModifiers::CONTROL, "control"),
+        (KeyModifiers::ALT, "alt"),
+        (KeyModifiers::SUPER, "super"),
+        (KeyModifiers::HYPER, "hyper"),
+        (KeyModifiers::META, "meta"),
+    ];
+
+    Value::List {
+        vals: names
+            .iter()
+            .filter(|(modifier, _)| modifiers.contains(*modifier))
+            .map(|(_, name)| Value::string(*name, span))
+            .collect(),
+        span,
+    }
+}
+
+fn record(cols: Vec<&str>, vals: Vec<Value>, span: Span) -> Value {
+    Value::Record {
+        cols: cols.into_iter().map(String::from).collect(),
+        vals,
+        span,
+    }
+}
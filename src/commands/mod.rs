@@ -0,0 +1,45 @@
+#[cfg(feature = "dns")]
+mod dns;
+mod from_git_log;
+mod griddle;
+#[cfg(feature = "git")]
+mod gstat;
+mod history;
+mod host_fn;
+#[cfg(feature = "nu-plugin")]
+mod in_process_plugin;
+mod input_listen;
+mod overlay_reload;
+mod ping;
+#[cfg(feature = "plugin")]
+mod plugin;
+#[cfg(feature = "pty")]
+mod pty;
+mod removed;
+#[cfg(feature = "battery")]
+mod sys_battery;
+mod sys_temp;
+mod table;
+
+#[cfg(feature = "dns")]
+pub use dns::{Dns, DnsQuery};
+pub use from_git_log::FromGitLog;
+pub use griddle::Griddle;
+#[cfg(feature = "git")]
+pub use gstat::GStat;
+pub use history::{History, HistoryExport, HistoryImport};
+pub use host_fn::{HostFn, HostFnArgs};
+#[cfg(feature = "nu-plugin")]
+pub use in_process_plugin::InProcessPlugin;
+pub use input_listen::InputListen;
+pub use overlay_reload::OverlayReload;
+pub use ping::Ping;
+#[cfg(feature = "plugin")]
+pub use plugin::{Plugin, PluginAdd, PluginRm, PluginStop};
+#[cfg(feature = "pty")]
+pub use pty::Pty;
+pub use removed::{DateFormat, LetEnv};
+#[cfg(feature = "battery")]
+pub use sys_battery::SysBattery;
+pub use sys_temp::SysTemp;
+pub use table::Table;
@@ -0,0 +1,64 @@
+use nu_engine::{env::current_dir, CallExt};
+use nu_protocol::{
+    ast::Call,
+    engine::{Command, EngineState, Stack},
+    Category, Example, PipelineData, ShellError, Signature, Spanned, SyntaxShape, Type,
+};
+
+use crate::reload::queue_reload;
+
+#[derive(Clone)]
+pub struct OverlayReload;
+
+impl Command for OverlayReload {
+    fn name(&self) -> &str {
+        "overlay reload"
+    }
+
+    fn signature(&self) -> Signature {
+        Signature::build("overlay reload")
+            .input_output_types(vec![(Type::Nothing, Type::Nothing)])
+            .required("file", SyntaxShape::Filepath, "the module file to reload")
+            .category(Category::Core)
+    }
+
+    fn usage(&self) -> &str {
+        "Parse a module file again and replace the definitions of its overlay."
+    }
+
+    fn extra_usage(&self) -> &str {
+        r#"Like `overlay use --reload`, but the path doesn't need to be known when the line is
+parsed, and the module is reloaded once the line has finished. Commands the
+file no longer defines are gone afterwards."#
+    }
+
+    fn search_terms(&self) -> Vec<&str> {
+        vec!["hot reload", "module", "refresh"]
+    }
+
+    fn run(
+        &self,
+        engine_state: &EngineState,
+        stack: &mut Stack,
+        call: &Call,
+        _input: PipelineData,
+    ) -> Result<PipelineData, ShellError> {
+        let file: Spanned<String> = call.req(engine_state, stack, 0)?;
+        let path = nu_path::expand_path_with(&file.item, current_dir(engine_state, stack)?);
+
+        if !path.is_file() {
+            return Err(ShellError::FileNotFound(file.span));
+        }
+        queue_reload(path);
+
+        Ok(PipelineData::empty())
+    }
+
+    fn examples(&self) -> Vec<Example<'_>> {
+        vec![Example {
+            description: "Pick up the changes to a module while working on it",
+            example: "overlay reload spam.nu",
+            result: None,
+        }]
+    }
+}
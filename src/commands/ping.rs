@@ -0,0 +1,180 @@
+use std::{
+    net::{SocketAddr, TcpStream, ToSocketAddrs},
+    time::{Duration, Instant},
+};
+
+use nu_engine::CallExt;
+use nu_protocol::{
+    ast::Call,
+    engine::{Command, EngineState, Stack},
+    Category, Example, IntoInterruptiblePipelineData, PipelineData, ShellError, Signature, Span,
+    Spanned, SyntaxShape, Type, Value,
+};
+
+#[derive(Clone)]
+pub struct Ping;
+
+impl Command for Ping {
+    fn name(&self) -> &str {
+        "ping"
+    }
+
+    fn signature(&self) -> Signature {
+        Signature::build("ping")
+            .input_output_types(vec![(Type::Nothing, Type::Table(vec![]))])
+            .required(
+                "host",
+                SyntaxShape::String,
+                "the host name or IP address to probe",
+            )
+            .named(
+                "port",
+                SyntaxShape::Int,
+                "the TCP port to connect to (defaults to 80)",
+                Some('p'),
+            )
+            .named(
+                "count",
+                SyntaxShape::Int,
+                "the number of probes to send (defaults to 1)",
+                Some('c'),
+            )
+            .named(
+                "timeout",
+                SyntaxShape::Duration,
+                "how long to wait for each connection (defaults to 1sec)",
+                Some('t'),
+            )
+            .category(Category::Network)
+    }
+
+    fn usage(&self) -> &str {
+        "Check whether a host is reachable by timing TCP connections to it."
+    }
+
+    fn extra_usage(&self) -> &str {
+        "This doesn't send ICMP packets, so it works without elevated privileges but needs an open (or actively refused) port."
+    }
+
+    fn search_terms(&self) -> Vec<&str> {
+        vec!["network", "reachable", "latency", "health", "tcp"]
+    }
+
+    fn run(
+        &self,
+        engine_state: &EngineState,
+        stack: &mut Stack,
+        call: &Call,
+        _input: PipelineData,
+    ) -> Result<PipelineData, ShellError> {
+        let host: Spanned<String> = call.req(engine_state, stack, 0)?;
+        let port: Option<Spanned<i64>> = call.get_flag(engine_state, stack, "port")?;
+        let count: Option<Spanned<i64>> = call.get_flag(engine_state, stack, "count")?;
+        let timeout: Option<Spanned<i64>> = call.get_flag(engine_state, stack, "timeout")?;
+
+        let port = match port {
+            Some(port) => u16::try_from(port.item).map_err(|_| ShellError::IncorrectValue {
+                msg: "port must be between 0 and 65535".into(),
+                val_span: port.span,
+                call_span: call.head,
+            })?,
+            None => 80,
+        };
+        let count = match count {
+            Some(count) if count.item < 1 => {
+                return Err(ShellError::IncorrectValue {
+                    msg: "count must be at least 1".into(),
+                    val_span: count.span,
+                    call_span: call.head,
+                })
+            }
+            Some(count) => count.item as u64,
+            None => 1,
+        };
+        let timeout = match timeout {
+            Some(timeout) if timeout.item <= 0 => {
+                return Err(ShellError::IncorrectValue {
+                    msg: "timeout must be positive".into(),
+                    val_span: timeout.span,
+                    call_span: call.head,
+                })
+            }
+            Some(timeout) => Duration::from_nanos(timeout.item as u64),
+            None => Duration::from_secs(1),
+        };
+
+        let address = (host.item.as_str(), port)
+            .to_socket_addrs()
+            .ok()
+            .and_then(|mut addrs| addrs.next())
+            .ok_or_else(|| {
+                ShellError::GenericError(
+                    "Could not resolve host".into(),
+                    format!("no address found for '{}'", host.item),
+                    Some(host.span),
+                    None,
+                    vec![],
+                )
+            })?;
+
+        let span = call.head;
+        Ok((1..=count)
+            .map(move |seq| probe(&host.item, address, seq, timeout, span))
+            .into_pipeline_data(engine_state.ctrlc.clone()))
+    }
+
+    fn examples(&self) -> Vec<Example<'_>> {
+        vec![
+            Example {
+                description: "Check whether a web server is reachable",
+                example: "ping nushell.sh --port 443",
+                result: None,
+            },
+            Example {
+                description: "Average the latency of a few probes",
+                example: "ping 10.0.0.1 -p 22 -c 5 | where reachable | get latency | math avg",
+                result: None,
+            },
+        ]
+    }
+}
+
+fn probe(host: &str, address: SocketAddr, seq: u64, timeout: Duration, span: Span) -> Value {
+    let start = Instant::now();
+    let result = TcpStream::connect_timeout(&address, timeout);
+    let elapsed = start.elapsed();
+
+    let (reachable, latency, error) = match result {
+        Ok(_) => (true, Value::duration(elapsed.as_nanos() as i64, span), None),
+        // the host answered, even though nothing listens on the port
+        Err(err) if err.kind() == std::io::ErrorKind::ConnectionRefused => {
+            (true, Value::duration(elapsed.as_nanos() as i64, span), None)
+        }
+        Err(err) => (false, Value::nothing(span), Some(err.to_string())),
+    };
+
+    Value::Record {
+        cols: vec![
+            "host".into(),
+            "address".into(),
+            "port".into(),
+            "seq".into(),
+            "reachable".into(),
+            "latency".into(),
+            "error".into(),
+        ],
+        vals: vec![
+            Value::string(host, span),
+            Value::string(address.ip().to_string(), span),
+            Value::int(address.port() as i64, span),
+            Value::int(seq as i64, span),
+            Value::bool(reachable, span),
+            latency,
+            match error {
+                Some(error) => Value::string(error, span),
+                None => Value::nothing(span),
+            },
+        ],
+        span,
+    }
+}
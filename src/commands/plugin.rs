@@ -0,0 +1,288 @@
+use nu_engine::{env::current_dir, get_full_help, CallExt};
+use nu_protocol::{
+    ast::Call,
+    engine::{Command, EngineState, Stack},
+    Category, Example, IntoPipelineData, PipelineData, ShellError, Signature, Spanned, SyntaxShape,
+    Type, Value,
+};
+
+use crate::plugins::{queue_add_plugin, queue_remove_plugin, stop_plugin};
+
+#[derive(Clone)]
+pub struct Plugin;
+
+impl Command for Plugin {
+    fn name(&self) -> &str {
+        "plugin"
+    }
+
+    fn signature(&self) -> Signature {
+        Signature::build("plugin")
+            .input_output_types(vec![(Type::Nothing, Type::String)])
+            .category(Category::Core)
+    }
+
+    fn usage(&self) -> &str {
+        "Commands for adding, removing and stopping plugins while the shell runs."
+    }
+
+    fn extra_usage(&self) -> &str {
+        "You must use one of the following subcommands. Using this command as-is will only produce this help message."
+    }
+
+    fn search_terms(&self) -> Vec<&str> {
+        vec!["register", "registry"]
+    }
+
+    fn run(
+        &self,
+        engine_state: &EngineState,
+        stack: &mut Stack,
+        call: &Call,
+        _input: PipelineData,
+    ) -> Result<PipelineData, ShellError> {
+        Ok(Value::String {
+            val: get_full_help(
+                &Plugin.signature(),
+                &Plugin.examples(),
+                engine_state,
+                stack,
+                self.is_parser_keyword(),
+            ),
+            span: call.head,
+        }
+        .into_pipeline_data())
+    }
+}
+
+#[derive(Clone)]
+pub struct PluginAdd;
+
+impl Command for PluginAdd {
+    fn name(&self) -> &str {
+        "plugin add"
+    }
+
+    fn signature(&self) -> Signature {
+        Signature::build("plugin add")
+            .input_output_types(vec![(Type::Nothing, Type::List(Box::new(Type::String)))])
+            .required(
+                "filename",
+                SyntaxShape::Filepath,
+                "path of the plugin's executable",
+            )
+            .named(
+                "shell",
+                SyntaxShape::Filepath,
+                "the shell or interpreter that runs the plugin, like python",
+                Some('s'),
+            )
+            .category(Category::Core)
+    }
+
+    fn usage(&self) -> &str {
+        "Add a plugin's commands, and keep them in the plugin registry for the next sessions."
+    }
+
+    fn extra_usage(&self) -> &str {
+        r#"Unlike `register`, the path can be computed while the shell runs. The plugin's
+commands can be used once the source that adds it has finished, from the next
+line in the REPL on, but not further down the same script.
+
+The plugin picks json or msgpack when it starts, msgpack is quicker for large
+values. Either way it has to be built for the plugin protocol of nu 0.84."#
+    }
+
+    fn run(
+        &self,
+        engine_state: &EngineState,
+        stack: &mut Stack,
+        call: &Call,
+        _input: PipelineData,
+    ) -> Result<PipelineData, ShellError> {
+        let cwd = current_dir(engine_state, stack)?;
+        let filename: Spanned<String> = call.req(engine_state, stack, 0)?;
+        let shell: Option<Spanned<String>> = call.get_flag(engine_state, stack, "shell")?;
+
+        let filename = nu_path::expand_path_with(&filename.item, &cwd);
+        let shell = shell.map(|shell| nu_path::expand_path_with(&shell.item, &cwd));
+
+        let names = queue_add_plugin(engine_state, stack, &filename, shell.as_deref())?;
+        Ok(Value::List {
+            vals: names
+                .into_iter()
+                .map(|name| Value::string(name, call.head))
+                .collect(),
+            span: call.head,
+        }
+        .into_pipeline_data())
+    }
+
+    fn examples(&self) -> Vec<Example<'_>> {
+        vec![
+            Example {
+                description: "Add the gstat plugin",
+                example: "plugin add ~/.cargo/bin/nu_plugin_gstat",
+                result: None,
+            },
+            Example {
+                description: "Add a plugin written in python",
+                example: "plugin add --shell python3 ~/plugins/nu_plugin_len.py",
+                result: None,
+            },
+        ]
+    }
+}
+
+#[derive(Clone)]
+pub struct PluginRm;
+
+impl Command for PluginRm {
+    fn name(&self) -> &str {
+        "plugin rm"
+    }
+
+    fn signature(&self) -> Signature {
+        Signature::build("plugin rm")
+            .input_output_types(vec![(Type::Nothing, Type::List(Box::new(Type::String)))])
+            .required(
+                "plugin",
+                SyntaxShape::String,
+                "the plugin's path, or its file name with or without nu_plugin_",
+            )
+            .category(Category::Core)
+    }
+
+    fn usage(&self) -> &str {
+        "Remove a plugin's commands, here and from the plugin registry."
+    }
+
+    fn extra_usage(&self) -> &str {
+        "Like with `plugin add`, the commands are gone once the source that removes them has finished."
+    }
+
+    fn run(
+        &self,
+        engine_state: &EngineState,
+        stack: &mut Stack,
+        call: &Call,
+        _input: PipelineData,
+    ) -> Result<PipelineData, ShellError> {
+        let plugin: Spanned<String> = call.req(engine_state, stack, 0)?;
+
+        // a path names the plugin file, anything else a plugin's name
+        let plugin_path =
+            nu_path::expand_path_with(&plugin.item, current_dir(engine_state, stack)?);
+        let plugin_path = plugin_path.canonicalize().unwrap_or(plugin_path);
+        let names = match plugin.item.contains(std::path::is_separator) {
+            true => queue_remove_plugin(engine_state, &plugin_path.to_string_lossy()),
+            false => queue_remove_plugin(engine_state, &plugin.item),
+        }
+        .map_err(|err| match err {
+            ShellError::GenericError(msg, label, _, help, inner) => {
+                ShellError::GenericError(msg, label, Some(plugin.span), help, inner)
+            }
+            err => err,
+        })?;
+
+        Ok(Value::List {
+            vals: names
+                .into_iter()
+                .map(|name| Value::string(name, call.head))
+                .collect(),
+            span: call.head,
+        }
+        .into_pipeline_data())
+    }
+
+    fn examples(&self) -> Vec<Example<'_>> {
+        vec![Example {
+            description: "Remove the gstat plugin",
+            example: "plugin rm gstat",
+            result: None,
+        }]
+    }
+}
+
+#[derive(Clone)]
+pub struct PluginStop;
+
+impl Command for PluginStop {
+    fn name(&self) -> &str {
+        "plugin stop"
+    }
+
+    fn signature(&self) -> Signature {
+        Signature::build("plugin stop")
+            .input_output_types(vec![(Type::Nothing, Type::Table(vec![]))])
+            .required(
+                "plugin",
+                SyntaxShape::String,
+                "the plugin's path, or its file name with or without nu_plugin_",
+            )
+            .category(Category::Core)
+    }
+
+    fn usage(&self) -> &str {
+        "Stop the running processes of a plugin."
+    }
+
+    fn extra_usage(&self) -> &str {
+        r#"A plugin gets a process of its own for each call, which exits once it has answered.
+This stops the ones that hang, e.g. from another closure of `par-each`, and the
+calls they were answering fail. Lists the processes that were stopped."#
+    }
+
+    fn run(
+        &self,
+        engine_state: &EngineState,
+        stack: &mut Stack,
+        call: &Call,
+        _input: PipelineData,
+    ) -> Result<PipelineData, ShellError> {
+        let plugin: Spanned<String> = call.req(engine_state, stack, 0)?;
+
+        let plugin = match plugin.item.contains(std::path::is_separator) {
+            true => {
+                let path =
+                    nu_path::expand_path_with(&plugin.item, current_dir(engine_state, stack)?);
+                path.canonicalize()
+                    .unwrap_or(path)
+                    .to_string_lossy()
+                    .into_owned()
+            }
+            false => plugin.item,
+        };
+
+        let span = call.head;
+        let stopped = stop_plugin(&plugin)
+            .into_iter()
+            .map(|process| Value::Record {
+                cols: vec!["pid".into(), "plugin".into(), "running".into()],
+                vals: vec![
+                    Value::int(process.pid.into(), span),
+                    Value::string(process.filename.to_string_lossy(), span),
+                    Value::Duration {
+                        val: process.running.as_nanos() as i64,
+                        span,
+                    },
+                ],
+                span,
+            })
+            .collect();
+
+        Ok(Value::List {
+            vals: stopped,
+            span,
+        }
+        .into_pipeline_data())
+    }
+
+    fn examples(&self) -> Vec<Example<'_>> {
+        vec![Example {
+            description: "Stop the gstat plugin if it hangs",
+            example: "plugin stop gstat",
+            result: None,
+        }]
+    }
+}
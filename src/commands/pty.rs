@@ -0,0 +1,537 @@
+use std::{
+    collections::HashMap,
+    io::{IsTerminal, Read, Write},
+    path::PathBuf,
+    process::{self, Stdio},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    thread,
+    time::Duration,
+};
+
+use nu_engine::{current_dir, env_to_strings, CallExt};
+use nu_protocol::{
+    ast::Call,
+    engine::{Command, EngineState, Stack},
+    Category, Example, ListStream, PipelineData, RawStream, ShellError, Signature, Span, Spanned,
+    SyntaxShape, Type, Value,
+};
+use portable_pty::{native_pty_system, CommandBuilder, MasterPty, PtySize};
+
+use crate::{resize::ResizeSignal, streams::RawStreamReader};
+
+// what the terminal driver treats as end of input once the piped input has been written
+#[cfg(unix)]
+const END_OF_INPUT: &[u8] = b"\x04";
+#[cfg(windows)]
+const END_OF_INPUT: &[u8] = b"\x1a\r\n";
+
+#[derive(Clone)]
+pub struct Pty;
+
+impl Command for Pty {
+    fn name(&self) -> &str {
+        "pty"
+    }
+
+    fn signature(&self) -> Signature {
+        Signature::build("pty")
+            .input_output_types(vec![(Type::Any, Type::Any)])
+            .required(
+                "command",
+                SyntaxShape::String,
+                "the external command to run",
+            )
+            .rest(
+                "args",
+                SyntaxShape::Any,
+                "arguments for the external command",
+            )
+            .named(
+                "rows",
+                SyntaxShape::Int,
+                "height of the terminal (defaults to the size of the current one, or 24)",
+                None,
+            )
+            .named(
+                "cols",
+                SyntaxShape::Int,
+                "width of the terminal (defaults to the size of the current one, or 80)",
+                None,
+            )
+            .allows_unknown_args()
+            .category(Category::System)
+    }
+
+    fn usage(&self) -> &str {
+        "Run an external command inside a pseudo-terminal."
+    }
+
+    fn extra_usage(&self) -> &str {
+        r#"Programs that check whether they're attached to a terminal, like `vim` or `top`, behave as if started directly from one.
+Piped input is written to the terminal, otherwise keystrokes are forwarded to it while the command runs."#
+    }
+
+    fn search_terms(&self) -> Vec<&str> {
+        vec!["terminal", "tty", "interactive", "external", "isatty"]
+    }
+
+    fn run(
+        &self,
+        engine_state: &EngineState,
+        stack: &mut Stack,
+        call: &Call,
+        input: PipelineData,
+    ) -> Result<PipelineData, ShellError> {
+        let span = call.head;
+        let name: Spanned<String> = call.req(engine_state, stack, 0)?;
+        let rows: Option<Spanned<i64>> = call.get_flag(engine_state, stack, "rows")?;
+        let cols: Option<Spanned<i64>> = call.get_flag(engine_state, stack, "cols")?;
+
+        // some terminals report a size of zero while they're being set up
+        let (default_cols, default_rows) = crossterm::terminal::size()
+            .ok()
+            .filter(|(cols, rows)| *cols > 0 && *rows > 0)
+            .unwrap_or((80, 24));
+        // a size given on the command line stays, otherwise it changes along with the terminal
+        let follow_resizes = rows.is_none() && cols.is_none() && std::io::stdout().is_terminal();
+        let size = PtySize {
+            rows: dimension(rows, default_rows, span)?,
+            cols: dimension(cols, default_cols, span)?,
+            pixel_width: 0,
+            pixel_height: 0,
+        };
+
+        let mut args = vec![];
+        for arg in call.rest::<Value>(engine_state, stack, 1)? {
+            args.extend(arg_strings(arg)?);
+        }
+        let program = Program {
+            name,
+            args,
+            cwd: current_dir(engine_state, stack)?,
+            envs: env_to_strings(engine_state, stack)?,
+        };
+        let input = input_reader(engine_state, input)?;
+
+        let pair = match native_pty_system().openpty(size) {
+            Ok(pair) => pair,
+            // ConPTY only exists since Windows 10 1809, so older systems run the command as-is
+            Err(_) => return run_without_pty(engine_state, call, program, input),
+        };
+        let mut child = pair
+            .slave
+            .spawn_command(program.pty_command())
+            .map_err(|err| pty_error("Could not run external command", &err, program.name.span))?;
+        // the output only ends once every handle to the child's side is closed
+        drop(pair.slave);
+
+        let reader = pair
+            .master
+            .try_clone_reader()
+            .map_err(|err| pty_error("Could not read from the pseudo-terminal", &err, span))?;
+        let writer = pair
+            .master
+            .take_writer()
+            .map_err(|err| pty_error("Could not write to the pseudo-terminal", &err, span))?;
+
+        let done = Arc::new(AtomicBool::new(false));
+        let mut raw_mode = false;
+        #[cfg(windows)]
+        let console = windows_console::enable_virtual_terminal(input.is_none());
+        match input {
+            None => {
+                if std::io::stdin().is_terminal() {
+                    raw_mode = crossterm::terminal::enable_raw_mode().is_ok();
+                    let done = done.clone();
+                    thread::Builder::new()
+                        .name("pty stdin".into())
+                        .spawn(move || forward_stdin(writer, &done))
+                        .map_err(|err| pty_error("Could not forward input", &err, span))?;
+                }
+            }
+            Some(input) => {
+                thread::Builder::new()
+                    .name("pty input".into())
+                    .spawn(move || write_input(input, writer))
+                    .map_err(|err| pty_error("Could not forward input", &err, span))?;
+            }
+        }
+
+        let master = Arc::new(Mutex::new(pair.master));
+        if follow_resizes {
+            let master = master.clone();
+            let done = done.clone();
+            thread::Builder::new()
+                .name("pty resize".into())
+                .spawn(move || forward_resizes(master, size, &done))
+                .map_err(|err| pty_error("Could not follow terminal resizes", &err, span))?;
+        }
+
+        let output = OutputChunks {
+            reader,
+            session: Some(Session {
+                _master: master,
+                done,
+                raw_mode,
+                #[cfg(windows)]
+                _console: console,
+            }),
+            finished: false,
+        };
+
+        let exit_code = std::iter::once_with(move || match child.wait() {
+            Ok(status) => Value::int(status.exit_code() as i64, span),
+            Err(err) => Value::error(pty_error("Could not wait for external command", &err, span)),
+        });
+
+        Ok(PipelineData::ExternalStream {
+            stdout: Some(RawStream::new(
+                Box::new(output),
+                engine_state.ctrlc.clone(),
+                span,
+                None,
+            )),
+            stderr: None,
+            exit_code: Some(ListStream::from_stream(exit_code, None)),
+            span,
+            metadata: None,
+            trim_end_newline: false,
+        })
+    }
+
+    fn examples(&self) -> Vec<Example<'_>> {
+        vec![
+            Example {
+                description: "Edit a file with vim",
+                example: "pty vim notes.txt",
+                result: None,
+            },
+            Example {
+                description: "Capture the colored output of a command that only colors terminals",
+                example: "pty --cols 120 ls --color=auto | lines",
+                result: None,
+            },
+        ]
+    }
+}
+
+struct Program {
+    name: Spanned<String>,
+    args: Vec<String>,
+    cwd: PathBuf,
+    envs: HashMap<String, String>,
+}
+
+impl Program {
+    fn pty_command(&self) -> CommandBuilder {
+        let mut command = CommandBuilder::new(&self.name.item);
+        command.args(&self.args);
+        command.cwd(&self.cwd);
+        for (key, val) in &self.envs {
+            command.env(key, val);
+        }
+        command
+    }
+
+    fn process_command(&self) -> process::Command {
+        let mut command = process::Command::new(&self.name.item);
+        command
+            .args(&self.args)
+            .current_dir(&self.cwd)
+            .envs(&self.envs);
+        command
+    }
+}
+
+// keeps the pseudo-terminal open and the current terminal in raw mode until the output is done
+struct Session {
+    _master: Arc<Mutex<Box<dyn MasterPty + Send>>>,
+    done: Arc<AtomicBool>,
+    raw_mode: bool,
+    #[cfg(windows)]
+    _console: windows_console::ConsoleModes,
+}
+
+impl Drop for Session {
+    fn drop(&mut self) {
+        self.done.store(true, Ordering::SeqCst);
+        if self.raw_mode {
+            let _ = crossterm::terminal::disable_raw_mode();
+        }
+    }
+}
+
+struct OutputChunks {
+    reader: Box<dyn Read + Send>,
+    session: Option<Session>,
+    finished: bool,
+}
+
+impl Iterator for OutputChunks {
+    type Item = Result<Vec<u8>, ShellError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished {
+            return None;
+        }
+
+        let mut buffer = vec![0; 8192];
+        loop {
+            match self.reader.read(&mut buffer) {
+                Ok(0) => break,
+                Ok(len) => {
+                    buffer.truncate(len);
+                    return Some(Ok(buffer));
+                }
+                Err(err) if err.kind() == std::io::ErrorKind::Interrupted => continue,
+                // linux reports EIO instead of end of file once the child side is closed
+                Err(_) => break,
+            }
+        }
+
+        self.finished = true;
+        self.session = None;
+        None
+    }
+}
+
+fn input_reader(
+    engine_state: &EngineState,
+    input: PipelineData,
+) -> Result<Option<Box<dyn Read + Send>>, ShellError> {
+    Ok(Some(match input {
+        PipelineData::Empty => return Ok(None),
+        PipelineData::ExternalStream { .. } => match RawStreamReader::from_pipeline_data(input) {
+            Some(reader) => Box::new(reader),
+            None => Box::new(std::io::empty()),
+        },
+        PipelineData::Value(Value::Binary { val, .. }, ..) => Box::new(std::io::Cursor::new(val)),
+        input => Box::new(std::io::Cursor::new(
+            input.collect_string("\n", engine_state.get_config())?,
+        )),
+    }))
+}
+
+// without a pseudo-terminal the command still runs, just attached to our own stdio
+fn run_without_pty(
+    engine_state: &EngineState,
+    call: &Call,
+    program: Program,
+    input: Option<Box<dyn Read + Send>>,
+) -> Result<PipelineData, ShellError> {
+    let span = call.head;
+    let mut command = program.process_command();
+    command.stdin(match input {
+        Some(_) => Stdio::piped(),
+        None => Stdio::inherit(),
+    });
+    if call.redirect_stdout {
+        command.stdout(Stdio::piped());
+    }
+
+    let mut child = command
+        .spawn()
+        .map_err(|err| pty_error("Could not run external command", &err, program.name.span))?;
+
+    if let (Some(mut input), Some(mut stdin)) = (input, child.stdin.take()) {
+        thread::Builder::new()
+            .name("external input".into())
+            .spawn(move || std::io::copy(&mut input, &mut stdin))
+            .map_err(|err| pty_error("Could not forward input", &err, span))?;
+    }
+
+    let stdout = child.stdout.take().map(|stdout| {
+        let output = OutputChunks {
+            reader: Box::new(stdout),
+            session: None,
+            finished: false,
+        };
+        RawStream::new(Box::new(output), engine_state.ctrlc.clone(), span, None)
+    });
+
+    let exit_code = std::iter::once_with(move || match child.wait() {
+        Ok(status) => Value::int(status.code().unwrap_or(-1) as i64, span),
+        Err(err) => Value::error(pty_error("Could not wait for external command", &err, span)),
+    });
+
+    Ok(PipelineData::ExternalStream {
+        stdout,
+        stderr: None,
+        exit_code: Some(ListStream::from_stream(exit_code, None)),
+        span,
+        metadata: None,
+        trim_end_newline: false,
+    })
+}
+
+fn write_input(mut input: Box<dyn Read + Send>, mut writer: Box<dyn Write + Send>) {
+    let mut buffer = [0; 8192];
+    let mut last = b'\n';
+    loop {
+        match input.read(&mut buffer) {
+            Ok(0) | Err(_) => break,
+            Ok(len) => {
+                if writer.write_all(&buffer[..len]).is_err() {
+                    return;
+                }
+                last = buffer[len - 1];
+            }
+        }
+    }
+
+    // a partial line has to be flushed to the reader before end of input is seen
+    if last != b'\n' {
+        let _ = writer.write_all(END_OF_INPUT);
+    }
+    let _ = writer.write_all(END_OF_INPUT);
+    let _ = writer.flush();
+}
+
+#[cfg(unix)]
+fn forward_stdin(mut writer: Box<dyn Write + Send>, done: &AtomicBool) {
+    let mut buffer = [0; 1024];
+    while !done.load(Ordering::SeqCst) {
+        // poll so the thread notices the command finished instead of holding on to a keystroke
+        let mut fds = libc::pollfd {
+            fd: libc::STDIN_FILENO,
+            events: libc::POLLIN,
+            revents: 0,
+        };
+        if unsafe { libc::poll(&mut fds, 1, 50) } <= 0 {
+            continue;
+        }
+
+        let len =
+            unsafe { libc::read(libc::STDIN_FILENO, buffer.as_mut_ptr().cast(), buffer.len()) };
+        if len <= 0 || writer.write_all(&buffer[..len as usize]).is_err() {
+            break;
+        }
+        let _ = writer.flush();
+    }
+}
+
+#[cfg(not(unix))]
+fn forward_stdin(mut writer: Box<dyn Write + Send>, done: &AtomicBool) {
+    let mut stdin = std::io::stdin();
+    let mut buffer = [0; 1024];
+    while !done.load(Ordering::SeqCst) {
+        match stdin.read(&mut buffer) {
+            Ok(0) | Err(_) => break,
+            Ok(len) => {
+                if done.load(Ordering::SeqCst) || writer.write_all(&buffer[..len]).is_err() {
+                    break;
+                }
+                let _ = writer.flush();
+            }
+        }
+    }
+}
+
+fn forward_resizes(
+    master: Arc<Mutex<Box<dyn MasterPty + Send>>>,
+    mut size: PtySize,
+    done: &AtomicBool,
+) {
+    let signal = ResizeSignal::register();
+    // polled rather than waited on, so the thread notices the command finished
+    while !done.load(Ordering::SeqCst) {
+        thread::sleep(Duration::from_millis(50));
+        if !signal.take() {
+            continue;
+        }
+
+        let Ok((cols, rows)) = crossterm::terminal::size() else {
+            continue;
+        };
+        if cols == 0 || rows == 0 || (cols, rows) == (size.cols, size.rows) {
+            continue;
+        }
+
+        size.cols = cols;
+        size.rows = rows;
+        if let Ok(master) = master.lock() {
+            let _ = master.resize(size);
+        }
+    }
+}
+
+fn dimension(val: Option<Spanned<i64>>, default: u16, span: Span) -> Result<u16, ShellError> {
+    match val {
+        Some(val) => match u16::try_from(val.item) {
+            Ok(size) if size > 0 => Ok(size),
+            _ => Err(ShellError::IncorrectValue {
+                msg: "terminal size must be between 1 and 65535".into(),
+                val_span: val.span,
+                call_span: span,
+            }),
+        },
+        None => Ok(default),
+    }
+}
+
+fn arg_strings(arg: Value) -> Result<Vec<String>, ShellError> {
+    match arg {
+        Value::List { vals, .. } => {
+            let mut args = vec![];
+            for val in vals {
+                args.extend(arg_strings(val)?);
+            }
+            Ok(args)
+        }
+        val => Ok(vec![val.as_string()?]),
+    }
+}
+
+fn pty_error(msg: &str, err: &impl ToString, span: Span) -> ShellError {
+    ShellError::GenericError(msg.into(), err.to_string(), Some(span), None, vec![])
+}
+
+#[cfg(windows)]
+mod windows_console {
+    use crossterm_winapi::{ConsoleMode, Handle};
+
+    const ENABLE_VIRTUAL_TERMINAL_PROCESSING: u32 = 0x0004;
+    const ENABLE_VIRTUAL_TERMINAL_INPUT: u32 = 0x0200;
+
+    // the console modes to put back once the command is done
+    pub struct ConsoleModes {
+        saved: Vec<(ConsoleMode, u32)>,
+    }
+
+    /// Let the console render the escape sequences ConPTY produces, and, when keystrokes are
+    /// forwarded, report keys like the arrows as escape sequences too.
+    pub fn enable_virtual_terminal(input: bool) -> ConsoleModes {
+        let mut saved = vec![];
+        let mut enable = |handle: std::io::Result<Handle>, flag: u32| {
+            let Ok(console) = handle.map(ConsoleMode::from) else {
+                return;
+            };
+            if let Ok(mode) = console.mode() {
+                if console.set_mode(mode | flag).is_ok() {
+                    saved.push((console, mode));
+                }
+            }
+        };
+
+        enable(
+            Handle::current_out_handle(),
+            ENABLE_VIRTUAL_TERMINAL_PROCESSING,
+        );
+        if input {
+            enable(Handle::current_in_handle(), ENABLE_VIRTUAL_TERMINAL_INPUT);
+        }
+
+        ConsoleModes { saved }
+    }
+
+    impl Drop for ConsoleModes {
+        fn drop(&mut self) {
+            for (console, mode) in &self.saved {
+                let _ = console.set_mode(*mode);
+            }
+        }
+    }
+}
@@ -0,0 +1,177 @@
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+
+use miette::{LabeledSpan, MietteDiagnostic, Severity};
+use nu_engine::CallExt;
+use nu_protocol::{
+    ast::Call,
+    engine::{Command, EngineState, Stack},
+    PipelineData, ShellError, Signature, Span, Spanned, Value,
+};
+
+use crate::deprecation::RemovedCommands;
+use crate::helpers::report_error_new;
+
+/// `let-env FOO = ...`, removed in favor of `$env.FOO = ...`.
+#[derive(Clone)]
+pub struct LetEnv {
+    handling: RemovedCommands,
+    warned: Arc<AtomicBool>,
+}
+
+impl LetEnv {
+    pub fn new(handling: RemovedCommands) -> Self {
+        Self {
+            handling,
+            warned: Arc::default(),
+        }
+    }
+}
+
+impl Default for LetEnv {
+    fn default() -> Self {
+        Self::new(RemovedCommands::default())
+    }
+}
+
+impl Command for LetEnv {
+    fn name(&self) -> &str {
+        "let-env"
+    }
+
+    fn signature(&self) -> Signature {
+        nu_command::LetEnv.signature()
+    }
+
+    fn usage(&self) -> &str {
+        nu_command::LetEnv.usage()
+    }
+
+    fn run(
+        &self,
+        engine_state: &EngineState,
+        stack: &mut Stack,
+        call: &Call,
+        input: PipelineData,
+    ) -> Result<PipelineData, ShellError> {
+        if self.handling != RemovedCommands::Warn {
+            return nu_command::LetEnv.run(engine_state, stack, call, input);
+        }
+        warn_once(
+            engine_state,
+            &self.warned,
+            self.name(),
+            "$env.FOO = ...",
+            call.head,
+        );
+
+        let (Some(name), Some(value)) = (
+            call.opt::<Spanned<String>>(engine_state, stack, 0)?,
+            call.opt::<Value>(engine_state, stack, 1)?,
+        ) else {
+            return Err(ShellError::GenericError(
+                "Missing variable".into(),
+                "expected a name and `= value`".into(),
+                Some(call.head),
+                Some("like `let-env FOO = 'bar'`".into()),
+                vec![],
+            ));
+        };
+
+        // `cd` and sourcing keep these up to date, like they do for `$env.PWD = ...`
+        if ["FILE_PWD", "CURRENT_FILE", "PWD"].contains(&name.item.as_str()) {
+            return Err(ShellError::AutomaticEnvVarSetManually {
+                envvar_name: name.item,
+                span: name.span,
+            });
+        }
+
+        stack.add_env_var(name.item, value);
+        Ok(PipelineData::empty())
+    }
+}
+
+/// `date format`, renamed to `format date`.
+#[derive(Clone)]
+pub struct DateFormat {
+    handling: RemovedCommands,
+    warned: Arc<AtomicBool>,
+}
+
+impl DateFormat {
+    pub fn new(handling: RemovedCommands) -> Self {
+        Self {
+            handling,
+            warned: Arc::default(),
+        }
+    }
+}
+
+impl Default for DateFormat {
+    fn default() -> Self {
+        Self::new(RemovedCommands::default())
+    }
+}
+
+impl Command for DateFormat {
+    fn name(&self) -> &str {
+        "date format"
+    }
+
+    fn signature(&self) -> Signature {
+        // nu-command's stub takes the same arguments as `format date`
+        nu_command::DateFormat.signature()
+    }
+
+    fn usage(&self) -> &str {
+        nu_command::DateFormat.usage()
+    }
+
+    fn run(
+        &self,
+        engine_state: &EngineState,
+        stack: &mut Stack,
+        call: &Call,
+        input: PipelineData,
+    ) -> Result<PipelineData, ShellError> {
+        if self.handling != RemovedCommands::Warn {
+            return nu_command::DateFormat.run(engine_state, stack, call, input);
+        }
+        warn_once(
+            engine_state,
+            &self.warned,
+            self.name(),
+            "format date",
+            call.head,
+        );
+
+        nu_command::FormatDate.run(engine_state, stack, call, input)
+    }
+}
+
+// one warning per command is enough to get a script fixed, a loop shouldn't repeat it
+fn warn_once(
+    engine_state: &EngineState,
+    warned: &AtomicBool,
+    name: &str,
+    replacement: &str,
+    span: Span,
+) {
+    if warned.swap(true, Ordering::Relaxed) {
+        return;
+    }
+
+    let warning = MietteDiagnostic::new(format!("`{name}` has been removed"))
+        .with_code("nu_app::removed_command")
+        .with_severity(Severity::Warning)
+        .with_label(LabeledSpan::at(
+            span.start..span.end,
+            format!("use {replacement} instead"),
+        ))
+        .with_help(format!(
+            "this still runs as {replacement} for now, but other nu versions will refuse it"
+        ));
+    report_error_new(engine_state, &warning);
+}
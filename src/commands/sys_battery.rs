@@ -0,0 +1,128 @@
+use nu_protocol::{
+    ast::Call,
+    engine::{Command, EngineState, Stack},
+    Category, Example, IntoPipelineData, PipelineData, ShellError, Signature, Span, Type, Value,
+};
+use starship_battery::units::{
+    electric_potential::volt, energy::watt_hour, power::watt, ratio::percent,
+    thermodynamic_temperature::degree_celsius, time::nanosecond, Time,
+};
+
+#[derive(Clone)]
+pub struct SysBattery;
+
+impl Command for SysBattery {
+    fn name(&self) -> &str {
+        "sys battery"
+    }
+
+    fn signature(&self) -> Signature {
+        Signature::build("sys battery")
+            .input_output_types(vec![(Type::Nothing, Type::Table(vec![]))])
+            .category(Category::System)
+    }
+
+    fn usage(&self) -> &str {
+        "View the state of the batteries in the system."
+    }
+
+    fn extra_usage(&self) -> &str {
+        "Charge and health are percentages, energy is in watt-hours, rate in watts and voltage in volts."
+    }
+
+    fn search_terms(&self) -> Vec<&str> {
+        vec!["power", "charge", "laptop"]
+    }
+
+    fn run(
+        &self,
+        _engine_state: &EngineState,
+        _stack: &mut Stack,
+        call: &Call,
+        _input: PipelineData,
+    ) -> Result<PipelineData, ShellError> {
+        let span = call.head;
+
+        let batteries = starship_battery::Manager::new()
+            .and_then(|manager| {
+                manager
+                    .batteries()?
+                    .map(|battery| battery.map(|battery| battery_record(&battery, span)))
+                    .collect::<Result<Vec<_>, _>>()
+            })
+            .map_err(|err| {
+                ShellError::GenericError(
+                    "Could not read battery information".into(),
+                    err.to_string(),
+                    Some(span),
+                    None,
+                    vec![],
+                )
+            })?;
+
+        Ok(Value::List {
+            vals: batteries,
+            span,
+        }
+        .into_pipeline_data())
+    }
+
+    fn examples(&self) -> Vec<Example<'_>> {
+        vec![Example {
+            description: "Show the charge of the first battery",
+            example: "sys battery | first | get charge",
+            result: None,
+        }]
+    }
+}
+
+fn battery_record(battery: &starship_battery::Battery, span: Span) -> Value {
+    let optional_str = |val: Option<&str>| match val {
+        Some(val) => Value::string(val, span),
+        None => Value::nothing(span),
+    };
+    let optional_duration = |val: Option<Time>| match val {
+        Some(val) => Value::duration(val.get::<nanosecond>() as i64, span),
+        None => Value::nothing(span),
+    };
+
+    Value::Record {
+        cols: vec![
+            "vendor".into(),
+            "model".into(),
+            "state".into(),
+            "charge".into(),
+            "health".into(),
+            "energy".into(),
+            "energy_full".into(),
+            "rate".into(),
+            "voltage".into(),
+            "time_to_full".into(),
+            "time_to_empty".into(),
+            "cycle_count".into(),
+            "temp".into(),
+        ],
+        vals: vec![
+            optional_str(battery.vendor()),
+            optional_str(battery.model()),
+            Value::string(battery.state().to_string(), span),
+            Value::float(battery.state_of_charge().get::<percent>() as f64, span),
+            Value::float(battery.state_of_health().get::<percent>() as f64, span),
+            Value::float(battery.energy().get::<watt_hour>() as f64, span),
+            Value::float(battery.energy_full().get::<watt_hour>() as f64, span),
+            Value::float(battery.energy_rate().get::<watt>() as f64, span),
+            Value::float(battery.voltage().get::<volt>() as f64, span),
+            optional_duration(battery.time_to_full()),
+            optional_duration(battery.time_to_empty()),
+            match battery.cycle_count() {
+                Some(count) => Value::int(count as i64, span),
+                None => Value::nothing(span),
+            },
+            match battery.temperature() {
+                Some(temp) => Value::float(temp.get::<degree_celsius>() as f64, span),
+                None => Value::nothing(span),
+            },
+        ],
+        span,
+    }
+}
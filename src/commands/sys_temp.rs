@@ -0,0 +1,84 @@
+use nu_protocol::{
+    ast::Call,
+    engine::{Command, EngineState, Stack},
+    Category, Example, IntoPipelineData, PipelineData, ShellError, Signature, Type, Value,
+};
+use sysinfo::{ComponentExt, System, SystemExt};
+
+#[derive(Clone)]
+pub struct SysTemp;
+
+impl Command for SysTemp {
+    fn name(&self) -> &str {
+        "sys temp"
+    }
+
+    fn signature(&self) -> Signature {
+        Signature::build("sys temp")
+            .input_output_types(vec![(Type::Nothing, Type::Table(vec![]))])
+            .category(Category::System)
+    }
+
+    fn usage(&self) -> &str {
+        "View the temperature sensors of the system, in degrees Celsius."
+    }
+
+    fn extra_usage(&self) -> &str {
+        "Returns an empty table on platforms where sensors can't be read."
+    }
+
+    fn search_terms(&self) -> Vec<&str> {
+        vec!["sensors", "temperature", "thermal"]
+    }
+
+    fn run(
+        &self,
+        _engine_state: &EngineState,
+        _stack: &mut Stack,
+        call: &Call,
+        _input: PipelineData,
+    ) -> Result<PipelineData, ShellError> {
+        let span = call.head;
+
+        let mut sys = System::new();
+        sys.refresh_components_list();
+        sys.refresh_components();
+
+        let sensors = sys
+            .components()
+            .iter()
+            .map(|component| Value::Record {
+                cols: vec![
+                    "unit".into(),
+                    "temp".into(),
+                    "high".into(),
+                    "critical".into(),
+                ],
+                vals: vec![
+                    Value::string(component.label(), span),
+                    Value::float(component.temperature() as f64, span),
+                    Value::float(component.max() as f64, span),
+                    match component.critical() {
+                        Some(critical) => Value::float(critical as f64, span),
+                        None => Value::nothing(span),
+                    },
+                ],
+                span,
+            })
+            .collect();
+
+        Ok(Value::List {
+            vals: sensors,
+            span,
+        }
+        .into_pipeline_data())
+    }
+
+    fn examples(&self) -> Vec<Example<'_>> {
+        vec![Example {
+            description: "Find sensors that are running hot",
+            example: "sys temp | where temp > 80",
+            result: None,
+        }]
+    }
+}
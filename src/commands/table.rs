@@ -0,0 +1,239 @@
+use std::{
+    sync::mpsc::{sync_channel, Receiver, RecvTimeoutError},
+    thread,
+    time::{Duration, Instant},
+};
+
+use nu_engine::{env::get_config, CallExt};
+use nu_protocol::{
+    ast::{Argument, Call, Expr, Expression},
+    engine::{Command, EngineState, Stack},
+    Example, ListStream, PipelineData, PipelineMetadata, RawStream, ShellError, Signature, Spanned,
+    Type, Value,
+};
+
+use crate::{
+    resize::ResizeSignal,
+    units::{duration_precision, round_durations, DurationPrecision},
+};
+
+// nu-command's own limit on the rows in one rendered table
+const PAGE_SIZE: usize = 1000;
+// the first rows are shown quickly, so a slow stream shows signs of life
+const FIRST_PAGE_WAIT: Duration = Duration::from_millis(100);
+// later pages wait longer, so the header isn't redrawn every few rows
+const PAGE_WAIT: Duration = Duration::from_secs(1);
+// how often a page that's waiting for rows looks for a resized terminal
+const RESIZE_POLL: Duration = Duration::from_millis(50);
+
+/// nu-command's `table`, but rendering streams as their rows arrive.
+///
+/// nu-command only renders a page once its next row arrives a second after the page started,
+/// so a stream that stalls shows nothing until it picks up again.
+#[derive(Clone)]
+pub struct Table;
+
+impl Command for Table {
+    fn name(&self) -> &str {
+        "table"
+    }
+
+    fn signature(&self) -> Signature {
+        nu_command::Table.signature()
+    }
+
+    fn usage(&self) -> &str {
+        nu_command::Table.usage()
+    }
+
+    fn extra_usage(&self) -> &str {
+        r#"If the table contains a column called 'index', this column is used as the table index instead of the usual continuous index.
+Streams are shown a page at a time as their rows arrive, each page with its own header.
+Each page fits the width the terminal has when it's drawn, and resizing the terminal starts a new page."#
+    }
+
+    fn search_terms(&self) -> Vec<&str> {
+        nu_command::Table.search_terms()
+    }
+
+    fn run(
+        &self,
+        engine_state: &EngineState,
+        stack: &mut Stack,
+        call: &Call,
+        input: PipelineData,
+    ) -> Result<PipelineData, ShellError> {
+        let input = match duration_precision() {
+            DurationPrecision::Nanosecond => input,
+            precision => match input {
+                PipelineData::Value(value, metadata) => {
+                    PipelineData::Value(round_durations(value, precision), metadata)
+                }
+                PipelineData::ListStream(stream, metadata) => {
+                    let ctrlc = stream.ctrlc.clone();
+                    let rounded = stream.map(move |value| round_durations(value, precision));
+                    PipelineData::ListStream(ListStream::from_stream(rounded, ctrlc), metadata)
+                }
+                input => input,
+            },
+        };
+
+        let (stream, metadata) = match input {
+            PipelineData::ListStream(stream, metadata) if !call.has_flag("list") => {
+                (stream, metadata)
+            }
+            input => return nu_command::Table.run(engine_state, stack, call, input),
+        };
+        let span = call.head;
+        let start_number: Option<i64> = call.get_flag(engine_state, stack, "start-number")?;
+
+        // rows are pulled on their own thread, so a page can be shown while the next row is awaited
+        let (sender, rows) = sync_channel(PAGE_SIZE);
+        thread::Builder::new()
+            .name("table rows".into())
+            .spawn(move || {
+                for row in stream {
+                    if sender.send(row).is_err() {
+                        break;
+                    }
+                }
+            })
+            .map_err(|err| ShellError::IOError(err.to_string()))?;
+
+        let mut call = call.clone();
+        call.arguments.retain(
+            |arg| !matches!(arg, Argument::Named((name, ..)) if name.item == "start-number"),
+        );
+
+        let pages = Pages {
+            rows,
+            engine_state: engine_state.clone(),
+            stack: stack.clone(),
+            call,
+            metadata,
+            row_offset: start_number.unwrap_or_default(),
+            rendered: false,
+            resized: ResizeSignal::register(),
+        };
+
+        Ok(PipelineData::ExternalStream {
+            stdout: Some(RawStream::new(
+                Box::new(pages),
+                engine_state.ctrlc.clone(),
+                span,
+                None,
+            )),
+            stderr: None,
+            exit_code: None,
+            span,
+            metadata: None,
+            trim_end_newline: false,
+        })
+    }
+
+    fn examples(&self) -> Vec<Example<'_>> {
+        vec![
+            Example {
+                description: "List the files in current directory, with indexes starting from 1",
+                example: "ls | table -n 1",
+                result: None,
+            },
+            Example {
+                description: "Show rows of a slow stream as soon as they are produced",
+                example: "1..5 | each {|i| sleep 1sec; {i: $i} } | table",
+                result: None,
+            },
+        ]
+    }
+}
+
+// the tables for a stream, each covering the rows that arrived while the one before was shown
+struct Pages {
+    rows: Receiver<Value>,
+    engine_state: EngineState,
+    stack: Stack,
+    call: Call,
+    metadata: Option<Box<PipelineMetadata>>,
+    row_offset: i64,
+    rendered: bool,
+    resized: ResizeSignal,
+}
+
+impl Pages {
+    fn next_batch(&mut self) -> Vec<Value> {
+        let Ok(first) = self.rows.recv() else {
+            return vec![];
+        };
+        // this page isn't drawn yet, so it gets the new width anyway
+        self.resized.take();
+
+        let wait = if self.rendered {
+            PAGE_WAIT
+        } else {
+            FIRST_PAGE_WAIT
+        };
+        let deadline = Instant::now() + wait;
+        let mut batch = vec![first];
+        while batch.len() < PAGE_SIZE {
+            // the rows after a resize go on a page of their own, drawn to the new width
+            if self.resized.take() {
+                break;
+            }
+
+            let timeout = deadline.saturating_duration_since(Instant::now());
+            match self.rows.recv_timeout(timeout.min(RESIZE_POLL)) {
+                Ok(row) => batch.push(row),
+                Err(RecvTimeoutError::Timeout) if timeout > RESIZE_POLL => {}
+                Err(RecvTimeoutError::Timeout | RecvTimeoutError::Disconnected) => break,
+            }
+        }
+
+        batch
+    }
+
+    fn render(&mut self, batch: Vec<Value>) -> Result<Vec<u8>, ShellError> {
+        let span = self.call.head;
+        let mut call = self.call.clone();
+        call.add_named((
+            Spanned {
+                item: "start-number".into(),
+                span,
+            },
+            None,
+            Some(Expression {
+                expr: Expr::Int(self.row_offset),
+                span,
+                ty: Type::Int,
+                custom_completion: None,
+            }),
+        ));
+        self.row_offset += batch.len() as i64;
+
+        // nu-command's table asks the terminal for its width each time it's run
+        let batch = PipelineData::Value(Value::List { vals: batch, span }, self.metadata.clone());
+        match nu_command::Table.run(&self.engine_state, &mut self.stack, &call, batch)? {
+            PipelineData::ExternalStream {
+                stdout: Some(stdout),
+                ..
+            } => Ok(stdout.into_bytes()?.item),
+            output => Ok(output
+                .collect_string("", &get_config(&self.engine_state, &self.stack))?
+                .into_bytes()),
+        }
+    }
+}
+
+impl Iterator for Pages {
+    type Item = Result<Vec<u8>, ShellError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let batch = self.next_batch();
+        // an empty stream still gets nu-command's "empty list" placeholder
+        if batch.is_empty() && self.rendered {
+            return None;
+        }
+
+        self.rendered = true;
+        Some(self.render(batch))
+    }
+}
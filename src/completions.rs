@@ -0,0 +1,49 @@
+use std::sync::Arc;
+
+use nu_cli::NuCompleter;
+use nu_protocol::engine::{EngineState, Stack};
+use reedline::Completer;
+pub use reedline::Suggestion;
+
+use crate::helpers::sync_pwd;
+
+/// The completions nu's REPL would offer for `line` with the cursor at the byte offset `pos`:
+/// commands, subcommands and flags, variables and cell paths, and the files in the stack's
+/// current directory.
+///
+/// Each suggestion replaces the bytes of its `span` in `line` with its `value`. The arguments of
+/// externals come from the closure of `$env.config.completions.external.completer` when the
+/// config has one, like carapace.
+///
+/// ```
+/// use nu_app::{completions::complete, create_engine_state, create_stack};
+///
+/// let engine_state = create_engine_state();
+/// let stack = create_stack();
+///
+/// let suggestions = complete(&engine_state, &stack, "str up", 6);
+/// assert!(suggestions.iter().any(|suggestion| suggestion.value == "str upcase"));
+/// ```
+pub fn complete(
+    engine_state: &EngineState,
+    stack: &Stack,
+    line: &str,
+    pos: usize,
+) -> Vec<Suggestion> {
+    // the completer slices `line` at the cursor
+    let mut pos = pos.min(line.len());
+    while !line.is_char_boundary(pos) {
+        pos -= 1;
+    }
+
+    completer(engine_state, stack).complete(line, pos)
+}
+
+// nu's completer for the engine as it is now, which completes paths in the stack's current
+// directory
+pub(crate) fn completer(engine_state: &EngineState, stack: &Stack) -> NuCompleter {
+    let mut engine_state = engine_state.clone();
+    sync_pwd(&mut engine_state, stack);
+
+    NuCompleter::new(Arc::new(engine_state), stack.clone())
+}
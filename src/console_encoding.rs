@@ -0,0 +1,299 @@
+use nu_protocol::{PipelineData, RawStream, ShellError};
+
+/// The codepage number of UTF-8, which needs no conversion.
+pub const CP_UTF8: u32 = 65001;
+
+const UTF8_BOM: &[u8] = b"\xef\xbb\xbf";
+const UTF16LE_BOM: &[u8] = b"\xff\xfe";
+const UTF16BE_BOM: &[u8] = b"\xfe\xff";
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Encoding {
+    // nothing seen yet, so a byte order mark may still follow
+    Start,
+    // only ASCII seen, which reads the same in every codepage
+    Ascii,
+    Utf8,
+    Utf16Le,
+    Utf16Be,
+    Codepage,
+    Binary,
+}
+
+/// Converts text written by console programs to UTF-8 as it streams in.
+///
+/// A byte order mark selects UTF-8 or UTF-16 (what PowerShell redirects files as). Otherwise
+/// text stays as-is while it's valid UTF-8, and is decoded with the codepage from the first
+/// chunk that isn't. Bytes that look binary are passed through untouched.
+pub struct ConsoleDecoder {
+    codepage: u32,
+    encoding: Encoding,
+    pending: Vec<u8>,
+}
+
+impl ConsoleDecoder {
+    pub fn new(codepage: u32) -> Self {
+        ConsoleDecoder {
+            codepage,
+            encoding: Encoding::Start,
+            pending: vec![],
+        }
+    }
+
+    /// A decoder for the codepage of the console this process runs in.
+    pub fn for_console() -> Self {
+        ConsoleDecoder::new(console_codepage())
+    }
+
+    /// Decode the next chunk, holding back any bytes of a character that isn't complete yet.
+    pub fn decode(&mut self, chunk: &[u8]) -> Vec<u8> {
+        let mut bytes = std::mem::take(&mut self.pending);
+        bytes.extend_from_slice(chunk);
+
+        if self.encoding == Encoding::Start {
+            if [UTF8_BOM, UTF16LE_BOM, UTF16BE_BOM]
+                .iter()
+                .any(|bom| bytes.len() < bom.len() && bom.starts_with(&bytes))
+            {
+                self.pending = bytes;
+                return vec![];
+            }
+
+            if bytes.starts_with(UTF8_BOM) {
+                bytes.drain(..UTF8_BOM.len());
+                self.encoding = Encoding::Utf8;
+            } else if bytes.starts_with(UTF16LE_BOM) {
+                bytes.drain(..UTF16LE_BOM.len());
+                self.encoding = Encoding::Utf16Le;
+            } else if bytes.starts_with(UTF16BE_BOM) {
+                bytes.drain(..UTF16BE_BOM.len());
+                self.encoding = Encoding::Utf16Be;
+            } else if !bytes.is_empty() {
+                self.encoding = Encoding::Ascii;
+            }
+        }
+
+        if self.encoding == Encoding::Ascii {
+            match std::str::from_utf8(&bytes) {
+                Ok(text) => {
+                    if !text.is_ascii() {
+                        self.encoding = Encoding::Utf8;
+                    }
+                    return bytes;
+                }
+                // a character split between chunks
+                Err(err) if err.error_len().is_none() => {
+                    self.pending = bytes.split_off(err.valid_up_to());
+                    return bytes;
+                }
+                Err(_) if self.codepage == CP_UTF8 => self.encoding = Encoding::Utf8,
+                Err(_) if bytes.contains(&0) => self.encoding = Encoding::Binary,
+                Err(_) => self.encoding = Encoding::Codepage,
+            }
+        }
+
+        match self.encoding {
+            Encoding::Start | Encoding::Ascii => bytes,
+            Encoding::Utf8 | Encoding::Binary => bytes,
+            Encoding::Utf16Le | Encoding::Utf16Be => {
+                let mut units = utf16_units(&bytes, self.encoding == Encoding::Utf16Le);
+                let mut complete = units.len() * 2;
+                // keep the first half of a surrogate pair for the next chunk
+                if units
+                    .last()
+                    .is_some_and(|unit| (0xd800..0xdc00).contains(unit))
+                {
+                    units.pop();
+                    complete -= 2;
+                }
+                self.pending = bytes.split_off(complete);
+                String::from_utf16_lossy(&units).into_bytes()
+            }
+            Encoding::Codepage => {
+                self.pending = bytes.split_off(complete_len(self.codepage, &bytes));
+                decode_codepage(self.codepage, &bytes)
+            }
+        }
+    }
+
+    /// Decode whatever was held back once the input has ended.
+    pub fn finish(&mut self) -> Vec<u8> {
+        let bytes = std::mem::take(&mut self.pending);
+        match self.encoding {
+            Encoding::Start | Encoding::Ascii | Encoding::Utf8 | Encoding::Binary => bytes,
+            Encoding::Utf16Le | Encoding::Utf16Be => {
+                let mut text = String::from_utf16_lossy(&utf16_units(
+                    &bytes,
+                    self.encoding == Encoding::Utf16Le,
+                ));
+                if bytes.len() % 2 == 1 {
+                    text.push(char::REPLACEMENT_CHARACTER);
+                }
+                text.into_bytes()
+            }
+            Encoding::Codepage => decode_codepage(self.codepage, &bytes),
+        }
+    }
+}
+
+/// Decode a stream with `decoder` as it's read.
+pub fn decode_stream(mut stream: RawStream, mut decoder: ConsoleDecoder) -> RawStream {
+    stream.leftover = decoder.decode(&stream.leftover);
+
+    let chunks = std::mem::replace(&mut stream.stream, Box::new(std::iter::empty()));
+    stream.stream = Box::new(DecodedChunks {
+        chunks,
+        decoder: Some(decoder),
+    });
+
+    stream
+}
+
+/// Decode the stdout and stderr of an external stream with decoders made by `decoder`, passing
+/// any other pipeline data through.
+pub fn decode_pipeline_data(
+    pipeline_data: PipelineData,
+    decoder: impl Fn() -> ConsoleDecoder,
+) -> PipelineData {
+    match pipeline_data {
+        PipelineData::ExternalStream {
+            stdout,
+            stderr,
+            exit_code,
+            span,
+            metadata,
+            trim_end_newline,
+        } => PipelineData::ExternalStream {
+            stdout: stdout.map(|stream| decode_stream(stream, decoder())),
+            stderr: stderr.map(|stream| decode_stream(stream, decoder())),
+            exit_code,
+            span,
+            metadata,
+            trim_end_newline,
+        },
+        pipeline_data => pipeline_data,
+    }
+}
+
+struct DecodedChunks {
+    chunks: Box<dyn Iterator<Item = Result<Vec<u8>, ShellError>> + Send + 'static>,
+    decoder: Option<ConsoleDecoder>,
+}
+
+impl Iterator for DecodedChunks {
+    type Item = Result<Vec<u8>, ShellError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let decoder = self.decoder.as_mut()?;
+        loop {
+            match self.chunks.next() {
+                Some(Ok(chunk)) => {
+                    let decoded = decoder.decode(&chunk);
+                    if !decoded.is_empty() {
+                        return Some(Ok(decoded));
+                    }
+                }
+                Some(Err(err)) => return Some(Err(err)),
+                None => {
+                    let rest = decoder.finish();
+                    self.decoder = None;
+                    return (!rest.is_empty()).then_some(Ok(rest));
+                }
+            }
+        }
+    }
+}
+
+fn utf16_units(bytes: &[u8], little_endian: bool) -> Vec<u16> {
+    bytes
+        .chunks_exact(2)
+        .map(|pair| match little_endian {
+            true => u16::from_le_bytes([pair[0], pair[1]]),
+            false => u16::from_be_bytes([pair[0], pair[1]]),
+        })
+        .collect()
+}
+
+/// The codepage console programs write their output in, which is UTF-8 outside of Windows.
+pub fn console_codepage() -> u32 {
+    #[cfg(windows)]
+    {
+        use windows_sys::Win32::{Globalization::GetOEMCP, System::Console::GetConsoleOutputCP};
+
+        // without a console attached, programs fall back to the OEM codepage
+        match unsafe { GetConsoleOutputCP() } {
+            0 => unsafe { GetOEMCP() },
+            codepage => codepage,
+        }
+    }
+    #[cfg(not(windows))]
+    {
+        CP_UTF8
+    }
+}
+
+// the length of `bytes` without a trailing lead byte of a double byte character
+#[cfg(windows)]
+fn complete_len(codepage: u32, bytes: &[u8]) -> usize {
+    use windows_sys::Win32::Globalization::IsDBCSLeadByteEx;
+
+    let mut pos = 0;
+    while pos < bytes.len() {
+        if unsafe { IsDBCSLeadByteEx(codepage, bytes[pos]) } != 0 {
+            if pos + 1 == bytes.len() {
+                return pos;
+            }
+            pos += 2;
+        } else {
+            pos += 1;
+        }
+    }
+    bytes.len()
+}
+
+#[cfg(not(windows))]
+fn complete_len(_codepage: u32, bytes: &[u8]) -> usize {
+    bytes.len()
+}
+
+#[cfg(windows)]
+fn decode_codepage(codepage: u32, bytes: &[u8]) -> Vec<u8> {
+    use windows_sys::Win32::Globalization::MultiByteToWideChar;
+
+    if bytes.is_empty() {
+        return vec![];
+    }
+
+    let len = unsafe {
+        MultiByteToWideChar(
+            codepage,
+            0,
+            bytes.as_ptr(),
+            bytes.len() as i32,
+            std::ptr::null_mut(),
+            0,
+        )
+    };
+    if len <= 0 {
+        return String::from_utf8_lossy(bytes).into_owned().into_bytes();
+    }
+
+    let mut wide = vec![0; len as usize];
+    unsafe {
+        MultiByteToWideChar(
+            codepage,
+            0,
+            bytes.as_ptr(),
+            bytes.len() as i32,
+            wide.as_mut_ptr(),
+            len,
+        )
+    };
+    String::from_utf16_lossy(&wide).into_bytes()
+}
+
+// legacy codepages are only known to Windows
+#[cfg(not(windows))]
+fn decode_codepage(_codepage: u32, bytes: &[u8]) -> Vec<u8> {
+    String::from_utf8_lossy(bytes).into_owned().into_bytes()
+}
@@ -0,0 +1,176 @@
+use std::{
+    backtrace::Backtrace,
+    cell::RefCell,
+    collections::VecDeque,
+    fmt::Write as _,
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex, Once,
+    },
+};
+
+use nu_protocol::ShellError;
+
+/// The version of the nu crates the engine is built from.
+pub const ENGINE_VERSION: &str = "0.84.0";
+
+// how many of the sources evaluated before the one that crashed go into a report
+const HISTORY_LEN: usize = 10;
+
+static REPORTS: Mutex<Option<PathBuf>> = Mutex::new(None);
+static INCLUDE_HISTORY: AtomicBool = AtomicBool::new(true);
+static PANIC_HOOK: Once = Once::new();
+
+thread_local! {
+    // the last of these is the source being evaluated; each thread keeps its own, so the
+    // clients of a daemon don't end up in each other's reports
+    static SOURCES: RefCell<VecDeque<(String, Vec<u8>)>> = const { RefCell::new(VecDeque::new()) };
+}
+
+/// Where crash reports go unless they're enabled with another directory,
+/// `<config dir>/nu_app/crashes`.
+pub fn default_crash_report_dir() -> Option<PathBuf> {
+    nu_path::config_dir().map(|dir| dir.join("nu_app").join("crashes"))
+}
+
+/// Write a crash report to `dir` when the app panics, or evaluating hits an internal error of
+/// the engine.
+///
+/// A report has the source that was being evaluated, the versions and features the app was
+/// built with, a backtrace, and the sources evaluated before on the same thread, see
+/// [`set_crash_report_history`].
+/// The panic is still printed by the hook that was there before.
+pub fn enable_crash_reports(dir: PathBuf) {
+    *REPORTS.lock().expect("crash reports are never poisoned") = Some(dir);
+
+    PANIC_HOOK.call_once(|| {
+        let previous = std::panic::take_hook();
+        std::panic::set_hook(Box::new(move |info| {
+            previous(info);
+            report_crash(&info.to_string());
+        }));
+    });
+}
+
+/// Leave the sources evaluated before the one that crashed out of the reports, they can have
+/// things in them that shouldn't leave the machine.
+pub fn set_crash_report_history(include: bool) {
+    INCLUDE_HISTORY.store(include, Ordering::Relaxed);
+}
+
+/// Write a crash report for `reason` to the directory crash reports are enabled for.
+///
+/// Returns the path of the report, or `None` when crash reports aren't enabled.
+pub fn write_crash_report(reason: &str) -> std::io::Result<Option<PathBuf>> {
+    // a panic while one of the locks is held must still get its report out
+    let dir = match REPORTS.try_lock() {
+        Ok(dir) => dir.clone(),
+        Err(_) => return Ok(None),
+    };
+    let Some(dir) = dir else {
+        return Ok(None);
+    };
+
+    std::fs::create_dir_all(&dir)?;
+    let path = dir.join(format!(
+        "nu_app-crash-{}-{}.txt",
+        chrono::Local::now().format("%Y%m%d-%H%M%S"),
+        std::process::id()
+    ));
+    std::fs::write(&path, crash_report(reason))?;
+
+    Ok(Some(path))
+}
+
+/// Remember `source` as the one being evaluated on this thread, for the crash report.
+pub(crate) fn record_source(fname: &str, source: &[u8]) {
+    let _ = SOURCES.try_with(|sources| {
+        if let Ok(mut sources) = sources.try_borrow_mut() {
+            if sources.len() > HISTORY_LEN {
+                sources.pop_front();
+            }
+            sources.push_back((fname.into(), source.to_vec()));
+        }
+    });
+}
+
+/// Write a crash report if `err` means the engine itself went wrong, rather than the source.
+pub(crate) fn report_fatal_error(err: &ShellError) {
+    if let ShellError::NushellFailed { .. }
+    | ShellError::NushellFailedSpanned { .. }
+    | ShellError::NushellFailedHelp { .. } = err
+    {
+        report_crash(&format!("{err:?}"));
+    }
+}
+
+fn report_crash(reason: &str) {
+    match write_crash_report(reason) {
+        Ok(Some(path)) => eprintln!("A crash report was written to {}", path.display()),
+        Ok(None) => {}
+        Err(err) => eprintln!("Error: could not write the crash report: {err}"),
+    }
+}
+
+fn crash_report(reason: &str) -> String {
+    let mut report = String::from("nu_app crash report\n\n");
+    let _ = writeln!(report, "reason: {reason}");
+    let _ = writeln!(report, "time: {}", chrono::Local::now().to_rfc3339());
+    let _ = writeln!(report, "nu_app: {}", env!("CARGO_PKG_VERSION"));
+    let _ = writeln!(report, "nu: {ENGINE_VERSION}");
+    let features = enabled_features();
+    let features = match features.is_empty() {
+        true => "none".into(),
+        false => features.join(", "),
+    };
+    let _ = writeln!(report, "features: {features}");
+    let _ = writeln!(
+        report,
+        "os: {} {}",
+        std::env::consts::OS,
+        std::env::consts::ARCH
+    );
+
+    // a panic while the sources are borrowed must still get its report out
+    let sources: Vec<(String, Vec<u8>)> = SOURCES
+        .try_with(|sources| match sources.try_borrow() {
+            Ok(sources) => sources.iter().cloned().collect(),
+            Err(_) => vec![],
+        })
+        .unwrap_or_default();
+    if let Some(((fname, source), history)) = sources.split_last() {
+        let _ = writeln!(report, "\n== source ({fname}) ==");
+        let _ = writeln!(report, "{}", String::from_utf8_lossy(source));
+
+        if INCLUDE_HISTORY.load(Ordering::Relaxed) && !history.is_empty() {
+            let _ = writeln!(report, "== recent history ==");
+            for (fname, source) in history {
+                let _ = writeln!(report, "-- {fname} --");
+                let _ = writeln!(report, "{}", String::from_utf8_lossy(source));
+            }
+        }
+    }
+
+    let _ = writeln!(report, "\n== backtrace ==\n{}", Backtrace::force_capture());
+
+    report
+}
+
+fn enabled_features() -> Vec<&'static str> {
+    let features = [
+        ("battery", cfg!(feature = "battery")),
+        ("dns", cfg!(feature = "dns")),
+        ("git", cfg!(feature = "git")),
+        ("plugin", cfg!(feature = "plugin")),
+        ("pty", cfg!(feature = "pty")),
+        ("query", cfg!(feature = "query")),
+        ("serve", cfg!(feature = "serve")),
+        ("which-support", cfg!(feature = "which-support")),
+    ];
+
+    features
+        .into_iter()
+        .filter_map(|(feature, enabled)| enabled.then_some(feature))
+        .collect()
+}
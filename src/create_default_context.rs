@@ -1,402 +1,413 @@
+use std::{collections::BTreeSet, fmt, sync::Arc};
+
+use crate::commands::*;
+// replaces nu-command's version, which always captures the mouse
+use crate::commands::InputListen;
+// replaces nu-command's version, which only shows a stalled stream once it continues
+use crate::commands::Table;
+// replaces nu-command's version, which only shows icons together with --color
+use crate::commands::Griddle;
+// replace nu-command's stubs, which can only fail
+use crate::commands::{DateFormat, LetEnv};
+use crate::{
+    color_scheme::ColorScheme, deprecation::RemovedCommands, table_style::TableStyle,
+    theme::OutputTheme, units::DisplayUnits,
+};
 use nu_cmd_lang::*;
 use nu_command::*;
-use nu_protocol::engine::{EngineState, StateWorkingSet};
+use nu_protocol::{
+    engine::{Command, EngineState, StateWorkingSet},
+    ShellError,
+};
+
+// the commands `nu_cli::add_cli_context` adds, with their subcommands
+const CLI_COMMANDS: [&str; 3] = ["commandline", "history", "keybindings"];
+
+/// The groups of commands [`ContextBuilder`] puts together an engine from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum CommandGroup {
+    /// `def`, `let`, `if`, `use`, `help` and the rest of the language.
+    Core,
+    /// `histogram`.
+    Charts,
+    /// Commands like `where`, `each` and `sort-by` that work on lists and tables.
+    Filters,
+    /// `tutor` and `history import`/`history export`.
+    Misc,
+    /// `path` and its subcommands.
+    Path,
+    /// Running externals, `sys`, `ps` and `exec`.
+    System,
+    /// `str` and the other commands for working with text.
+    Strings,
+    /// `bytes` and its subcommands.
+    Bytes,
+    /// Commands like `ls`, `open`, `save` and `rm` that read and change files.
+    FileSystem,
+    /// The terminal, like `ansi`, `input` and `clear`, and `kill` and `sleep`.
+    Platform,
+    /// `date` and its subcommands.
+    Date,
+    /// `exit`.
+    Shells,
+    /// `from` and `to` with their formats.
+    Formats,
+    /// `table` and `grid`, which printed values are rendered with.
+    Viewers,
+    /// `into` and its subcommands.
+    Conversions,
+    /// `load-env`, `with-env` and `config`.
+    Env,
+    /// `math` and its subcommands.
+    Math,
+    /// Commands that reach other machines, like `http`, `port` and `ping`.
+    Network,
+    /// `random` and its subcommands.
+    Random,
+    /// `seq` and `cal`.
+    Generators,
+    /// `hash` and its subcommands.
+    Hash,
+    /// `source`, which `source-env` and `use` replace.
+    Deprecated,
+    /// `gstat`, with the `git` feature.
+    Git,
+    /// `query`, with the `query` feature.
+    Query,
+    /// `register` and `plugin`, with the `plugin` feature.
+    Plugin,
+    /// `keybindings`, `history` and `commandline` for configuring and inspecting the REPL.
+    Cli,
+    /// The commands whose interface may still change, like `view source`.
+    Experimental,
+}
 
-pub fn create_default_context() -> EngineState {
-    let mut engine_state = EngineState::new();
+impl CommandGroup {
+    /// The groups [`create_default_context`] adds, every one but [`CommandGroup::Experimental`].
+    pub const DEFAULT: &[CommandGroup] = &[
+        CommandGroup::Core,
+        CommandGroup::Charts,
+        CommandGroup::Filters,
+        CommandGroup::Misc,
+        CommandGroup::Path,
+        CommandGroup::System,
+        CommandGroup::Strings,
+        CommandGroup::Bytes,
+        CommandGroup::FileSystem,
+        CommandGroup::Platform,
+        CommandGroup::Date,
+        CommandGroup::Shells,
+        CommandGroup::Formats,
+        CommandGroup::Viewers,
+        CommandGroup::Conversions,
+        CommandGroup::Env,
+        CommandGroup::Math,
+        CommandGroup::Network,
+        CommandGroup::Random,
+        CommandGroup::Generators,
+        CommandGroup::Hash,
+        CommandGroup::Deprecated,
+        CommandGroup::Git,
+        CommandGroup::Query,
+        CommandGroup::Plugin,
+        CommandGroup::Cli,
+    ];
+    /// The groups [`create_sandboxed_context`] adds, the ones that only work on the data they're
+    /// given.
+    pub const SANDBOXED: &[CommandGroup] = &[
+        CommandGroup::Core,
+        CommandGroup::Filters,
+        CommandGroup::Strings,
+        CommandGroup::Bytes,
+        CommandGroup::Date,
+        CommandGroup::Formats,
+        CommandGroup::Viewers,
+        CommandGroup::Conversions,
+        CommandGroup::Math,
+        CommandGroup::Generators,
+        CommandGroup::Hash,
+    ];
+}
 
-    let delta = {
-        let mut working_set = StateWorkingSet::new(&engine_state);
+// the commands of the sandboxed groups that still reach the file system: `use` reads modules
+// from files as it's parsed, `overlay reload` once the source has run, and `touch` is bound
+// with the formats
+const SANDBOX_DENIED: [&str; 5] = [
+    "use",
+    "export use",
+    "overlay use",
+    "overlay reload",
+    "touch",
+];
+
+/// An engine with only the groups of commands an embedding needs, so scripts can't reach the
+/// network or the file system when it doesn't want them to:
+///
+/// ```no_run
+/// use nu_app::create_default_context::ContextBuilder;
+///
+/// let engine_state = ContextBuilder::new()
+///     .with_core()
+///     .with_filters()
+///     .without_network()
+///     .build()
+///     .expect("the default config is valid");
+/// ```
+///
+/// Without [`with_core`](Self::with_core) there are no `def`, `let` or `if` either.
+///
+/// Single commands are left out with [`deny_commands`](Self::deny_commands), or all but some with
+/// [`allow_commands`](Self::allow_commands):
+///
+/// ```no_run
+/// use nu_app::create_default_context::ContextBuilder;
+///
+/// let engine_state = ContextBuilder::with_defaults()
+///     .deny_commands(&["rm", "http", "exec", "run-external"])
+///     .build()
+///     .expect("the default config is valid");
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct ContextBuilder {
+    groups: BTreeSet<CommandGroup>,
+    command_filters: Vec<CommandFilter>,
+    removed_commands: RemovedCommands,
+    table_style: Option<TableStyle>,
+    color_scheme: Option<ColorScheme>,
+    output_theme: Option<OutputTheme>,
+    display_units: Option<DisplayUnits>,
+    #[cfg(feature = "plugin")]
+    plugin_file: Option<std::path::PathBuf>,
+}
 
-        macro_rules! bind_command {
-            ( $( $command:expr ),* $(,)? ) => {
-                $( working_set.add_decl(Box::new($command)); )*
-            };
-        }
+impl ContextBuilder {
+    /// A builder without any commands.
+    pub fn new() -> Self {
+        Self::default()
+    }
 
-        // Core
-        bind_command! {
-            Alias,
-            Ast,
-            Break,
-            Const,
-            Continue,
-            Debug,
-            Def,
-            DefEnv,
-            Describe,
-            Do,
-            Echo,
-            ErrorMake,
-            ExportAlias,
-            ExportCommand,
-            ExportDef,
-            ExportDefEnv,
-            ExportExtern,
-            ExportUse,
-            Extern,
-            For,
-            Help,
-            HelpAliases,
-            HelpCommands,
-            HelpModules,
-            HelpOperators,
-            Hide,
-            HideEnv,
-            If,
-            Ignore,
-            Overlay,
-            OverlayUse,
-            OverlayList,
-            OverlayNew,
-            OverlayHide,
-            Let,
-            Loop,
-            Metadata,
-            Module,
-            Mut,
-            Return,
-            Try,
-            Use,
-            Version,
-            While,
-        };
+    /// A builder with the commands of [`create_default_context`].
+    pub fn with_defaults() -> Self {
+        Self::new().with_groups(CommandGroup::DEFAULT)
+    }
 
-        // Charts
-        bind_command! {
-            Histogram
-        }
-
-        // Filters
-        bind_command! {
-            All,
-            Any,
-            Append,
-            Collect,
-            Columns,
-            Compact,
-            Default,
-            Drop,
-            DropColumn,
-            DropNth,
-            Each,
-            Empty,
-            Enumerate,
-            Every,
-            Filter,
-            Find,
-            First,
-            Flatten,
-            Get,
-            Group,
-            GroupBy,
-            Headers,
-            Insert,
-            SplitBy,
-            Take,
-            Merge,
-            Move,
-            TakeWhile,
-            TakeUntil,
-            Last,
-            Length,
-            Lines,
-            ParEach,
-            Prepend,
-            Range,
-            Reduce,
-            Reject,
-            Rename,
-            Reverse,
-            Select,
-            Shuffle,
-            Skip,
-            SkipUntil,
-            SkipWhile,
-            Sort,
-            SortBy,
-            SplitList,
-            Transpose,
-            Uniq,
-            UniqBy,
-            Upsert,
-            Update,
-            Values,
-            Where,
-            Window,
-            Wrap,
-            Zip,
-        };
+    /// A builder with the commands of [`create_sandboxed_context`].
+    pub fn sandboxed() -> Self {
+        Self::new()
+            .with_groups(CommandGroup::SANDBOXED)
+            .deny_commands(&SANDBOX_DENIED)
+    }
 
-        // Misc
-        bind_command! {
-            Tutor,
-        };
+    pub fn with(mut self, group: CommandGroup) -> Self {
+        self.groups.insert(group);
+        self
+    }
 
-        // Path
-        bind_command! {
-            Path,
-            PathBasename,
-            PathDirname,
-            PathExists,
-            PathExpand,
-            PathJoin,
-            PathParse,
-            PathRelativeTo,
-            PathSplit,
-            PathType,
-        };
+    pub fn with_groups(mut self, groups: &[CommandGroup]) -> Self {
+        self.groups.extend(groups);
+        self
+    }
 
-        // System
-        bind_command! {
-            Complete,
-            External,
-            NuCheck,
-            Sys,
-        };
+    pub fn without(mut self, group: CommandGroup) -> Self {
+        self.groups.remove(&group);
+        self
+    }
 
-        #[cfg(unix)]
-        bind_command! { Exec }
-
-        #[cfg(windows)]
-        bind_command! { RegistryQuery }
-
-        #[cfg(any(
-            target_os = "android",
-            target_os = "linux",
-            target_os = "macos",
-            target_os = "windows"
-        ))]
-        bind_command! { Ps };
-
-        #[cfg(feature = "which-support")]
-        bind_command! { Which };
-
-        // Strings
-        bind_command! {
-            Char,
-            Decode,
-            Encode,
-            DecodeBase64,
-            EncodeBase64,
-            DetectColumns,
-            Parse,
-            Size,
-            Split,
-            SplitChars,
-            SplitColumn,
-            SplitRow,
-            SplitWords,
-            Str,
-            StrCapitalize,
-            StrContains,
-            StrDowncase,
-            StrJoin,
-            StrReplace,
-            StrIndexOf,
-            StrLength,
-            StrStartsWith,
-            StrSubstring,
-            StrTrim,
-            StrUpcase
-        };
+    /// The language itself, see [`CommandGroup::Core`].
+    pub fn with_core(self) -> Self {
+        self.with(CommandGroup::Core)
+    }
 
-        // Bytes
-        bind_command! {
-            Bytes,
-            BytesLen,
-            BytesStartsWith,
-            BytesEndsWith,
-            BytesReverse,
-            BytesReplace,
-            BytesAdd,
-            BytesAt,
-            BytesIndexOf,
-            BytesCollect,
-            BytesRemove,
-            BytesBuild,
-        }
-
-        // FileSystem
-        bind_command! {
-            Cd,
-            Cp,
-            Ls,
-            Mkdir,
-            Mv,
-            Open,
-            Start,
-            Rm,
-            Save,
-            Touch,
-            Glob,
-            Watch,
-        };
+    /// The commands for lists and tables, see [`CommandGroup::Filters`].
+    pub fn with_filters(self) -> Self {
+        self.with(CommandGroup::Filters)
+    }
 
-        // Platform
-        bind_command! {
-            Ansi,
-            AnsiStrip,
-            Clear,
-            Du,
-            Input,
-            Kill,
-            Sleep,
-            TermSize,
-        };
+    /// The commands whose interface may still change, see [`CommandGroup::Experimental`].
+    pub fn with_experimental(self) -> Self {
+        self.with(CommandGroup::Experimental)
+    }
 
-        // Date
-        bind_command! {
-            Date,
-            DateFormat,
-            DateHumanize,
-            DateListTimezones,
-            DateNow,
-            DateToRecord,
-            DateToTable,
-            DateToTimezone,
-        };
+    /// Leave out the commands that reach other machines, see [`CommandGroup::Network`].
+    pub fn without_network(self) -> Self {
+        self.without(CommandGroup::Network)
+    }
 
-        // Shells
-        bind_command! {
-            Exit,
-        };
+    /// Leave out the commands that read and change files, see [`CommandGroup::FileSystem`].
+    pub fn without_file_system(self) -> Self {
+        self.without(CommandGroup::FileSystem)
+    }
 
-        // Formats
-        bind_command! {
-            From,
-            FromCsv,
-            FromJson,
-            FromNuon,
-            FromOds,
-            FromSsv,
-            FromToml,
-            FromTsv,
-            FromXlsx,
-            FromXml,
-            FromYaml,
-            FromYml,
-            To,
-            ToCsv,
-            ToJson,
-            ToMd,
-            ToNuon,
-            ToText,
-            ToToml,
-            ToTsv,
-            Touch,
-            Use,
-            Upsert,
-            Where,
-            ToXml,
-            ToYaml,
-        };
+    /// Only add the commands named in `names` of the groups the builder has. A name includes
+    /// the subcommands, `str` keeps `str join` as well.
+    pub fn allow_commands(self, names: &[&str]) -> Self {
+        let names: BTreeSet<String> = names.iter().map(|name| name.to_string()).collect();
+        self.filter_commands(move |command| names_command(|name| names.contains(name), command))
+    }
 
-        // Viewers
-        bind_command! {
-            Griddle,
-            Table,
-        };
+    /// Leave out the commands named in `names` and their subcommands, e.g. `rm`, `http` and
+    /// `exec`, whatever groups they're in.
+    pub fn deny_commands(self, names: &[&str]) -> Self {
+        let names: BTreeSet<String> = names.iter().map(|name| name.to_string()).collect();
+        self.filter_commands(move |command| !names_command(|name| names.contains(name), command))
+    }
 
-        // Conversions
-        bind_command! {
-            Into,
-            IntoBool,
-            IntoBinary,
-            IntoDatetime,
-            IntoDecimal,
-            IntoDuration,
-            IntoFilesize,
-            IntoInt,
-            IntoRecord,
-            IntoString,
-        };
+    /// Only add the commands `keep` is true for, it's called with the whole name of each, like
+    /// `str join`. With more than one filter a command has to pass all of them.
+    pub fn filter_commands(mut self, keep: impl Fn(&str) -> bool + Send + Sync + 'static) -> Self {
+        self.command_filters.push(CommandFilter(Arc::new(keep)));
+        self
+    }
 
-        // Env
-        bind_command! {
-            ExportEnv,
-            LetEnv,
-            LoadEnv,
-            SourceEnv,
-            WithEnv,
-            ConfigNu,
-            ConfigEnv,
-            ConfigMeta,
-            ConfigReset,
-        };
+    /// What the removed commands like `let-env` do, they error by default.
+    pub fn removed_commands(mut self, handling: RemovedCommands) -> Self {
+        self.removed_commands = handling;
+        self
+    }
 
-        // Math
-        bind_command! {
-            Math,
-            MathAbs,
-            MathAvg,
-            MathCeil,
-            MathFloor,
-            MathMax,
-            MathMedian,
-            MathMin,
-            MathMode,
-            MathProduct,
-            MathRound,
-            MathSqrt,
-            MathStddev,
-            MathSum,
-            MathVariance,
-            MathLog,
-        };
+    pub fn table_style(mut self, table_style: TableStyle) -> Self {
+        self.table_style = Some(table_style);
+        self
+    }
 
-        // Network
-        bind_command! {
-            Http,
-            HttpGet,
-            HttpPost,
-            Url,
-            UrlBuildQuery,
-            UrlEncode,
-            UrlJoin,
-            UrlParse,
-            Port,
-        }
-
-        // Random
-        bind_command! {
-            Random,
-            RandomBool,
-            RandomChars,
-            RandomDecimal,
-            RandomDice,
-            RandomInteger,
-            RandomUuid,
-        };
+    pub fn color_scheme(mut self, color_scheme: ColorScheme) -> Self {
+        self.color_scheme = Some(color_scheme);
+        self
+    }
 
-        // Generators
-        bind_command! {
-            Cal,
-            Seq,
-            SeqDate,
-            SeqChar,
-        };
+    pub fn output_theme(mut self, output_theme: OutputTheme) -> Self {
+        self.output_theme = Some(output_theme);
+        self
+    }
 
-        // Hash
-        bind_command! {
-            Hash,
-            HashMd5::default(),
-            HashSha256::default(),
-        };
+    pub fn display_units(mut self, display_units: DisplayUnits) -> Self {
+        self.display_units = Some(display_units);
+        self
+    }
 
-        // Experimental
-        bind_command! {
-            ViewSource,
-            IsAdmin,
-        };
+    /// Load the plugins of this registry, see
+    /// [`load_plugin_file`](crate::plugins::load_plugin_file).
+    #[cfg(feature = "plugin")]
+    pub fn plugin_file(mut self, path: std::path::PathBuf) -> Self {
+        self.plugin_file = Some(path);
+        self
+    }
 
-        // Deprecated
-        bind_command! {
-            Source,
+    /// Create the engine, failing if the table style or the display units aren't valid.
+    ///
+    /// A plugin registry that doesn't load is reported, the engine is built without its plugins.
+    pub fn build(&self) -> Result<EngineState, ShellError> {
+        let keep = |command: &str| {
+            self.command_filters
+                .iter()
+                .all(|filter| (filter.0)(command))
         };
+        let mut engine_state = add_command_groups(
+            EngineState::new(),
+            &self.groups,
+            self.removed_commands,
+            &keep,
+        );
 
         #[cfg(feature = "plugin")]
-        bind_command!(Register);
+        if let Some(path) = &self.plugin_file {
+            if let Err(err) = crate::plugins::load_plugin_file(&mut engine_state, path) {
+                crate::helpers::report_error_new(&engine_state, &err);
+            }
+        }
+
+        if let Some(table_style) = &self.table_style {
+            table_style.apply(&mut engine_state)?;
+        }
+        if let Some(color_scheme) = &self.color_scheme {
+            color_scheme.apply(&mut engine_state);
+        }
+        if let Some(output_theme) = &self.output_theme {
+            output_theme.apply(&mut engine_state);
+        }
+        if let Some(display_units) = &self.display_units {
+            display_units.apply(&mut engine_state)?;
+        }
+
+        Ok(engine_state)
+    }
+}
+
+pub fn create_default_context() -> EngineState {
+    let groups = CommandGroup::DEFAULT.iter().copied().collect();
+    add_command_groups(
+        EngineState::new(),
+        &groups,
+        RemovedCommands::default(),
+        &|_| true,
+    )
+}
+
+/// An engine for scripts that can't be trusted with the machine they run on, like ones users
+/// hand to a server.
+///
+/// It only has the commands that work on the data they're given, the ones of
+/// [`CommandGroup::SANDBOXED`]: the language, filters, strings, math, formats and conversions.
+/// There's nothing to read or change files, reach the network, look at the system or run
+/// externals with. `use` and `overlay reload` are left out as well, as they read modules from
+/// files.
+///
+/// ```
+/// use nu_app::create_sandboxed_context;
+///
+/// let engine_state = create_sandboxed_context();
+/// assert!(engine_state.find_decl(b"overlay reload", &[]).is_none());
+/// assert!(engine_state.find_decl(b"use", &[]).is_none());
+/// assert!(engine_state.find_decl(b"where", &[]).is_some());
+/// ```
+pub fn create_sandboxed_context() -> EngineState {
+    let groups = CommandGroup::SANDBOXED.iter().copied().collect();
+    add_command_groups(
+        EngineState::new(),
+        &groups,
+        RemovedCommands::default(),
+        &|command| !names_command(|name| SANDBOX_DENIED.contains(&name), command),
+    )
+}
+
+/// Add the commands whose interface may still change, like `view source`.
+///
+/// They're left out of [`create_default_context`], so an embedding only exposes the stable
+/// commands unless it opts in to these.
+pub fn add_experimental_context(engine_state: EngineState) -> EngineState {
+    let groups = BTreeSet::from([CommandGroup::Experimental]);
+    add_command_groups(engine_state, &groups, RemovedCommands::default(), &|_| true)
+}
+
+// a predicate of `ContextBuilder::filter_commands`
+#[derive(Clone)]
+struct CommandFilter(Arc<dyn Fn(&str) -> bool + Send + Sync>);
+
+impl fmt::Debug for CommandFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("CommandFilter")
+    }
+}
+
+// whether `command` is one of the names `is_name` is true for, or a subcommand of one
+fn names_command(is_name: impl Fn(&str) -> bool, command: &str) -> bool {
+    is_name(command)
+        || command
+            .match_indices(' ')
+            .any(|(end, _)| is_name(&command[..end]))
+}
+
+fn add_command_groups(
+    mut engine_state: EngineState,
+    groups: &BTreeSet<CommandGroup>,
+    removed_commands: RemovedCommands,
+    keep: &dyn Fn(&str) -> bool,
+) -> EngineState {
+    let delta = {
+        let mut working_set = StateWorkingSet::new(&engine_state);
+        for group in groups {
+            add_command_group(&mut working_set, *group, removed_commands, keep);
+        }
 
         working_set.render()
     };
@@ -405,5 +416,486 @@ pub fn create_default_context() -> EngineState {
         eprintln!("Error creating default context: {err:?}");
     }
 
+    if groups.contains(&CommandGroup::Cli) {
+        engine_state = nu_cli::add_cli_context(engine_state);
+
+        // replaces nu-cli's `history`, which reads nushell's history instead of the REPL's
+        let delta = {
+            let mut working_set = StateWorkingSet::new(&engine_state);
+            working_set.add_decl(Box::new(History));
+            working_set.render()
+        };
+        if let Err(err) = engine_state.merge_delta(delta) {
+            eprintln!("Error creating default context: {err:?}");
+        }
+
+        // nu-cli adds its commands itself, the ones filtered out are hidden again
+        let filtered: Vec<Vec<u8>> = engine_state
+            .get_decls_sorted(false)
+            .map(|(name, _)| String::from_utf8_lossy(&name).into_owned())
+            .filter(|name| names_command(|name| CLI_COMMANDS.contains(&name), name) && !keep(name))
+            .map(String::into_bytes)
+            .collect();
+        if !filtered.is_empty() {
+            let mut working_set = StateWorkingSet::new(&engine_state);
+            working_set.hide_decls(&filtered);
+            let delta = working_set.render();
+            if let Err(err) = engine_state.merge_delta(delta) {
+                eprintln!("Error creating default context: {err:?}");
+            }
+        }
+    }
+
+    // printed values are rendered with `table`, like they are in nu
+    engine_state.table_decl_id = engine_state.find_decl("table".as_bytes(), &[]);
+    crate::helpers::add_default_paths(&mut engine_state);
+
     engine_state
 }
+
+fn add_command_group(
+    working_set: &mut StateWorkingSet,
+    group: CommandGroup,
+    removed_commands: RemovedCommands,
+    keep: &dyn Fn(&str) -> bool,
+) {
+    macro_rules! bind_command {
+        ( $( $command:expr ),* $(,)? ) => {
+            $(
+                let command = $command;
+                if keep(command.name()) {
+                    working_set.add_decl(Box::new(command));
+                }
+            )*
+        };
+    }
+
+    match group {
+        CommandGroup::Core => {
+            bind_command! {
+                Alias,
+                Ast,
+                Break,
+                Const,
+                Continue,
+                Debug,
+                Def,
+                DefEnv,
+                Describe,
+                Do,
+                Echo,
+                ErrorMake,
+                ExportAlias,
+                ExportCommand,
+                ExportDef,
+                ExportDefEnv,
+                ExportExtern,
+                ExportUse,
+                Extern,
+                For,
+                Help,
+                HelpAliases,
+                HelpCommands,
+                HelpModules,
+                HelpOperators,
+                Hide,
+                HideEnv,
+                If,
+                Ignore,
+                Overlay,
+                OverlayUse,
+                OverlayList,
+                OverlayNew,
+                OverlayHide,
+                OverlayReload,
+                Let,
+                Loop,
+                Metadata,
+                Module,
+                Mut,
+                Return,
+                Try,
+                Use,
+                Version,
+                While,
+            };
+        }
+        CommandGroup::Charts => {
+            bind_command! {
+                Histogram
+            }
+        }
+        CommandGroup::Filters => {
+            bind_command! {
+                All,
+                Any,
+                Append,
+                Collect,
+                Columns,
+                Compact,
+                Default,
+                Drop,
+                DropColumn,
+                DropNth,
+                Each,
+                Empty,
+                Enumerate,
+                Every,
+                Filter,
+                Find,
+                First,
+                Flatten,
+                Get,
+                Group,
+                GroupBy,
+                Headers,
+                Insert,
+                SplitBy,
+                Take,
+                Merge,
+                Move,
+                TakeWhile,
+                TakeUntil,
+                Last,
+                Length,
+                Lines,
+                ParEach,
+                Prepend,
+                Range,
+                Reduce,
+                Reject,
+                Rename,
+                Reverse,
+                Select,
+                Shuffle,
+                Skip,
+                SkipUntil,
+                SkipWhile,
+                Sort,
+                SortBy,
+                SplitList,
+                Transpose,
+                Uniq,
+                UniqBy,
+                Upsert,
+                Update,
+                Values,
+                Where,
+                Window,
+                Wrap,
+                Zip,
+            };
+        }
+        CommandGroup::Misc => {
+            bind_command! {
+                HistoryExport,
+                HistoryImport,
+                Tutor,
+            };
+        }
+        CommandGroup::Path => {
+            bind_command! {
+                Path,
+                PathBasename,
+                PathDirname,
+                PathExists,
+                PathExpand,
+                PathJoin,
+                PathParse,
+                PathRelativeTo,
+                PathSplit,
+                PathType,
+            };
+        }
+        CommandGroup::System => {
+            bind_command! {
+                Complete,
+                External,
+                NuCheck,
+                Sys,
+                SysTemp,
+            };
+
+            #[cfg(feature = "battery")]
+            bind_command! { SysBattery }
+
+            #[cfg(feature = "pty")]
+            bind_command! { Pty }
+
+            #[cfg(unix)]
+            bind_command! { Exec }
+
+            #[cfg(windows)]
+            bind_command! { RegistryQuery }
+
+            #[cfg(any(
+                target_os = "android",
+                target_os = "linux",
+                target_os = "macos",
+                target_os = "windows"
+            ))]
+            bind_command! { Ps };
+
+            #[cfg(feature = "which-support")]
+            bind_command! { Which };
+        }
+        CommandGroup::Strings => {
+            bind_command! {
+                Char,
+                Decode,
+                Encode,
+                DecodeBase64,
+                EncodeBase64,
+                DetectColumns,
+                FormatDate,
+                Parse,
+                Size,
+                Split,
+                SplitChars,
+                SplitColumn,
+                SplitRow,
+                SplitWords,
+                Str,
+                StrCapitalize,
+                StrContains,
+                StrDowncase,
+                StrJoin,
+                StrReplace,
+                StrIndexOf,
+                StrLength,
+                StrStartsWith,
+                StrSubstring,
+                StrTrim,
+                StrUpcase
+            };
+        }
+        CommandGroup::Bytes => {
+            bind_command! {
+                Bytes,
+                BytesLen,
+                BytesStartsWith,
+                BytesEndsWith,
+                BytesReverse,
+                BytesReplace,
+                BytesAdd,
+                BytesAt,
+                BytesIndexOf,
+                BytesCollect,
+                BytesRemove,
+                BytesBuild,
+            }
+        }
+        CommandGroup::FileSystem => {
+            bind_command! {
+                Cd,
+                Cp,
+                Ls,
+                Mkdir,
+                Mv,
+                Open,
+                Start,
+                Rm,
+                Save,
+                Touch,
+                Glob,
+                Watch,
+            };
+        }
+        CommandGroup::Platform => {
+            bind_command! {
+                Ansi,
+                AnsiStrip,
+                Clear,
+                Du,
+                Input,
+                InputListen,
+                Kill,
+                Sleep,
+                TermSize,
+            };
+        }
+        CommandGroup::Date => {
+            bind_command! {
+                Date,
+                DateHumanize,
+                DateListTimezones,
+                DateNow,
+                DateToRecord,
+                DateToTable,
+                DateToTimezone,
+            };
+
+            if removed_commands != RemovedCommands::Hide {
+                bind_command! { DateFormat::new(removed_commands) }
+            }
+        }
+        CommandGroup::Shells => {
+            bind_command! {
+                Exit,
+            };
+        }
+        CommandGroup::Formats => {
+            bind_command! {
+                From,
+                FromCsv,
+                FromGitLog,
+                FromJson,
+                FromNuon,
+                FromOds,
+                FromSsv,
+                FromToml,
+                FromTsv,
+                FromXlsx,
+                FromXml,
+                FromYaml,
+                FromYml,
+                To,
+                ToCsv,
+                ToJson,
+                ToMd,
+                ToNuon,
+                ToText,
+                ToToml,
+                ToTsv,
+                Touch,
+                Use,
+                Upsert,
+                Where,
+                ToXml,
+                ToYaml,
+            };
+        }
+        CommandGroup::Viewers => {
+            bind_command! {
+                Griddle,
+                Table,
+            };
+        }
+        CommandGroup::Conversions => {
+            bind_command! {
+                Into,
+                IntoBool,
+                IntoBinary,
+                IntoDatetime,
+                IntoDecimal,
+                IntoDuration,
+                IntoFilesize,
+                IntoInt,
+                IntoRecord,
+                IntoString,
+            };
+        }
+        CommandGroup::Env => {
+            bind_command! {
+                ExportEnv,
+                LoadEnv,
+                SourceEnv,
+                WithEnv,
+                ConfigNu,
+                ConfigEnv,
+                ConfigMeta,
+                ConfigReset,
+            };
+
+            if removed_commands != RemovedCommands::Hide {
+                bind_command! { LetEnv::new(removed_commands) }
+            }
+        }
+        CommandGroup::Math => {
+            bind_command! {
+                Math,
+                MathAbs,
+                MathAvg,
+                MathCeil,
+                MathFloor,
+                MathMax,
+                MathMedian,
+                MathMin,
+                MathMode,
+                MathProduct,
+                MathRound,
+                MathSqrt,
+                MathStddev,
+                MathSum,
+                MathVariance,
+                MathLog,
+            };
+        }
+        CommandGroup::Network => {
+            bind_command! {
+                Http,
+                HttpGet,
+                HttpPost,
+                Url,
+                UrlBuildQuery,
+                UrlEncode,
+                UrlJoin,
+                UrlParse,
+                Port,
+                Ping,
+            }
+
+            #[cfg(feature = "dns")]
+            bind_command! {
+                Dns,
+                DnsQuery,
+            }
+        }
+        CommandGroup::Random => {
+            bind_command! {
+                Random,
+                RandomBool,
+                RandomChars,
+                RandomDecimal,
+                RandomDice,
+                RandomInteger,
+                RandomUuid,
+            };
+        }
+        CommandGroup::Generators => {
+            bind_command! {
+                Cal,
+                Seq,
+                SeqDate,
+                SeqChar,
+            };
+        }
+        CommandGroup::Hash => {
+            bind_command! {
+                Hash,
+                HashMd5::default(),
+                HashSha256::default(),
+            };
+        }
+        CommandGroup::Deprecated => {
+            bind_command! {
+                Source,
+            };
+        }
+        CommandGroup::Git => {
+            #[cfg(feature = "git")]
+            bind_command! { GStat }
+        }
+        CommandGroup::Query => {
+            #[cfg(feature = "query")]
+            for command in InProcessPlugin::<nu_plugin_query::Query>::commands() {
+                if keep(command.name()) {
+                    working_set.add_decl(command);
+                }
+            }
+        }
+        CommandGroup::Plugin => {
+            #[cfg(feature = "plugin")]
+            bind_command!(Register, Plugin, PluginAdd, PluginRm, PluginStop);
+        }
+        CommandGroup::Cli => {
+            // nu-cli adds them itself, once the working set is merged
+        }
+        CommandGroup::Experimental => {
+            bind_command! {
+                ViewSource,
+                IsAdmin,
+            }
+        }
+    }
+}
@@ -0,0 +1,280 @@
+#[cfg(any(unix, feature = "serve"))]
+use std::sync::atomic::{AtomicUsize, Ordering};
+#[cfg(unix)]
+use std::{
+    io::{self, BufRead, BufReader, Read, Write},
+    os::unix::{
+        fs::FileTypeExt,
+        net::{UnixListener, UnixStream},
+    },
+    path::Path,
+    thread,
+};
+use std::{
+    sync::{atomic::AtomicBool, Arc},
+    time::Duration,
+};
+
+use nu_protocol::{
+    engine::{EngineState, StateWorkingSet},
+    PipelineData, Span, Value,
+};
+use serde::Deserialize;
+use serde_json::{json, Value as Json};
+
+#[cfg(unix)]
+use crate::set_use_colors;
+use crate::{
+    create_stack, eval_source_to_value, eval_source_with_timeout, format_error,
+    values::{from_value, serde_json_to_value},
+};
+
+/// How long a source may run by default before the daemon or the HTTP server stops it.
+pub const DEFAULT_EVAL_TIMEOUT: Duration = Duration::from_secs(30);
+
+// the longest line a client of the daemon may send, 16 MiB
+#[cfg(unix)]
+const MAX_LINE_LEN: usize = 16 * 1024 * 1024;
+// how many clients the daemon and the HTTP server serve at once
+#[cfg(any(unix, feature = "serve"))]
+pub(crate) const MAX_CLIENTS: usize = 64;
+
+/// A source for [`eval_request`] to evaluate, as the JSON a client sends.
+#[derive(Debug, Deserialize)]
+pub(crate) struct EvalRequest {
+    pub source: String,
+    /// Environment variables to set before it runs, like `PWD`, as JSON values.
+    #[serde(default)]
+    pub env: serde_json::Map<String, Json>,
+    /// The text that's the input of the source, `input` to the HTTP server.
+    #[serde(alias = "input")]
+    pub stdin: Option<String>,
+}
+
+/// What a source of an [`EvalRequest`] evaluated to.
+pub(crate) struct Evaluation {
+    /// The JSON of the output, or the rendered error.
+    pub value: Result<Json, String>,
+    pub exit_code: i64,
+}
+
+impl Evaluation {
+    fn error(error: String, exit_code: i64) -> Self {
+        Self {
+            value: Err(error),
+            exit_code,
+        }
+    }
+
+    /// The answer to a client, `value` or `error` with the `exit_code`.
+    pub fn to_json(&self) -> Json {
+        match &self.value {
+            Ok(value) => json!({ "value": value, "exit_code": self.exit_code }),
+            Err(error) => json!({ "error": error, "exit_code": self.exit_code }),
+        }
+    }
+}
+
+/// Keep `engine_state` warm and evaluate the sources clients send to the Unix socket at
+/// `path`, like the `--daemon` flag of the binary does, instead of starting a process for
+/// each of them.
+///
+/// A client sends a request on a line of JSON, like
+/// `{"source": "ls | length", "env": {"PWD": "/tmp"}, "stdin": "text"}`, and gets the answer
+/// of [`eval_request`] on a line, for as many requests as it likes. Each source runs on its
+/// own copy of the engine, so what one defines isn't seen by the next, and is stopped once it
+/// has run for `timeout`. A socket left behind by a daemon that's gone is replaced, anything
+/// else at `path` is an `AlreadyExists` error.
+///
+/// Only the user the daemon runs as can connect to the socket. At most 64 clients are served
+/// at once, the ones after them get an error and are disconnected.
+#[cfg(unix)]
+pub fn serve_daemon(
+    mut engine_state: EngineState,
+    path: impl AsRef<Path>,
+    timeout: Duration,
+) -> io::Result<()> {
+    let path = path.as_ref();
+    match std::fs::symlink_metadata(path) {
+        Ok(metadata) if !metadata.file_type().is_socket() => {
+            return Err(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                format!("{} exists and isn't a socket", path.display()),
+            ));
+        }
+        Ok(_) if UnixStream::connect(path).is_err() => std::fs::remove_file(path)?,
+        _ => {}
+    }
+    // the socket is created with the permissions the umask leaves, which mustn't let other
+    // users connect, not even for the moment before they could be changed
+    let umask = unsafe { libc::umask(0o177) };
+    let listener = UnixListener::bind(path);
+    unsafe { libc::umask(umask) };
+    let listener = listener?;
+
+    // the answers are JSON, the errors in them aren't shown on a terminal
+    set_use_colors(&mut engine_state, &mut create_stack(), false);
+    let engine_state = Arc::new(engine_state);
+    let clients = Arc::new(AtomicUsize::new(0));
+
+    for stream in listener.incoming() {
+        let mut stream = stream?;
+        let Some(slot) = ClientSlot::take(&clients) else {
+            let busy = Evaluation::error("the daemon is serving too many clients".into(), 1);
+            let _ = writeln!(stream, "{}", busy.to_json());
+            continue;
+        };
+        let engine_state = engine_state.clone();
+        thread::Builder::new()
+            .name("daemon client".into())
+            .spawn(move || {
+                let _slot = slot;
+                serve_client(&engine_state, stream, timeout)
+            })?;
+    }
+
+    Ok(())
+}
+
+#[cfg(unix)]
+fn serve_client(
+    engine_state: &EngineState,
+    stream: UnixStream,
+    timeout: Duration,
+) -> io::Result<()> {
+    let mut writer = stream.try_clone()?;
+    let mut reader = BufReader::new(stream);
+    loop {
+        let mut line = vec![];
+        let limit = MAX_LINE_LEN as u64 + 1;
+        if reader.by_ref().take(limit).read_until(b'\n', &mut line)? == 0 {
+            return Ok(());
+        }
+        if line.len() > MAX_LINE_LEN {
+            // the rest of the line would be read as the next request
+            let error = format!("the request is longer than {MAX_LINE_LEN} bytes");
+            writeln!(writer, "{}", Evaluation::error(error, 1).to_json())?;
+            return Ok(());
+        }
+
+        let response = match std::str::from_utf8(&line) {
+            Ok(request) => answer(engine_state, request.trim_end(), Some(timeout)),
+            Err(_) => Evaluation::error("the request isn't UTF-8".into(), 1)
+                .to_json()
+                .to_string(),
+        };
+        writeln!(writer, "{response}")?;
+        writer.flush()?;
+    }
+}
+
+/// A place for one of the [`MAX_CLIENTS`] clients served at once, given back when it's dropped.
+#[cfg(any(unix, feature = "serve"))]
+pub(crate) struct ClientSlot(Arc<AtomicUsize>);
+
+#[cfg(any(unix, feature = "serve"))]
+impl ClientSlot {
+    /// A place counted in `clients`, or `None` when they're all taken.
+    pub fn take(clients: &Arc<AtomicUsize>) -> Option<Self> {
+        clients
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |count| {
+                (count < MAX_CLIENTS).then_some(count + 1)
+            })
+            .ok()?;
+        Some(ClientSlot(clients.clone()))
+    }
+}
+
+#[cfg(any(unix, feature = "serve"))]
+impl Drop for ClientSlot {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Evaluate the source of a request of [`serve_daemon`] on a copy of `engine_state`, and
+/// answer with the JSON of its output as `value`, or of the error it ran into as `error`,
+/// both with the `exit_code` of the source.
+///
+/// ```
+/// use nu_app::{create_engine_state, daemon::eval_request};
+///
+/// let engine_state = create_engine_state();
+/// let request = r#"{"source": "$in | str upcase | { text: $in, mode: $env.MODE }", "env": {"MODE": "fast"}, "stdin": "hi"}"#;
+///
+/// let response: serde_json::Value = serde_json::from_str(&eval_request(&engine_state, request)).unwrap();
+/// assert_eq!(response["value"], serde_json::json!({ "text": "HI", "mode": "fast" }));
+/// assert_eq!(response["exit_code"], 0);
+/// ```
+pub fn eval_request(engine_state: &EngineState, request: &str) -> String {
+    answer(engine_state, request, None)
+}
+
+fn answer(engine_state: &EngineState, request: &str, timeout: Option<Duration>) -> String {
+    let evaluation = match serde_json::from_str(request) {
+        Ok(request) => evaluate(engine_state, &request, "daemon", timeout),
+        Err(err) => Evaluation::error(format!("the request isn't valid: {err}"), 1),
+    };
+
+    evaluation.to_json().to_string()
+}
+
+/// Evaluate the source of `request` on a copy of `engine_state` with a stack of its own, with
+/// `fname` as the name errors show for it, stopping it once it has run for `timeout` if
+/// there's one.
+pub(crate) fn evaluate(
+    engine_state: &EngineState,
+    request: &EvalRequest,
+    fname: &str,
+    timeout: Option<Duration>,
+) -> Evaluation {
+    let mut engine_state = engine_state.clone();
+    // a flag of its own, the copies share the engine's, and stopping this source mustn't
+    // stop the others, nor an interrupted one the ones after it
+    engine_state.ctrlc = Some(Arc::new(AtomicBool::new(false)));
+    let mut stack = create_stack();
+    for (name, value) in &request.env {
+        let value = serde_json_to_value(value.clone(), Span::unknown());
+        stack.add_env_var(name.clone(), value);
+    }
+    let input = match &request.stdin {
+        Some(stdin) => PipelineData::Value(Value::string(stdin, Span::unknown()), None),
+        None => PipelineData::Empty,
+    };
+
+    let source = request.source.as_bytes();
+    let result = match timeout {
+        Some(timeout) => eval_source_with_timeout(
+            &mut engine_state,
+            &mut stack,
+            source,
+            fname,
+            input,
+            true,
+            timeout,
+        ),
+        None => eval_source_to_value(&mut engine_state, &mut stack, source, fname, input, true),
+    };
+    let exit_code = stack
+        .get_env_var(&engine_state, "LAST_EXIT_CODE")
+        .and_then(|exit_code| exit_code.as_i64().ok())
+        .unwrap_or(0);
+
+    match result.map(|value| from_value::<Json>(&value)) {
+        Ok(Ok(value)) => Evaluation {
+            value: Ok(value),
+            exit_code,
+        },
+        Ok(Err(err)) => error_evaluation(&engine_state, &err, 1),
+        Err(err) => error_evaluation(&engine_state, &err, exit_code.max(1)),
+    }
+}
+
+fn error_evaluation(
+    engine_state: &EngineState,
+    error: &(dyn miette::Diagnostic + Send + Sync + 'static),
+    exit_code: i64,
+) -> Evaluation {
+    let error = format_error(&StateWorkingSet::new(engine_state), error);
+    Evaluation::error(error, exit_code)
+}
@@ -0,0 +1,29 @@
+use std::str::FromStr;
+
+/// What running a command nu has removed, like `let-env` or `date format`, does.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RemovedCommands {
+    /// Fail with an error that names the replacement, like nu does.
+    #[default]
+    Error,
+    /// Warn once per command, then run the replacement, so old scripts keep working while
+    /// they're migrated.
+    Warn,
+    /// Leave the commands out, so their names are free for externals and custom commands.
+    Hide,
+}
+
+impl FromStr for RemovedCommands {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "error" => Ok(RemovedCommands::Error),
+            "warn" => Ok(RemovedCommands::Warn),
+            "hide" => Ok(RemovedCommands::Hide),
+            _ => Err(format!(
+                "unknown handling '{s}' for removed commands, expected error, warn or hide"
+            )),
+        }
+    }
+}
@@ -0,0 +1,500 @@
+use std::{
+    fmt, io,
+    path::PathBuf,
+    str::FromStr,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, PoisonError, RwLock,
+    },
+};
+
+use miette::{
+    Diagnostic, GraphicalReportHandler, GraphicalTheme, LabeledSpan, Severity, ThemeCharacters,
+    ThemeStyles,
+};
+use nu_protocol::{engine::StateWorkingSet, CliError, ParseError, ShellError, Span};
+use serde_json::{json, Value as JsonValue};
+
+use crate::helpers::{format_error, report_error};
+
+static JSON_ERRORS: AtomicBool = AtomicBool::new(false);
+static ERROR_STYLE: RwLock<Option<ErrorStyle>> = RwLock::new(None);
+
+/// How [`report_error`](crate::helpers::report_error) writes errors to stderr.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ErrorFormat {
+    /// miette's rendering, with the source snippets underlined.
+    #[default]
+    Human,
+    /// One JSON object per error and line, for tools that show errors in their own UI.
+    Json,
+}
+
+impl FromStr for ErrorFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "human" => Ok(ErrorFormat::Human),
+            "json" => Ok(ErrorFormat::Json),
+            _ => Err(format!(
+                "unknown error format '{s}', expected human or json"
+            )),
+        }
+    }
+}
+
+/// Have [`report_error`](crate::helpers::report_error) write errors in `format` from now on,
+/// like `--error-format` does. It's the same for all engines of the process.
+pub fn set_error_format(format: ErrorFormat) {
+    JSON_ERRORS.store(format == ErrorFormat::Json, Ordering::Relaxed);
+}
+
+/// The format [`set_error_format`] picked, `Human` until it's called.
+pub fn error_format() -> ErrorFormat {
+    match JSON_ERRORS.load(Ordering::Relaxed) {
+        true => ErrorFormat::Json,
+        false => ErrorFormat::Human,
+    }
+}
+
+/// How errors are drawn in the [`ErrorFormat::Human`] format, for hosts that show them in a pane
+/// of their own rather than a terminal.
+///
+/// Until [`set_error_style`] is called errors are drawn like nu draws them, the options that
+/// aren't set keep nu's choice:
+///
+/// ```
+/// use nu_app::errors::{set_error_style, ErrorStyle};
+/// use nu_app::nu_protocol::{engine::StateWorkingSet, PipelineData};
+/// use nu_app::{create_engine_state, create_stack, eval_source_result, format_error};
+///
+/// set_error_style(ErrorStyle::new().width(60).colors(false).footer(false));
+///
+/// let mut engine_state = create_engine_state();
+/// let mut stack = create_stack();
+/// let source = b"let x: int = 'one'";
+/// let err = eval_source_result(&mut engine_state, &mut stack, source, "app", PipelineData::Empty, true)
+///     .unwrap_err();
+///
+/// let rendered = format_error(&StateWorkingSet::new(&engine_state), &err);
+/// assert!(rendered.is_ascii());
+/// assert!(!rendered.contains("help:"));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct ErrorStyle {
+    width: Option<usize>,
+    ascii: Option<bool>,
+    colors: Option<bool>,
+    styles: Option<ThemeStyles>,
+    hide_footer: bool,
+}
+
+impl ErrorStyle {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Wrap errors at this many columns instead of the width of the terminal.
+    pub fn width(mut self, width: usize) -> Self {
+        self.width = Some(width);
+        self
+    }
+
+    /// Draw the boxes and arrows with ASCII characters instead of Unicode ones. Without colors
+    /// they're ASCII by default.
+    pub fn ascii(mut self, ascii: bool) -> Self {
+        self.ascii = Some(ascii);
+        self
+    }
+
+    /// Whether to color errors, instead of going by `$env.config.use_ansi_coloring`.
+    pub fn colors(mut self, colors: bool) -> Self {
+        self.colors = Some(colors);
+        self
+    }
+
+    /// The colors of the message, the labels, the help and the line numbers when errors are
+    /// colored, `ThemeStyles::ansi()` by default, which follows the terminal's theme.
+    pub fn theme(mut self, styles: ThemeStyles) -> Self {
+        self.styles = Some(styles);
+        self
+    }
+
+    /// Whether to show the help and the link to the docs below the snippets.
+    pub fn footer(mut self, footer: bool) -> Self {
+        self.hide_footer = !footer;
+        self
+    }
+}
+
+/// Have errors drawn in `style` from now on, for all engines of the process.
+pub fn set_error_style(style: ErrorStyle) {
+    *ERROR_STYLE.write().unwrap_or_else(PoisonError::into_inner) = Some(style);
+}
+
+// an error the way `CliError` draws it, in the style picked with `set_error_style`
+pub(crate) fn render_error(
+    working_set: &StateWorkingSet,
+    error: &(dyn Diagnostic + Send + Sync + 'static),
+) -> String {
+    let style = ERROR_STYLE.read().unwrap_or_else(PoisonError::into_inner);
+    let Some(style) = style.as_ref() else {
+        return format!("{:?}", CliError(error, working_set));
+    };
+
+    let colors = style
+        .colors
+        .unwrap_or(working_set.get_config().use_ansi_coloring);
+    let theme = GraphicalTheme {
+        characters: match style.ascii.unwrap_or(!colors) {
+            true => ThemeCharacters::ascii(),
+            false => ThemeCharacters::unicode(),
+        },
+        styles: match colors {
+            true => style.styles.clone().unwrap_or_else(ThemeStyles::ansi),
+            false => ThemeStyles::none(),
+        },
+    };
+    let width = style
+        .width
+        .or_else(|| {
+            crossterm::terminal::size()
+                .ok()
+                .map(|(cols, _)| cols as usize)
+        })
+        .unwrap_or(80);
+    let handler = GraphicalReportHandler::new_themed(theme)
+        .with_width(width)
+        .with_links(false)
+        .with_urls(!style.hide_footer);
+
+    // like `CliError`, a span that isn't in the working set leaves the error unfinished
+    // instead of panicking
+    let mut rendered = String::new();
+    let error = CliError(error, working_set);
+    let _ = match style.hide_footer {
+        true => handler.render_report(&mut rendered, &WithoutHelp(&error)),
+        false => handler.render_report(&mut rendered, &error),
+    };
+
+    rendered
+}
+
+// a diagnostic without its help, which miette draws below the snippets
+#[derive(Debug)]
+struct WithoutHelp<'a>(&'a dyn Diagnostic);
+
+impl fmt::Display for WithoutHelp<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self.0, f)
+    }
+}
+
+impl std::error::Error for WithoutHelp<'_> {}
+
+impl Diagnostic for WithoutHelp<'_> {
+    fn code<'a>(&'a self) -> Option<Box<dyn fmt::Display + 'a>> {
+        self.0.code()
+    }
+
+    fn severity(&self) -> Option<Severity> {
+        self.0.severity()
+    }
+
+    fn source_code(&self) -> Option<&dyn miette::SourceCode> {
+        self.0.source_code()
+    }
+
+    fn labels(&self) -> Option<Box<dyn Iterator<Item = LabeledSpan> + '_>> {
+        self.0.labels()
+    }
+
+    fn related<'a>(&'a self) -> Option<Box<dyn Iterator<Item = &'a dyn Diagnostic> + 'a>> {
+        self.0.related()
+    }
+
+    fn diagnostic_source(&self) -> Option<&dyn Diagnostic> {
+        self.0.diagnostic_source()
+    }
+}
+
+/// An error as JSON, with its labels pointing into the file they came from:
+///
+/// ```json
+/// {"severity": "error", "code": "nu::parser::parse_mismatch", "message": "Parse mismatch during operation.",
+///  "labels": [{"text": "expected int", "file": "script.nu", "start": 4, "end": 7}],
+///  "help": null, "url": null, "related": []}
+/// ```
+///
+/// `start` and `end` are byte offsets into that file, or into all of the engine's sources when
+/// `file` is null.
+///
+/// ```
+/// use nu_app::{create_engine_state, create_stack, errors::error_json, eval_source_result};
+/// use nu_app::nu_protocol::{engine::StateWorkingSet, PipelineData};
+///
+/// let mut engine_state = create_engine_state();
+/// let mut stack = create_stack();
+/// let source = b"let x: int = 'one'";
+/// let err = eval_source_result(&mut engine_state, &mut stack, source, "script.nu", PipelineData::Empty, true)
+///     .unwrap_err();
+///
+/// let json = error_json(&StateWorkingSet::new(&engine_state), &err);
+/// assert_eq!(json["labels"][0]["file"], "script.nu");
+/// assert_eq!(json["labels"][0]["start"], 13);
+/// ```
+pub fn error_json(working_set: &StateWorkingSet, error: &dyn Diagnostic) -> JsonValue {
+    let severity = match error.severity().unwrap_or(Severity::Error) {
+        Severity::Advice => "advice",
+        Severity::Warning => "warning",
+        Severity::Error => "error",
+    };
+
+    let labels: Vec<JsonValue> = error
+        .labels()
+        .into_iter()
+        .flatten()
+        .map(|label| {
+            let start = label.offset();
+            let end = start + label.len();
+            let file = working_set
+                .files()
+                .find(|(_, file_start, file_end)| *file_start <= start && end <= *file_end);
+
+            match file {
+                Some((name, file_start, _)) => json!({
+                    "text": label.label(),
+                    "file": name,
+                    "start": start - file_start,
+                    "end": end - file_start,
+                }),
+                None => json!({
+                    "text": label.label(),
+                    "file": null,
+                    "start": start,
+                    "end": end,
+                }),
+            }
+        })
+        .collect();
+
+    let related: Vec<JsonValue> = error
+        .related()
+        .into_iter()
+        .flatten()
+        .map(|related| error_json(working_set, related))
+        .collect();
+
+    json!({
+        "severity": severity,
+        "code": error.code().map(|code| code.to_string()),
+        "message": error.to_string(),
+        "labels": labels,
+        "help": error.help().map(|help| help.to_string()),
+        "url": error.url().map(|url| url.to_string()),
+        "related": related,
+    })
+}
+
+/// Why evaluating a source, like with [`eval_source_result`](crate::eval_source_result), has no
+/// output.
+///
+/// Each kind keeps the error nu gave, so it still renders with its snippet, e.g. by
+/// [`report_error_new`](crate::report_error_new), and [`EvalError::span`] has where it points.
+///
+/// ```
+/// use nu_app::{create_engine_state, create_stack, eval_source_result, EvalError};
+/// use nu_app::nu_protocol::PipelineData;
+///
+/// let mut engine_state = create_engine_state();
+/// let mut stack = create_stack();
+/// let source = b"error make {msg: 'no such user'}";
+/// let err = eval_source_result(&mut engine_state, &mut stack, source, "app", PipelineData::Empty, true)
+///     .unwrap_err();
+///
+/// assert!(matches!(err, EvalError::Runtime(_)));
+/// assert_eq!(err.to_string(), "no such user");
+/// ```
+#[derive(Debug, Clone)]
+pub enum EvalError {
+    /// The source isn't valid nu.
+    Parse(Box<ParseError>),
+    /// The source parsed, but the engine couldn't take its definitions.
+    Compile(ShellError),
+    /// Evaluating the source failed.
+    Runtime(ShellError),
+    /// The source couldn't be read.
+    Io {
+        path: PathBuf,
+        error: Arc<io::Error>,
+    },
+}
+
+impl EvalError {
+    /// An `Io` error for the file at `path`.
+    pub fn io(path: impl Into<PathBuf>, error: io::Error) -> Self {
+        EvalError::Io {
+            path: path.into(),
+            error: Arc::new(error),
+        }
+    }
+
+    /// The span of the engine's sources the error points at, the one of its first label. `Io`
+    /// errors and errors without a label have none.
+    pub fn span(&self) -> Option<Span> {
+        let label = self.labels()?.next()?;
+
+        Some(Span::new(label.offset(), label.offset() + label.len()))
+    }
+
+    fn diagnostic(&self) -> Option<&dyn Diagnostic> {
+        match self {
+            EvalError::Parse(err) => Some(err.as_ref()),
+            EvalError::Compile(err) | EvalError::Runtime(err) => Some(err),
+            EvalError::Io { .. } => None,
+        }
+    }
+}
+
+impl fmt::Display for EvalError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EvalError::Parse(err) => err.fmt(f),
+            EvalError::Compile(err) | EvalError::Runtime(err) => err.fmt(f),
+            EvalError::Io { path, error } => {
+                write!(f, "could not read {}: {error}", path.display())
+            }
+        }
+    }
+}
+
+impl std::error::Error for EvalError {}
+
+impl Diagnostic for EvalError {
+    fn code<'a>(&'a self) -> Option<Box<dyn fmt::Display + 'a>> {
+        match self.diagnostic() {
+            Some(diagnostic) => diagnostic.code(),
+            None => Some(Box::new("nu_app::io_error")),
+        }
+    }
+
+    fn severity(&self) -> Option<Severity> {
+        self.diagnostic()?.severity()
+    }
+
+    fn help<'a>(&'a self) -> Option<Box<dyn fmt::Display + 'a>> {
+        self.diagnostic()?.help()
+    }
+
+    fn url<'a>(&'a self) -> Option<Box<dyn fmt::Display + 'a>> {
+        self.diagnostic()?.url()
+    }
+
+    fn source_code(&self) -> Option<&dyn miette::SourceCode> {
+        self.diagnostic()?.source_code()
+    }
+
+    fn labels(&self) -> Option<Box<dyn Iterator<Item = LabeledSpan> + '_>> {
+        self.diagnostic()?.labels()
+    }
+
+    fn related<'a>(&'a self) -> Option<Box<dyn Iterator<Item = &'a dyn Diagnostic> + 'a>> {
+        self.diagnostic()?.related()
+    }
+
+    fn diagnostic_source(&self) -> Option<&dyn Diagnostic> {
+        self.diagnostic()?.diagnostic_source()
+    }
+}
+
+impl From<ParseError> for EvalError {
+    fn from(err: ParseError) -> Self {
+        EvalError::Parse(Box::new(err))
+    }
+}
+
+impl From<ShellError> for EvalError {
+    fn from(err: ShellError) -> Self {
+        EvalError::Runtime(err)
+    }
+}
+
+/// Where errors go, like the ones [`eval_source_with_reporter`](crate::eval_source_with_reporter)
+/// runs into.
+///
+/// [`StderrReporter`] prints them like nu does and [`CollectErrors`] keeps them. A closure is a
+/// reporter too, e.g. to hand the errors to the application's log:
+///
+/// ```
+/// use nu_app::{create_engine_state, create_stack, eval_source_with_reporter, format_error};
+/// use nu_app::nu_protocol::{engine::StateWorkingSet, PipelineData};
+///
+/// let mut engine_state = create_engine_state();
+/// let mut stack = create_stack();
+/// let mut log = vec![];
+/// let mut reporter = |working_set: &StateWorkingSet, error: &_| {
+///     log.push(format_error(working_set, error));
+/// };
+///
+/// let ran = eval_source_with_reporter(
+///     &mut engine_state,
+///     &mut stack,
+///     b"error make {msg: 'no such user'}",
+///     "app",
+///     PipelineData::Empty,
+///     true,
+///     &mut reporter,
+/// );
+/// assert!(!ran);
+/// assert!(log[0].contains("no such user"));
+/// ```
+pub trait ErrorReporter {
+    fn report(
+        &mut self,
+        working_set: &StateWorkingSet,
+        error: &(dyn Diagnostic + Send + Sync + 'static),
+    );
+}
+
+impl<F> ErrorReporter for F
+where
+    F: FnMut(&StateWorkingSet, &(dyn Diagnostic + Send + Sync + 'static)),
+{
+    fn report(
+        &mut self,
+        working_set: &StateWorkingSet,
+        error: &(dyn Diagnostic + Send + Sync + 'static),
+    ) {
+        self(working_set, error)
+    }
+}
+
+/// Prints errors to stderr, like [`report_error`](crate::report_error) does.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct StderrReporter;
+
+impl ErrorReporter for StderrReporter {
+    fn report(
+        &mut self,
+        working_set: &StateWorkingSet,
+        error: &(dyn Diagnostic + Send + Sync + 'static),
+    ) {
+        report_error(working_set, error)
+    }
+}
+
+/// Keeps errors rendered the way [`report_error`](crate::report_error) would print them, for
+/// the application to show where it likes.
+#[derive(Debug, Clone, Default)]
+pub struct CollectErrors(pub Vec<String>);
+
+impl ErrorReporter for CollectErrors {
+    fn report(
+        &mut self,
+        working_set: &StateWorkingSet,
+        error: &(dyn Diagnostic + Send + Sync + 'static),
+    ) {
+        self.0.push(format_error(working_set, error))
+    }
+}
@@ -1,14 +1,38 @@
 use std::{
-    io::BufReader,
-    path::PathBuf,
-    sync::{atomic::AtomicBool, Arc},
+    io::{BufRead, BufReader, IsTerminal, Read, Write},
+    ops::ControlFlow,
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, RecvTimeoutError},
+        Arc,
+    },
+    time::Duration,
 };
 
-use nu_engine::{eval_block, eval_block_with_early_return};
+use nu_engine::{env::get_config, eval_block, eval_block_with_early_return};
 use nu_parser::parse;
 use nu_protocol::{
-    engine::{EngineState, Stack, StateWorkingSet},
-    print_if_stream, BufferedReader, CliError, PipelineData, RawStream, Span, Value,
+    ast::{Block, Call},
+    engine::{Command, EngineState, Stack, StateWorkingSet},
+    print_if_stream, BufferedReader, Config, IntoPipelineData, ListStream, PipelineData, RawStream,
+    ShellError, Signature, Span, Value, VarId,
+};
+#[cfg(windows)]
+use nu_utils::enable_vt_processing;
+use serde::de::DeserializeOwned;
+
+use crate::{
+    cancel::CancellationHandle,
+    commands::{HostFn, HostFnArgs},
+    errors::{
+        error_format, error_json, render_error, ErrorFormat, ErrorReporter, EvalError,
+        StderrReporter,
+    },
+    hooks::{display_output, run_post_execution, run_pre_execution},
+    streams::{RawStreamReader, ValueIter},
+    values::{from_value, json_to_value, IntoValue},
 };
 
 pub fn set_last_exit_code(stack: &mut Stack, exit_code: i64) {
@@ -31,11 +55,28 @@ pub fn report_error(
     working_set: &StateWorkingSet,
     error: &(dyn miette::Diagnostic + Send + Sync + 'static),
 ) {
-    eprintln!("Error: {:?}", CliError(error, working_set));
+    eprintln!("{}", format_error(working_set, error));
     // reset vt processing, aka ansi because illbehaved externals can break it
     #[cfg(windows)]
     {
-        let _ = nu_utils::enable_vt_processing();
+        let _ = enable_vt_processing();
+    }
+}
+
+/// An error the way [`report_error`] prints it, in the format picked with
+/// [`set_error_format`](crate::set_error_format), for writing it somewhere else.
+pub fn format_error(
+    working_set: &StateWorkingSet,
+    error: &(dyn miette::Diagnostic + Send + Sync + 'static),
+) -> String {
+    match error_format() {
+        ErrorFormat::Human => match error.severity() {
+            Some(miette::Severity::Warning) => {
+                format!("Warning: {}", render_error(working_set, error))
+            }
+            _ => format!("Error: {}", render_error(working_set, error)),
+        },
+        ErrorFormat::Json => error_json(working_set, error).to_string(),
     }
 }
 
@@ -55,6 +96,138 @@ pub fn eval_source(
     input: PipelineData,
     allow_return: bool,
 ) -> bool {
+    eval_source_with_reporter(
+        engine_state,
+        stack,
+        source,
+        fname,
+        input,
+        allow_return,
+        &mut StderrReporter,
+    )
+}
+
+/// Parse and evaluate `source` like [`eval_source`], printing the output, but hand the errors
+/// to `reporter` instead of printing them to stderr.
+///
+/// Returns whether the source ran and its output was printed without errors.
+pub fn eval_source_with_reporter(
+    engine_state: &mut EngineState,
+    stack: &mut Stack,
+    source: &[u8],
+    fname: &str,
+    input: PipelineData,
+    allow_return: bool,
+    reporter: &mut dyn ErrorReporter,
+) -> bool {
+    let result = eval_source_result(engine_state, stack, source, fname, input, allow_return)
+        .and_then(|pipeline_data| {
+            let pipeline_data = display_output(engine_state, stack, pipeline_data)?;
+            print_pipeline_data_result(engine_state, stack, pipeline_data, true).map_err(Into::into)
+        });
+
+    match result {
+        Ok(()) => true,
+        Err(err) => {
+            reporter.report(&StateWorkingSet::new(engine_state), &err);
+            false
+        }
+    }
+}
+
+/// Parse and evaluate `source`, handing back the output without printing or collecting it.
+///
+/// The returned `PipelineData` can be passed as the `input` of a later call, so streams
+/// keep flowing between separate evaluations. Errors are reported and yield `None`.
+pub fn eval_source_to_pipeline(
+    engine_state: &mut EngineState,
+    stack: &mut Stack,
+    source: &[u8],
+    fname: &str,
+    input: PipelineData,
+    allow_return: bool,
+) -> Option<PipelineData> {
+    match eval_source_result(engine_state, stack, source, fname, input, allow_return) {
+        Ok(pipeline_data) => Some(pipeline_data),
+        Err(err) => {
+            report_error_new(engine_state, &err);
+            None
+        }
+    }
+}
+
+/// Parse and evaluate `source` like [`eval_source_to_pipeline`], but hand errors back instead
+/// of printing them.
+///
+/// The source stays in the engine even when it doesn't parse, so the error can still be shown
+/// with its snippet, e.g. by [`report_error_new`].
+pub fn eval_source_result(
+    engine_state: &mut EngineState,
+    stack: &mut Stack,
+    source: &[u8],
+    fname: &str,
+    input: PipelineData,
+    allow_return: bool,
+) -> Result<PipelineData, EvalError> {
+    eval_source_redirected(
+        engine_state,
+        stack,
+        source,
+        fname,
+        input,
+        allow_return,
+        false,
+        false,
+    )
+}
+
+// with `redirect_stdout` and `redirect_stderr` an external at the end of the source hands its
+// output back instead of printing it
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn eval_source_redirected(
+    engine_state: &mut EngineState,
+    stack: &mut Stack,
+    source: &[u8],
+    fname: &str,
+    input: PipelineData,
+    allow_return: bool,
+    redirect_stdout: bool,
+    redirect_stderr: bool,
+) -> Result<PipelineData, EvalError> {
+    sync_pwd(engine_state, stack);
+    crate::crash_report::record_source(fname, source);
+    run_pre_execution(engine_state, stack, source);
+
+    // hosts pair the hooks, so post_execution runs for a source that doesn't parse as well
+    let result = parse_and_eval(
+        engine_state,
+        stack,
+        source,
+        fname,
+        input,
+        allow_return,
+        redirect_stdout,
+        redirect_stderr,
+    );
+    run_post_execution(engine_state, stack, source);
+
+    result
+}
+
+#[allow(clippy::too_many_arguments)]
+fn parse_and_eval(
+    engine_state: &mut EngineState,
+    stack: &mut Stack,
+    source: &[u8],
+    fname: &str,
+    input: PipelineData,
+    allow_return: bool,
+    redirect_stdout: bool,
+    redirect_stderr: bool,
+) -> Result<PipelineData, EvalError> {
+    #[cfg(feature = "plugin")]
+    let decls_before = engine_state.num_decls();
+
     let (block, delta) = {
         let mut working_set = StateWorkingSet::new(engine_state);
         let output = parse(
@@ -63,10 +236,19 @@ pub fn eval_source(
             source,
             false,
         );
-        if let Some(err) = working_set.parse_errors.first() {
+        if let Some(err) = working_set.parse_errors.first().cloned() {
             set_last_exit_code(stack, 1);
-            report_error(&working_set, err);
-            return false;
+
+            // only the file, the definitions of source that doesn't parse can't be trusted;
+            // it's the first thing both working sets add, so the spans stay the same
+            let mut working_set = StateWorkingSet::new(engine_state);
+            let _ = working_set.add_file(fname.into(), source);
+            let delta = working_set.render();
+            engine_state
+                .merge_delta(delta)
+                .map_err(EvalError::Compile)?;
+
+            return Err(err.into());
         }
 
         (output, working_set.render())
@@ -74,88 +256,972 @@ pub fn eval_source(
 
     if let Err(err) = engine_state.merge_delta(delta) {
         set_last_exit_code(stack, 1);
-        report_error_new(engine_state, &err);
-        return false;
+        return Err(EvalError::Compile(err));
     }
 
+    let outer_reloads = crate::reload::take_pending_reloads();
     let b = if allow_return {
-        eval_block_with_early_return(engine_state, stack, &block, input, false, false)
+        eval_block_with_early_return(
+            engine_state,
+            stack,
+            &block,
+            input,
+            redirect_stdout,
+            redirect_stderr,
+        )
     } else {
-        eval_block(engine_state, stack, &block, input, false, false)
+        eval_block(
+            engine_state,
+            stack,
+            &block,
+            input,
+            redirect_stdout,
+            redirect_stderr,
+        )
+    };
+
+    // commands like `plugin add` and `overlay reload` change the engine once they're done
+    #[cfg(feature = "plugin")]
+    if let Err(err) = crate::plugins::apply_plugin_changes(engine_state)
+        .and_then(|_| crate::plugins::restore_registry(engine_state, decls_before))
+    {
+        report_error_new(engine_state, &err);
+    }
+    crate::reload::apply_pending_reloads(engine_state, stack);
+    crate::reload::restore_pending_reloads(outer_reloads);
+
+    b.map_err(|err| {
+        set_last_exit_code(stack, 1);
+        crate::crash_report::report_fatal_error(&err);
+
+        err.into()
+    })
+}
+
+/// Parse and evaluate `source` like [`eval_source_result`], and collect the output into a
+/// `Value` instead of printing it, a stream becomes a list and the output of an external a
+/// string.
+///
+/// Collecting stops when the engine's ctrl-c flag is set, that's an error rather than a
+/// shorter list. The exit code of an external the output comes from is recorded as
+/// `$env.LAST_EXIT_CODE`, like printing it would.
+pub fn eval_source_to_value(
+    engine_state: &mut EngineState,
+    stack: &mut Stack,
+    source: &[u8],
+    fname: &str,
+    input: PipelineData,
+    allow_return: bool,
+) -> Result<Value, EvalError> {
+    let pipeline_data = eval_source_redirected(
+        engine_state,
+        stack,
+        source,
+        fname,
+        input,
+        allow_return,
+        true,
+        false,
+    )?;
+
+    // `into_value` waits for the exit code of an external, but doesn't hand it back
+    let (value, exit_code) = match pipeline_data {
+        PipelineData::ExternalStream {
+            stdout,
+            stderr,
+            exit_code,
+            span,
+            metadata,
+            trim_end_newline,
+        } => {
+            let value = PipelineData::ExternalStream {
+                stdout,
+                stderr,
+                exit_code: None,
+                span,
+                metadata,
+                trim_end_newline,
+            }
+            .into_value(span);
+            let exit_code = exit_code
+                .and_then(|exit_code| exit_code.into_iter().last())
+                .and_then(|exit_code| exit_code.as_i64().ok());
+
+            (value, exit_code)
+        }
+        pipeline_data => (pipeline_data.into_value(Span::unknown()), None),
     };
+    if let Some(exit_code) = exit_code {
+        set_last_exit_code(stack, exit_code);
+    }
 
-    match b {
-        Ok(pipeline_data) => {
-            let result;
-            if let PipelineData::ExternalStream {
-                stdout: stream,
-                stderr: stderr_stream,
-                exit_code,
-                ..
-            } = pipeline_data
-            {
-                result = print_if_stream(stream, stderr_stream, false, exit_code);
-            } else {
-                result = pipeline_data.print(engine_state, stack, true, false);
+    let interrupted = engine_state
+        .ctrlc
+        .as_ref()
+        .is_some_and(|ctrlc| ctrlc.load(Ordering::SeqCst));
+    if interrupted {
+        set_last_exit_code(stack, 1);
+        return Err(ShellError::InterruptedByUser { span: None }.into());
+    }
+
+    match value {
+        Value::Error { error } => {
+            set_last_exit_code(stack, 1);
+            Err(EvalError::Runtime(*error))
+        }
+        value => Ok(value),
+    }
+}
+
+/// Parse and evaluate `source` like [`eval_source_to_value`], but give up once it has run for
+/// `timeout`, with an error saying so.
+///
+/// It's stopped through the engine's ctrl-c flag, which is added if the engine doesn't have
+/// one yet, so only commands that watch for ctrl-c stop, and externals are only stopped from
+/// being read.
+pub fn eval_source_with_timeout(
+    engine_state: &mut EngineState,
+    stack: &mut Stack,
+    source: &[u8],
+    fname: &str,
+    input: PipelineData,
+    allow_return: bool,
+    timeout: Duration,
+) -> Result<Value, EvalError> {
+    let handle = CancellationHandle::new(engine_state);
+    let expired = Arc::new(AtomicBool::new(false));
+
+    let (done, wait_for_done) = mpsc::channel::<()>();
+    let watchdog = {
+        let (handle, expired) = (handle.clone(), expired.clone());
+        std::thread::spawn(move || {
+            if let Err(RecvTimeoutError::Timeout) = wait_for_done.recv_timeout(timeout) {
+                expired.store(true, Ordering::SeqCst);
+                handle.cancel();
             }
+        })
+    };
 
-            match result {
-                Err(err) => {
-                    let working_set = StateWorkingSet::new(engine_state);
+    let result = eval_source_to_value(engine_state, stack, source, fname, input, allow_return);
+    let _ = done.send(());
+    let _ = watchdog.join();
 
-                    report_error(&working_set, &err);
+    if !expired.load(Ordering::SeqCst) {
+        return result;
+    }
+
+    // the flag was set for the timeout, not by the user
+    handle.reset();
+    set_last_exit_code(stack, 1);
+    Err(ShellError::GenericError(
+        "Evaluation timed out".into(),
+        format!("{fname} was stopped after {timeout:?}"),
+        None,
+        None,
+        vec![],
+    )
+    .into())
+}
+
+/// Parse and evaluate `source` like [`eval_source_result`], and call `f` with each value of the
+/// output as soon as the pipeline has it, instead of collecting or printing it.
+///
+/// A list or stream gives each of its items, the output of an external each chunk it is read
+/// in, as a string, or as binary when it isn't text. Returning `ControlFlow::Break` stops the
+/// pipeline, like `first` does. An error in the stream ends it with that error, and so does
+/// the engine's ctrl-c flag being set.
+pub fn eval_source_for_each(
+    engine_state: &mut EngineState,
+    stack: &mut Stack,
+    source: &[u8],
+    fname: &str,
+    input: PipelineData,
+    allow_return: bool,
+    mut f: impl FnMut(Value) -> ControlFlow<()>,
+) -> Result<(), EvalError> {
+    let pipeline_data = eval_source_redirected(
+        engine_state,
+        stack,
+        source,
+        fname,
+        input,
+        allow_return,
+        true,
+        false,
+    )?;
+    let ctrlc = engine_state.ctrlc.clone();
 
-                    return false;
+    let result = match pipeline_data {
+        PipelineData::ExternalStream {
+            stdout, exit_code, ..
+        } => {
+            let mut flow = ControlFlow::Continue(());
+            let mut result = Ok(());
+            for chunk in stdout.into_iter().flatten() {
+                match chunk {
+                    Ok(chunk) => flow = f(chunk),
+                    Err(err) => result = Err(err),
                 }
-                Ok(exit_code) => {
+                if flow.is_break() || result.is_err() {
+                    break;
+                }
+            }
+
+            // a stopped external isn't waited for, it may never finish
+            if flow.is_continue() {
+                let exit_code = exit_code
+                    .and_then(|exit_code| exit_code.into_iter().last())
+                    .and_then(|exit_code| exit_code.as_i64().ok());
+                if let Some(exit_code) = exit_code {
                     set_last_exit_code(stack, exit_code);
                 }
             }
+            result
+        }
+        pipeline_data => ValueIter::new(pipeline_data, ctrlc.clone())
+            .try_for_each(|value| match value.map(&mut f) {
+                Ok(ControlFlow::Continue(())) => ControlFlow::Continue(()),
+                Ok(ControlFlow::Break(())) => ControlFlow::Break(Ok(())),
+                Err(err) => ControlFlow::Break(Err(err)),
+            })
+            .break_value()
+            .unwrap_or(Ok(())),
+    };
 
-            // reset vt processing, aka ansi because illbehaved externals can break it
-            #[cfg(windows)]
-            {
-                let _ = enable_vt_processing();
+    let interrupted = ctrlc.is_some_and(|ctrlc| ctrlc.load(Ordering::SeqCst));
+    let result = match result {
+        Ok(()) if interrupted => Err(ShellError::InterruptedByUser { span: None }),
+        result => result,
+    };
+
+    result.map_err(|err| {
+        set_last_exit_code(stack, 1);
+        err.into()
+    })
+}
+
+/// Parse and evaluate `source` like [`eval_source_to_value`], and convert the output into `T`
+/// with serde, see [`from_value`].
+///
+/// ```no_run
+/// # use nu_app::{create_engine_state, create_stack, eval_source_into, nu_protocol::PipelineData};
+/// #[derive(serde::Deserialize)]
+/// struct File {
+///     name: String,
+///     size: u64,
+/// }
+///
+/// let mut engine_state = create_engine_state();
+/// let mut stack = create_stack();
+/// let files: Vec<File> = eval_source_into(
+///     &mut engine_state,
+///     &mut stack,
+///     b"ls | select name size",
+///     "app",
+///     PipelineData::Empty,
+///     true,
+/// )
+/// .unwrap();
+/// ```
+pub fn eval_source_into<T: DeserializeOwned>(
+    engine_state: &mut EngineState,
+    stack: &mut Stack,
+    source: &[u8],
+    fname: &str,
+    input: PipelineData,
+    allow_return: bool,
+) -> Result<T, EvalError> {
+    let value = eval_source_to_value(engine_state, stack, source, fname, input, allow_return)?;
+
+    from_value(&value).map_err(|err| {
+        set_last_exit_code(stack, 1);
+        err.into()
+    })
+}
+
+/// A problem [`check_source`] found in a source.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseDiagnostic {
+    /// What's wrong, like `Parse mismatch during operation.`
+    pub message: String,
+    /// What the span points at, like `expected int`.
+    pub label: Option<String>,
+    pub help: Option<String>,
+    /// The bytes of the source it's about, `0..0` when it's about another file, e.g. a module
+    /// the source uses.
+    pub span: Span,
+    pub severity: miette::Severity,
+}
+
+/// Parse `source` without evaluating it, and give back what's wrong with it, nothing when it's
+/// valid. The engine is left as it is, the definitions of the source aren't added.
+///
+/// ```
+/// use nu_app::{check_source, create_engine_state};
+///
+/// let engine_state = create_engine_state();
+/// let diagnostics = check_source(&engine_state, b"let x: int = 'one'", "script.nu");
+/// assert_eq!(diagnostics[0].span.start, 13);
+/// ```
+pub fn check_source(
+    engine_state: &EngineState,
+    source: &[u8],
+    fname: &str,
+) -> Vec<ParseDiagnostic> {
+    parse_detached(
+        engine_state,
+        source,
+        fname,
+        |working_set, _, source_span| parse_diagnostics(working_set, source_span),
+    )
+}
+
+// parse `source` in a working set that's dropped afterwards, `f` gets the span the source has in
+// it as well
+pub(crate) fn parse_detached<R>(
+    engine_state: &EngineState,
+    source: &[u8],
+    fname: &str,
+    f: impl FnOnce(&StateWorkingSet, &Block, Span) -> R,
+) -> R {
+    // `use` resolves its path against the engine's PWD, which evaluating a source sets
+    let with_pwd;
+    let engine_state = match engine_state.get_env_var("PWD") {
+        Some(_) => engine_state,
+        None => {
+            let mut engine_state = engine_state.clone();
+            let pwd = get_init_cwd().to_string_lossy().into_owned();
+            engine_state.add_env_var("PWD".into(), Value::string(pwd, Span::unknown()));
+            with_pwd = engine_state;
+            &with_pwd
+        }
+    };
+
+    let mut working_set = StateWorkingSet::new(engine_state);
+    let start = working_set.next_span_start();
+    let block = parse(&mut working_set, Some(fname), source, false);
+
+    f(&working_set, &block, Span::new(start, start + source.len()))
+}
+
+// the parse errors of the source at `source_span`, with spans into it
+pub(crate) fn parse_diagnostics(
+    working_set: &StateWorkingSet,
+    source_span: Span,
+) -> Vec<ParseDiagnostic> {
+    let mut diagnostics: Vec<ParseDiagnostic> = working_set
+        .parse_errors
+        .iter()
+        .map(|err| {
+            let label = miette::Diagnostic::labels(err)
+                .and_then(|mut labels| labels.next())
+                .and_then(|label| label.label().map(String::from));
+
+            ParseDiagnostic {
+                message: err.to_string(),
+                label,
+                help: miette::Diagnostic::help(err).map(|help| help.to_string()),
+                span: relative_span(err.span(), source_span),
+                severity: miette::Diagnostic::severity(err).unwrap_or(miette::Severity::Error),
             }
+        })
+        .collect();
+    // the parser can run into the same problem twice, like a missing `]`
+    diagnostics.dedup();
+
+    diagnostics
+}
+
+// `span` as offsets into the source at `source_span`, `0..0` when it's in another file
+pub(crate) fn relative_span(span: Span, source_span: Span) -> Span {
+    if source_span.start <= span.start && span.end <= source_span.end {
+        Span::new(span.start - source_span.start, span.end - source_span.start)
+    } else {
+        Span::new(0, 0)
+    }
+}
+
+/// Make `$name` a variable holding `value` in the sources evaluated afterwards, like a `let` at
+/// the top of each of them. `value` is a `Value` or plain Rust data, see [`IntoValue`].
+///
+/// The variable is a constant too, so `const` and `use` can read it while parsing. Setting the
+/// same name again shadows the variable set before.
+pub fn set_variable(
+    engine_state: &mut EngineState,
+    stack: &mut Stack,
+    name: &str,
+    value: impl IntoValue,
+) -> Result<VarId, ShellError> {
+    let value = value.into_value();
+    let name = name.strip_prefix('$').unwrap_or(name);
+    // the parser reads these before any variable of the same name
+    if matches!(name, "nu" | "env" | "in") || !is_variable_name(name) {
+        return Err(ShellError::GenericError(
+            format!("`${name}` can't be set"),
+            "not a name a variable can have".into(),
+            None,
+            Some("variable names can't be empty, nu, env or in, or have spaces or symbols".into()),
+            vec![],
+        ));
+    }
+
+    let mut working_set = StateWorkingSet::new(engine_state);
+    let var_id = working_set.add_variable(
+        name.as_bytes().to_vec(),
+        Span::unknown(),
+        value.get_type(),
+        false,
+    );
+    working_set.set_variable_const_val(var_id, value.clone());
+    let delta = working_set.render();
+    engine_state.merge_delta(delta)?;
+
+    stack.add_var(var_id, value);
+
+    Ok(var_id)
+}
+
+/// Make `body` a command scripts can call, with the name and parameters of `signature`. It gets
+/// the call's arguments and its input, and its value is the command's output:
+///
+/// ```
+/// use nu_app::{create_engine_state, create_stack, eval_source_to_value, register_fn};
+/// use nu_app::nu_protocol::{PipelineData, Signature, SyntaxShape, Value};
+///
+/// let mut engine_state = create_engine_state();
+/// let mut stack = create_stack();
+///
+/// let signature = Signature::build("greet")
+///     .usage("Greet someone from the application.")
+///     .required("name", SyntaxShape::String, "who to greet");
+/// register_fn(&mut engine_state, signature, |args, _input| {
+///     let name: String = args.req(0)?;
+///     Ok(Value::string(format!("hello, {name}"), args.head))
+/// })
+/// .expect("the command is added");
+///
+/// let value = eval_source_to_value(
+///     &mut engine_state,
+///     &mut stack,
+///     b"greet nu",
+///     "app",
+///     PipelineData::Empty,
+///     true,
+/// )
+/// .unwrap();
+/// assert_eq!(value.as_string().unwrap(), "hello, nu");
+/// ```
+pub fn register_fn(
+    engine_state: &mut EngineState,
+    signature: Signature,
+    body: impl Fn(&HostFnArgs, Value) -> Result<Value, ShellError> + Send + Sync + 'static,
+) -> Result<(), ShellError> {
+    add_commands(engine_state, vec![Box::new(HostFn::new(signature, body))])
+}
+
+/// Add the application's own commands to an engine it already created, e.g. with
+/// [`create_engine_state`]. A command replaces the one with the same name for the sources parsed
+/// afterwards.
+pub fn add_commands(
+    engine_state: &mut EngineState,
+    commands: Vec<Box<dyn Command>>,
+) -> Result<(), ShellError> {
+    let mut working_set = StateWorkingSet::new(engine_state);
+    for command in commands {
+        working_set.add_decl(command);
+    }
+    let delta = working_set.render();
+    engine_state.merge_delta(delta)?;
+
+    // a `table` of the application renders the printed values from now on
+    engine_state.table_decl_id = engine_state.find_decl(b"table", &[]);
+
+    Ok(())
+}
+
+/// Set `$env.<name>` to `value` for the sources evaluated afterwards with `stack`.
+pub fn set_env_var(stack: &mut Stack, name: &str, value: impl IntoValue) {
+    stack.add_env_var(name.into(), value.into_value());
+}
+
+/// Have `use` and `source` look for the modules and scripts they're given by name in `dirs`,
+/// in order, like nu does with `$env.NU_LIB_DIRS`.
+///
+/// ```
+/// # use nu_app::{create_engine_state, create_stack, eval_source_to_value, nu_protocol::PipelineData};
+/// use nu_app::set_lib_dirs;
+///
+/// let dir = std::env::temp_dir().join("nu_app_lib_dirs_example");
+/// std::fs::create_dir_all(&dir).unwrap();
+/// std::fs::write(dir.join("greet.nu"), "export def main [] { 'hello' }").unwrap();
+///
+/// let mut engine_state = create_engine_state();
+/// let mut stack = create_stack();
+/// set_lib_dirs(&mut engine_state, [&dir]);
+///
+/// let source = b"use greet.nu; greet";
+/// let greeting = eval_source_to_value(&mut engine_state, &mut stack, source, "app", PipelineData::Empty, true)
+///     .unwrap();
+/// assert_eq!(greeting.as_string().unwrap(), "hello");
+/// ```
+pub fn set_lib_dirs(
+    engine_state: &mut EngineState,
+    dirs: impl IntoIterator<Item = impl AsRef<Path>>,
+) {
+    set_dirs_var(engine_state, "NU_LIB_DIRS", dirs);
+}
+
+/// Have `register` look for the plugins it's given by name in `dirs`, in order, like nu does
+/// with `$env.NU_PLUGIN_DIRS`.
+pub fn set_plugin_dirs(
+    engine_state: &mut EngineState,
+    dirs: impl IntoIterator<Item = impl AsRef<Path>>,
+) {
+    set_dirs_var(engine_state, "NU_PLUGIN_DIRS", dirs);
+}
+
+// the parser reads the search paths from the engine's environment, as a list
+fn set_dirs_var(
+    engine_state: &mut EngineState,
+    name: &str,
+    dirs: impl IntoIterator<Item = impl AsRef<Path>>,
+) {
+    let dirs = dirs
+        .into_iter()
+        .map(|dir| Value::string(dir.as_ref().to_string_lossy(), Span::unknown()))
+        .collect();
+
+    engine_state.add_env_var(name.into(), Value::list(dirs, Span::unknown()));
+}
+
+/// The value of `$env.<name>` for `stack`, e.g. one a script set with `$env.NAME = ...`.
+pub fn get_env_var(engine_state: &EngineState, stack: &Stack, name: &str) -> Option<Value> {
+    stack.get_env_var(engine_state, name)
+}
+
+/// The value of the top-level variable `$name`, one of a `let`, `mut` or `const` of the sources
+/// evaluated with `stack`, or one given by [`set_variable`].
+///
+/// Variables of blocks and closures are gone once they finished, they're never found.
+pub fn get_variable(engine_state: &EngineState, stack: &Stack, name: &str) -> Option<Value> {
+    let name = format!("${}", name.strip_prefix('$').unwrap_or(name));
+
+    engine_state
+        .active_overlays(&[])
+        .rev()
+        .find_map(|overlay| overlay.vars.get(name.as_bytes()))
+        .and_then(|var_id| variable_value(engine_state, stack, *var_id))
+}
+
+/// The top-level variables and their values, see [`get_variable`], sorted by name and without
+/// the `$`.
+pub fn variables(engine_state: &EngineState, stack: &Stack) -> Vec<(String, Value)> {
+    // later overlays shadow the variables of earlier ones
+    let mut var_ids = std::collections::BTreeMap::new();
+    for overlay in engine_state.active_overlays(&[]) {
+        for (name, var_id) in &overlay.vars {
+            let name = String::from_utf8_lossy(name);
+            var_ids.insert(name.trim_start_matches('$').to_string(), *var_id);
         }
+    }
+
+    var_ids
+        .into_iter()
+        .filter_map(|(name, var_id)| Some((name, variable_value(engine_state, stack, var_id)?)))
+        .collect()
+}
+
+// constants are only in the engine when nothing put them on the stack
+fn variable_value(engine_state: &EngineState, stack: &Stack, var_id: VarId) -> Option<Value> {
+    stack
+        .get_var(var_id, Span::unknown())
+        .ok()
+        .or_else(|| engine_state.get_var(var_id).const_val.clone())
+}
+
+fn is_variable_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.bytes().all(|byte| {
+            !byte.is_ascii_whitespace()
+                && !matches!(
+                    byte,
+                    b'.' | b'['
+                        | b'('
+                        | b'{'
+                        | b'+'
+                        | b'-'
+                        | b'*'
+                        | b'^'
+                        | b'/'
+                        | b'='
+                        | b'!'
+                        | b'<'
+                        | b'>'
+                        | b'&'
+                        | b'|'
+                        | b'$'
+                        | b'"'
+                        | b'\''
+                        | b'`'
+                        | b'?'
+                        | b')'
+                        | b']'
+                        | b'}'
+                        | b';'
+                        | b':'
+                        | b','
+                )
+        })
+}
+
+// the parser resolves the paths of `use` and `source` against the engine's PWD, `cd` only changes
+// the stack's
+pub(crate) fn sync_pwd(engine_state: &mut EngineState, stack: &Stack) {
+    if let Some(pwd) = stack.get_env_var(engine_state, "PWD") {
+        engine_state.add_env_var("PWD".into(), pwd);
+    }
+}
+
+/// Print the final output of a (possibly chained) evaluation and record its exit code.
+///
+/// With `no_newline` values are printed without a line break after them, like `eval_source` does.
+pub fn print_pipeline_data(
+    engine_state: &EngineState,
+    stack: &mut Stack,
+    pipeline_data: PipelineData,
+    no_newline: bool,
+) -> bool {
+    match print_pipeline_data_result(engine_state, stack, pipeline_data, no_newline) {
+        Ok(()) => true,
         Err(err) => {
-            set_last_exit_code(stack, 1);
+            let working_set = StateWorkingSet::new(engine_state);
+            report_error(&working_set, &err);
+
+            false
+        }
+    }
+}
+
+// `print_pipeline_data`, handing the error back instead of reporting it
+pub(crate) fn print_pipeline_data_result(
+    engine_state: &EngineState,
+    stack: &mut Stack,
+    pipeline_data: PipelineData,
+    no_newline: bool,
+) -> Result<(), ShellError> {
+    // externals write in the console's codepage, which can't be printed as UTF-8 as-is
+    #[cfg(windows)]
+    let pipeline_data = crate::console_encoding::decode_pipeline_data(
+        pipeline_data,
+        crate::console_encoding::ConsoleDecoder::for_console,
+    );
+
+    let result;
+    if let PipelineData::ExternalStream {
+        stdout: stream,
+        stderr: stderr_stream,
+        exit_code,
+        ..
+    } = pipeline_data
+    {
+        result = print_if_stream(stream, stderr_stream, false, exit_code);
+    } else {
+        result = print_values(engine_state, stack, pipeline_data, no_newline);
+    }
+
+    let exit_code = result?;
+    set_last_exit_code(stack, exit_code);
+
+    // reset vt processing, aka ansi because illbehaved externals can break it
+    #[cfg(windows)]
+    {
+        let _ = enable_vt_processing();
+    }
+
+    Ok(())
+}
+
+// `PipelineData::print`, but with the config of the stack, which has the settings a source gave
+// `$env.config` and the engine doesn't have yet
+fn print_values(
+    engine_state: &EngineState,
+    stack: &mut Stack,
+    pipeline_data: PipelineData,
+    no_newline: bool,
+) -> Result<i64, ShellError> {
+    let config = get_config(engine_state, stack);
+    let table = render_table(engine_state, stack, pipeline_data)?;
+
+    let separator = if no_newline { "" } else { "\n" };
+    // an error in the output fails the source like an error while evaluating it
+    let mut exit_code = 0;
+    for item in table {
+        let (mut out, to_stderr) = render_item(engine_state, &config, item, separator);
+        if to_stderr {
+            exit_code = 1;
+        }
+        if !no_newline {
+            out.push('\n');
+        }
 
+        let written = if to_stderr {
+            write_and_flush(&mut std::io::stderr().lock(), out.as_bytes())
+        } else {
+            write_and_flush(&mut std::io::stdout().lock(), out.as_bytes())
+        };
+        written.map_err(|err| ShellError::IOError(err.to_string()))?;
+    }
+
+    Ok(exit_code)
+}
+
+/// `pipeline_data` rendered by the `table` command, unless it's been replaced by a custom one.
+pub(crate) fn render_table(
+    engine_state: &EngineState,
+    stack: &mut Stack,
+    pipeline_data: PipelineData,
+) -> Result<PipelineData, ShellError> {
+    match engine_state.table_decl_id {
+        Some(decl_id) if engine_state.get_decl(decl_id).get_block_id().is_none() => {
+            let mut call = Call::new(Span::unknown());
+            call.redirect_stdout = false;
+            engine_state
+                .get_decl(decl_id)
+                .run(engine_state, stack, &call, pipeline_data)
+        }
+        _ => Ok(pipeline_data),
+    }
+}
+
+/// The text of an item of the rendered output, and whether it's an error that goes to stderr,
+/// formatted like [`format_error`] does.
+pub(crate) fn render_item(
+    engine_state: &EngineState,
+    config: &Config,
+    item: Value,
+    separator: &str,
+) -> (String, bool) {
+    match item {
+        Value::Error { error } => {
             let working_set = StateWorkingSet::new(engine_state);
+            (format_error(&working_set, &*error), true)
+        }
+        item => (item.into_string(separator, config), false),
+    }
+}
 
-            report_error(&working_set, &err);
+fn write_and_flush(writer: &mut impl Write, bytes: &[u8]) -> std::io::Result<()> {
+    writer.write_all(bytes)?;
+    writer.flush()
+}
+
+/// What [`create_stdin_input`] makes of the bytes piped in.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum StdinFormat {
+    /// A stream of text, like the output of an external.
+    #[default]
+    Raw,
+    /// A single JSON document, parsed like `from json` does once all of it is read, but
+    /// without the Hjson it also takes.
+    Json,
+    /// A JSON document per line, streamed as a list and parsed as the lines come in. A line
+    /// that isn't JSON is an error in the list, lines without anything on them are skipped.
+    Ndjson,
+    /// A stream of the bytes as they are, without decoding them as text, for input like
+    /// archives or images.
+    Binary,
+}
 
-            return false;
+impl FromStr for StdinFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "raw" => Ok(StdinFormat::Raw),
+            "json" => Ok(StdinFormat::Json),
+            "ndjson" => Ok(StdinFormat::Ndjson),
+            "binary" => Ok(StdinFormat::Binary),
+            _ => Err(format!(
+                "unknown stdin format '{s}', expected raw, json, ndjson or binary"
+            )),
         }
     }
-    true
 }
 
-pub fn create_stdin_input() -> PipelineData {
+/// Stdin as the input of a source, when it's piped in rather than a terminal, as text, as
+/// bytes or as the values of the JSON in it.
+///
+/// A terminal is no input, reading it would wait for the user to type an EOF, unless
+/// `read_terminal` is set: then what the user types until they do is the input.
+///
+/// Setting `ctrlc`, usually the engine's, stops reading it.
+pub fn create_stdin_input(
+    format: StdinFormat,
+    read_terminal: bool,
+    ctrlc: Option<Arc<AtomicBool>>,
+) -> PipelineData {
     // stdin
     let stdin = std::io::stdin();
+
+    if stdin.is_terminal() && !read_terminal {
+        return PipelineData::Empty;
+    }
+
     let buf_reader = BufReader::new(stdin);
 
-    // ctrl-c
+    let stdout = RawStream::new(
+        Box::new(BufferedReader::new(buf_reader)),
+        ctrlc.clone(),
+        Span::unknown(),
+        None,
+    );
+
+    // piped input arrives in the console's codepage, or as UTF-16 when redirected from PowerShell
+    #[cfg(windows)]
+    let stdout = match format {
+        StdinFormat::Binary => stdout,
+        _ => crate::console_encoding::decode_stream(
+            stdout,
+            crate::console_encoding::ConsoleDecoder::for_console(),
+        ),
+    };
+
+    match format {
+        StdinFormat::Raw | StdinFormat::Binary => PipelineData::ExternalStream {
+            // a binary stream doesn't try to read its chunks as UTF-8 first
+            stdout: Some(RawStream {
+                is_binary: format == StdinFormat::Binary,
+                ..stdout
+            }),
+            stderr: None,
+            exit_code: None,
+            span: Span::unknown(),
+            metadata: None,
+            trim_end_newline: false,
+        },
+        StdinFormat::Json => {
+            let mut text = String::new();
+            let value = match RawStreamReader::new(stdout).read_to_string(&mut text) {
+                Ok(_) => json_to_value(&text, Span::unknown()),
+                Err(err) => Err(ShellError::IOError(format!("could not read stdin: {err}"))),
+            };
+
+            value
+                .unwrap_or_else(|error| Value::Error {
+                    error: Box::new(error),
+                })
+                .into_pipeline_data()
+        }
+        StdinFormat::Ndjson => {
+            create_ndjson_input(BufReader::new(RawStreamReader::new(stdout)), ctrlc)
+        }
+    }
+}
+
+/// Input of a JSON document per line of `reader`, streamed as a list and parsed as the lines
+/// are read, like stdin with [`StdinFormat::Ndjson`].
+///
+/// A line that isn't JSON is an error in the list, which doesn't stop the lines after it.
+/// Lines without anything on them are skipped.
+///
+/// ```
+/// use nu_app::{create_ndjson_input, nu_protocol::Value};
+///
+/// let lines = "{\"a\": 1}\nbad\n\n{a: 1}\n[2]\n";
+/// let values: Vec<Value> = create_ndjson_input(lines.as_bytes(), None).into_iter().collect();
+///
+/// assert_eq!(values.len(), 4);
+/// assert!(matches!(values[1], Value::Error { .. }));
+/// // the unquoted keys of Hjson aren't JSON either
+/// assert!(matches!(values[2], Value::Error { .. }));
+/// assert_eq!(values[3].as_list().unwrap().len(), 1);
+/// ```
+pub fn create_ndjson_input(
+    reader: impl BufRead + Send + 'static,
+    ctrlc: Option<Arc<AtomicBool>>,
+) -> PipelineData {
+    let values = reader
+        .lines()
+        .filter(|line| !matches!(line, Ok(line) if line.trim().is_empty()))
+        .map(|line| {
+            let value = match line {
+                Ok(line) => json_to_value(&line, Span::unknown()),
+                Err(err) => Err(ShellError::IOError(format!("could not read stdin: {err}"))),
+            };
+            value.unwrap_or_else(|error| Value::Error {
+                error: Box::new(error),
+            })
+        });
+
+    PipelineData::ListStream(ListStream::from_stream(values, ctrlc), None)
+}
+
+/// Set the engine's ctrl-c flag when the process gets ctrl-c, so what it's evaluating stops
+/// instead of the whole process.
+///
+/// The flag stays set until it's cleared, the REPL does that before each entry. A process has
+/// a single handler, installing one a second time fails.
+pub fn install_ctrlc_handler(engine_state: &mut EngineState) -> Result<(), ShellError> {
     let ctrlc = Arc::new(AtomicBool::new(false));
+    let handler_ctrlc = ctrlc.clone();
 
-    PipelineData::ExternalStream {
-        stdout: Some(RawStream::new(
-            Box::new(BufferedReader::new(buf_reader)),
-            Some(ctrlc),
-            Span::unknown(),
+    ctrlc::set_handler(move || handler_ctrlc.store(true, Ordering::SeqCst)).map_err(|err| {
+        ShellError::GenericError(
+            "Could not handle ctrl-c".into(),
+            err.to_string(),
             None,
-        )),
-        stderr: None,
-        exit_code: None,
-        span: Span::unknown(),
-        metadata: None,
-        trim_end_newline: false,
-    }
+            None,
+            vec![],
+        )
+    })?;
+    engine_state.ctrlc = Some(ctrlc);
+
+    Ok(())
 }
 
+/// Input for [`eval_source`] and the other evaluations from the application's own values, as if
+/// a list of them was piped into the source.
+///
+/// The values are only taken from `values` as the pipeline reads them, so it can be a long or
+/// endless iterator. Setting `ctrlc`, usually the engine's, stops the stream.
+pub fn create_list_input<I>(values: I, ctrlc: Option<Arc<AtomicBool>>) -> PipelineData
+where
+    I: IntoIterator,
+    I::Item: IntoValue + 'static,
+    I::IntoIter: Send + 'static,
+{
+    let values = values.into_iter().map(IntoValue::into_value);
+
+    PipelineData::ListStream(ListStream::from_stream(values, ctrlc), None)
+}
+
+/// An engine with the default commands, and with the plugins of the default plugin registry when
+/// the `plugin` feature is on.
+///
+/// `$nu` has the paths of the files nu_app reads:
+///
+/// ```
+/// # use nu_app::{create_engine_state, create_stack, eval_source_to_value, nu_protocol::PipelineData};
+/// let mut engine_state = create_engine_state();
+/// let mut stack = create_stack();
+/// let source = b"$nu.config-path | path split | last 2 | path join";
+/// let path = eval_source_to_value(&mut engine_state, &mut stack, source, "app", PipelineData::Empty, true)
+///     .unwrap();
+/// # #[cfg(unix)]
+/// assert_eq!(path.as_string().unwrap(), "nu_app/config.nu");
+/// ```
 pub fn create_engine_state() -> EngineState {
-    crate::create_default_context::create_default_context()
+    #[allow(unused_mut)]
+    let mut engine_state = crate::create_default_context::create_default_context();
+
+    #[cfg(feature = "plugin")]
+    if let Some(path) = crate::plugins::default_plugin_file() {
+        if let Err(err) = crate::plugins::load_plugin_file(&mut engine_state, &path) {
+            report_error_new(&engine_state, &err);
+        }
+    }
+
+    engine_state
 }
 
 pub fn create_stack() -> nu_protocol::engine::Stack {
@@ -174,3 +1240,149 @@ pub fn create_stack() -> nu_protocol::engine::Stack {
 
     stack
 }
+
+/// The directory `env.nu` and `config.nu` are read from unless others are given,
+/// `<config dir>/nu_app`.
+pub fn default_config_dir() -> Option<PathBuf> {
+    nu_path::config_dir().map(|dir| dir.join("nu_app"))
+}
+
+// the paths of a new engine: the config files `$nu` names are nu_app's, not nu's, and the
+// search paths are the ones of the process' environment, or the `scripts` and `plugins`
+// directories next to the config files like nu's default `env.nu` has them
+pub(crate) fn add_default_paths(engine_state: &mut EngineState) {
+    let config_dir = default_config_dir();
+    if let Some(dir) = &config_dir {
+        engine_state.set_config_path("config-path", dir.join("config.nu"));
+        engine_state.set_config_path("env-path", dir.join("env.nu"));
+    }
+
+    for (name, default_dir) in [("NU_LIB_DIRS", "scripts"), ("NU_PLUGIN_DIRS", "plugins")] {
+        let dirs: Vec<PathBuf> = match std::env::var_os(name) {
+            Some(dirs) => std::env::split_paths(&dirs).collect(),
+            None => config_dir.iter().map(|dir| dir.join(default_dir)).collect(),
+        };
+        set_dirs_var(engine_state, name, dirs);
+    }
+}
+
+/// Evaluate a config file like `env.nu` or `config.nu`, keeping its definitions and
+/// environment, and applying the `$env.config` it sets to the engine.
+///
+/// `key` is where `$nu` finds the file, `env-path` or `config-path`. The output of the file is
+/// printed, errors are handed back.
+pub fn eval_config_file(
+    engine_state: &mut EngineState,
+    stack: &mut Stack,
+    path: &Path,
+    key: &str,
+) -> Result<(), EvalError> {
+    let source = std::fs::read(path).map_err(|err| EvalError::io(path, err))?;
+    engine_state.set_config_path(key, path.into());
+
+    let result = crate::hooks::without_hooks(|| {
+        eval_source_result(
+            engine_state,
+            stack,
+            &source,
+            &path.to_string_lossy(),
+            PipelineData::Empty,
+            false,
+        )
+        .and_then(|pipeline_data| {
+            print_pipeline_data_result(engine_state, stack, pipeline_data, true).map_err(Into::into)
+        })
+    });
+
+    // the stack's environment moves to the engine, which is where `$env.config` takes effect,
+    // whatever the file got to set before it failed
+    let cwd = nu_engine::env::current_dir(engine_state, stack).unwrap_or_else(|_| get_init_cwd());
+    let merged = crate::hooks::merge_env(engine_state, stack, cwd);
+
+    result?;
+    Ok(merged?)
+}
+
+/// Add the environment variables of the process to the engine as strings, like nu does when it
+/// starts. The ones the engine already has, like `PWD` and the lists of `NU_LIB_DIRS`, are kept.
+///
+/// [`convert_env_vars`] turns them into nu values once `$env.ENV_CONVERSIONS` is set up.
+pub fn add_process_env(engine_state: &mut EngineState) {
+    for (name, val) in std::env::vars_os() {
+        // nu can't name or hold the ones that aren't UTF-8
+        let (Some(name), Some(val)) = (name.to_str(), val.to_str()) else {
+            continue;
+        };
+        if engine_state.get_env_var(name).is_none() {
+            engine_state.add_env_var(name.into(), Value::string(val, Span::unknown()));
+        }
+    }
+}
+
+/// Convert the environment variables of the engine that are still strings with the
+/// `from_string` closures of `$env.ENV_CONVERSIONS`, like nu does after `env.nu`. `PATH` always
+/// becomes a list of directories, so scripts can `append` to it.
+///
+/// The other way is taken care of by the engine: externals get the variables back as strings
+/// through the `to_string` closures, and `PATH` joined.
+///
+/// ```
+/// use nu_app::{convert_env_vars, create_engine_state, create_stack, eval_source_to_value};
+/// use nu_app::nu_protocol::{PipelineData, Span, Value};
+///
+/// let mut engine_state = create_engine_state();
+/// engine_state.add_env_var("PATH".into(), Value::string("/usr/bin", Span::unknown()));
+/// let mut stack = create_stack();
+/// convert_env_vars(&mut engine_state, &stack).unwrap();
+///
+/// let path = eval_source_to_value(&mut engine_state, &mut stack, b"$env.PATH | describe", "app", PipelineData::Empty, true)
+///     .unwrap();
+/// assert_eq!(path.as_string().unwrap(), "list<string>");
+/// ```
+pub fn convert_env_vars(engine_state: &mut EngineState, stack: &Stack) -> Result<(), ShellError> {
+    match nu_engine::env::convert_env_values(engine_state, stack) {
+        Some(err) => Err(err),
+        None => Ok(()),
+    }
+}
+
+/// Whether the output of the process can be colored: not when `NO_COLOR` is set to anything
+/// but an empty string, and not when stdout isn't a terminal, like in a pipeline or a CI log.
+pub fn supports_colors() -> bool {
+    let no_color = std::env::var_os("NO_COLOR").is_some_and(|no_color| !no_color.is_empty());
+
+    !no_color && std::io::stdout().is_terminal()
+}
+
+/// Turn the colors of tables and errors on or off, by `use_ansi_coloring` of the engine's config
+/// and of the `$env.config` of `stack`, which would turn them back on when the engine takes it.
+///
+/// ```
+/// use nu_app::{create_engine_state, create_stack, set_use_colors, supports_colors};
+///
+/// let mut engine_state = create_engine_state();
+/// let mut stack = create_stack();
+/// set_use_colors(&mut engine_state, &mut stack, supports_colors());
+/// ```
+pub fn set_use_colors(engine_state: &mut EngineState, stack: &mut Stack, colors: bool) {
+    let mut config = engine_state.get_config().clone();
+    config.use_ansi_coloring = colors;
+    engine_state.set_config(&config);
+
+    if let Some(Value::Record {
+        mut cols,
+        mut vals,
+        span,
+    }) = stack.get_env_var(engine_state, "config")
+    {
+        let colors = Value::bool(colors, span);
+        match cols.iter().position(|col| col == "use_ansi_coloring") {
+            Some(idx) => vals[idx] = colors,
+            None => {
+                cols.push("use_ansi_coloring".into());
+                vals.push(colors);
+            }
+        }
+        stack.add_env_var("config".into(), Value::Record { cols, vals, span });
+    }
+}
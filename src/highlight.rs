@@ -0,0 +1,50 @@
+use nu_parser::{flatten_block, FlatShape};
+use nu_protocol::{engine::EngineState, Span};
+
+use crate::helpers::parse_detached;
+
+/// A token of a source, see [`highlight`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HighlightSpan {
+    /// The bytes of the source the token is.
+    pub span: Span,
+    /// What the token is, like a call of a known command or a flag. Its `to_string()` is the
+    /// key of its color in `$env.config.color_config`, like `shape_internalcall`.
+    pub shape: FlatShape,
+}
+
+/// The tokens of `source` to color it with, the way nu's REPL sees them: calls of the engine's
+/// commands are `InternalCall`s, of anything else `External`s, and what doesn't parse is
+/// `Garbage`. The text between the tokens, like whitespace, has no span.
+///
+/// ```
+/// use nu_app::{create_engine_state, highlight::highlight, nu_parser::FlatShape};
+///
+/// let engine_state = create_engine_state();
+/// let tokens = highlight(&engine_state, "ls --all | length");
+///
+/// assert!(matches!(tokens[0].shape, FlatShape::InternalCall(_)));
+/// assert_eq!(tokens[1].shape, FlatShape::Flag);
+/// assert_eq!(&"ls --all | length"[tokens[1].span.start..tokens[1].span.end], "--all");
+/// ```
+pub fn highlight(engine_state: &EngineState, source: &str) -> Vec<HighlightSpan> {
+    parse_detached(
+        engine_state,
+        source.as_bytes(),
+        "highlight",
+        |working_set, block, source_span| {
+            let mut tokens: Vec<HighlightSpan> = flatten_block(working_set, block)
+                .into_iter()
+                // the tokens of modules the source uses are in their own files
+                .filter(|(span, _)| source_span.start <= span.start && span.end <= source_span.end)
+                .map(|(span, shape)| HighlightSpan {
+                    span: Span::new(span.start - source_span.start, span.end - source_span.start),
+                    shape,
+                })
+                .collect();
+            tokens.sort_by_key(|token| token.span.start);
+
+            tokens
+        },
+    )
+}
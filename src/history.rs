@@ -0,0 +1,229 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use chrono::{DateTime, TimeZone, Utc};
+use nu_protocol::{engine::EngineState, HistoryFileFormat, ShellError};
+use reedline::{
+    FileBackedHistory, History, HistoryItem, SearchDirection, SearchQuery, SqliteBackedHistory,
+};
+
+// every sqlite database starts with this
+const SQLITE_HEADER: &[u8] = b"SQLite format 3\0";
+// how nushell's plain text history writes the newlines of an entry
+const NEWLINE_ESCAPE: &str = "<\\n>";
+
+/// The file the REPL keeps its history in, `<config dir>/nu_app/history.txt`, or
+/// `history.sqlite3` when `$env.config.history.file_format` is `sqlite`.
+pub fn history_path(format: HistoryFileFormat) -> Option<PathBuf> {
+    let file = match format {
+        HistoryFileFormat::PlainText => "history.txt",
+        HistoryFileFormat::Sqlite => "history.sqlite3",
+    };
+    nu_path::config_dir().map(|dir| dir.join("nu_app").join(file))
+}
+
+/// Open the REPL's history at [`history_path`], in the format and with the size of
+/// `$env.config.history`, for the line editor to recall and add entries to.
+pub fn open_repl_history(engine_state: &EngineState) -> Result<Box<dyn History>, ShellError> {
+    let format = engine_state.config.history_file_format;
+    let path = history_path(format).ok_or_else(|| {
+        ShellError::IOError("could not find the config directory for the history".into())
+    })?;
+
+    open_history(&path, format, history_capacity(engine_state))
+}
+
+/// The entries of the REPL's history, oldest first. There are none before the REPL first ran.
+pub fn read_repl_history(engine_state: &EngineState) -> Result<Vec<HistoryItem>, ShellError> {
+    let format = engine_state.config.history_file_format;
+    match history_path(format) {
+        Some(path) if path.exists() => read_history(&path, format),
+        _ => Ok(vec![]),
+    }
+}
+
+/// Add the entries of another shell's history file to the REPL's history, oldest first.
+///
+/// Reads bash and zsh history files, with or without timestamps, and both kinds of nushell
+/// history. Returns how many entries were read.
+pub fn import_history(engine_state: &EngineState, path: &Path) -> Result<usize, ShellError> {
+    let items = read_history_file(path)?;
+
+    let format = engine_state.config.history_file_format;
+    let own_path = history_path(format).ok_or_else(|| {
+        ShellError::IOError("could not find the config directory for the history".into())
+    })?;
+    let mut history = open_history(&own_path, format, history_capacity(engine_state))?;
+    save_items(&mut *history, &own_path, items)
+}
+
+/// Write the REPL's history to `path` in `format`, for nushell or another copy of this app,
+/// replacing the file if there is one.
+///
+/// Returns how many entries were written.
+pub fn export_history(
+    engine_state: &EngineState,
+    path: &Path,
+    format: HistoryFileFormat,
+) -> Result<usize, ShellError> {
+    let items = read_repl_history(engine_state)?;
+
+    // both kinds of history would add to what's in the file
+    if path.exists() {
+        fs::remove_file(path).map_err(|err| {
+            ShellError::IOError(format!("could not replace {}: {err}", path.display()))
+        })?;
+    }
+    let mut history = open_history(path, format, items.len().max(1))?;
+    save_items(&mut *history, path, items)
+}
+
+/// Read the entries of a history file, telling its format from the contents.
+pub fn read_history_file(path: &Path) -> Result<Vec<HistoryItem>, ShellError> {
+    let contents = fs::read(path)
+        .map_err(|err| ShellError::IOError(format!("could not read {}: {err}", path.display())))?;
+
+    if contents.starts_with(SQLITE_HEADER) {
+        read_history(path, HistoryFileFormat::Sqlite)
+    } else {
+        Ok(parse_plain_text(&contents))
+    }
+}
+
+fn read_history(path: &Path, format: HistoryFileFormat) -> Result<Vec<HistoryItem>, ShellError> {
+    let history = open_history(path, format, usize::MAX - 1)?;
+
+    history
+        .search(SearchQuery::everything(SearchDirection::Forward, None))
+        .map_err(|err| history_error(path, err))
+}
+
+fn open_history(
+    path: &Path,
+    format: HistoryFileFormat,
+    capacity: usize,
+) -> Result<Box<dyn History>, ShellError> {
+    Ok(match format {
+        HistoryFileFormat::PlainText => Box::new(
+            FileBackedHistory::with_file(capacity, path.into()).map_err(|err| {
+                ShellError::IOError(format!("could not open {}: {err}", path.display()))
+            })?,
+        ),
+        // a database has no limit on the entries
+        HistoryFileFormat::Sqlite => Box::new(
+            SqliteBackedHistory::with_file(path.into()).map_err(|err| history_error(path, err))?,
+        ),
+    })
+}
+
+fn save_items(
+    history: &mut dyn History,
+    path: &Path,
+    items: Vec<HistoryItem>,
+) -> Result<usize, ShellError> {
+    let count = items.len();
+    for mut item in items {
+        // the ids belong to the history the entry came from
+        item.id = None;
+        history.save(item).map_err(|err| history_error(path, err))?;
+    }
+    history
+        .sync()
+        .map_err(|err| ShellError::IOError(format!("could not write {}: {err}", path.display())))?;
+
+    Ok(count)
+}
+
+fn history_capacity(engine_state: &EngineState) -> usize {
+    usize::try_from(engine_state.config.max_history_size).unwrap_or(usize::MAX - 1)
+}
+
+fn history_error(path: &Path, err: impl std::fmt::Display) -> ShellError {
+    ShellError::GenericError(
+        format!("Could not use the history in {}", path.display()),
+        err.to_string(),
+        None,
+        None,
+        vec![],
+    )
+}
+
+// bash writes `#<unix time>` above each command when HISTTIMEFORMAT is set, zsh's extended
+// history writes `: <unix time>:<seconds it ran>;<command>` and continues multi-line commands
+// with a `\`, and nushell's plain text history escapes newlines as `<\n>`
+fn parse_plain_text(contents: &[u8]) -> Vec<HistoryItem> {
+    let contents = unmetafy(contents);
+    let mut items = vec![];
+    let mut timestamp: Option<DateTime<Utc>> = None;
+    let mut lines = contents.lines();
+
+    while let Some(line) = lines.next() {
+        if let Some(time) = line.strip_prefix('#').and_then(parse_timestamp) {
+            timestamp = Some(time);
+            continue;
+        }
+
+        let mut item = match parse_zsh_line(line) {
+            Some((time, mut command)) => {
+                while command.ends_with('\\') {
+                    command.pop();
+                    command.push('\n');
+                    match lines.next() {
+                        Some(next) => command.push_str(next),
+                        None => break,
+                    }
+                }
+                let mut item = HistoryItem::from_command_line(command);
+                item.start_timestamp = time;
+                item
+            }
+            None => HistoryItem::from_command_line(line.replace(NEWLINE_ESCAPE, "\n")),
+        };
+        if item.start_timestamp.is_none() {
+            item.start_timestamp = timestamp.take();
+        }
+
+        if !item.command_line.trim().is_empty() {
+            items.push(item);
+        }
+    }
+
+    items
+}
+
+fn parse_zsh_line(line: &str) -> Option<(Option<DateTime<Utc>>, String)> {
+    let (meta, command) = line.strip_prefix(": ")?.split_once(';')?;
+    let (time, _duration) = meta.split_once(':')?;
+
+    Some((parse_timestamp(time), command.into()))
+}
+
+fn parse_timestamp(time: &str) -> Option<DateTime<Utc>> {
+    let seconds = time.trim().parse().ok()?;
+    Utc.timestamp_opt(seconds, 0).single()
+}
+
+// zsh writes bytes it treats specially as 0x83 followed by the byte xor 32, which leaves
+// anything but ascii invalid utf-8
+fn unmetafy(contents: &[u8]) -> String {
+    if let Ok(contents) = std::str::from_utf8(contents) {
+        return contents.into();
+    }
+
+    let mut bytes = Vec::with_capacity(contents.len());
+    let mut iter = contents.iter();
+    while let Some(&byte) = iter.next() {
+        match byte {
+            0x83 => {
+                if let Some(&next) = iter.next() {
+                    bytes.push(next ^ 32);
+                }
+            }
+            _ => bytes.push(byte),
+        }
+    }
+
+    String::from_utf8_lossy(&bytes).into_owned()
+}
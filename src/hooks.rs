@@ -0,0 +1,220 @@
+use std::{
+    cell::Cell,
+    path::Path,
+    sync::{Arc, Mutex, PoisonError},
+};
+
+use nu_command::hook::eval_hook;
+use nu_protocol::{
+    engine::{EngineState, Stack},
+    PipelineData, ShellError, Value,
+};
+
+use crate::helpers::report_error_new;
+
+type RustHook = Arc<dyn Fn(&mut EngineState, &mut Stack, &str) + Send + Sync>;
+
+static PRE_EXECUTION: Mutex<Vec<RustHook>> = Mutex::new(vec![]);
+static POST_EXECUTION: Mutex<Vec<RustHook>> = Mutex::new(vec![]);
+
+thread_local! {
+    // a hook that evaluates a source itself doesn't set off the hooks again
+    static IN_HOOK: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Call `hook` with each source right before it's evaluated, after the `pre_execution` hooks of
+/// `$env.config`. It's called for the sources of all engines of the process.
+///
+/// ```
+/// use nu_app::{create_engine_state, create_stack, eval_source_to_value, nu_protocol::PipelineData};
+/// use nu_app::hooks::add_pre_execution_hook;
+///
+/// add_pre_execution_hook(|_engine_state, stack, _source| {
+///     nu_app::set_env_var(stack, "RAN_HOOK", true);
+/// });
+///
+/// let mut engine_state = create_engine_state();
+/// let mut stack = create_stack();
+/// let ran_hook = eval_source_to_value(&mut engine_state, &mut stack, b"$env.RAN_HOOK", "app", PipelineData::Empty, true)
+///     .unwrap();
+/// assert!(ran_hook.as_bool().unwrap());
+/// ```
+pub fn add_pre_execution_hook(
+    hook: impl Fn(&mut EngineState, &mut Stack, &str) + Send + Sync + 'static,
+) {
+    lock(&PRE_EXECUTION).push(Arc::new(hook));
+}
+
+/// Call `hook` with each source once it's evaluated, after the `post_execution` hooks of
+/// `$env.config`. The output of the source may still be streaming then, it's called before the
+/// output is printed or collected.
+///
+/// It's called for every source the pre-execution hooks were called for, also one that doesn't
+/// parse, so the two can be paired to time sources or hold a lock while they run:
+///
+/// ```
+/// use std::sync::atomic::{AtomicUsize, Ordering};
+///
+/// use nu_app::{create_engine_state, create_stack, eval_source_to_value, nu_protocol::PipelineData};
+/// use nu_app::hooks::{add_post_execution_hook, add_pre_execution_hook};
+///
+/// static RUNNING: AtomicUsize = AtomicUsize::new(0);
+/// add_pre_execution_hook(|_, _, _| {
+///     RUNNING.fetch_add(1, Ordering::SeqCst);
+/// });
+/// add_post_execution_hook(|_, _, _| {
+///     RUNNING.fetch_sub(1, Ordering::SeqCst);
+/// });
+///
+/// let mut engine_state = create_engine_state();
+/// let mut stack = create_stack();
+/// let result = eval_source_to_value(&mut engine_state, &mut stack, b"[1, 2", "app", PipelineData::Empty, true);
+/// assert!(result.is_err());
+/// assert_eq!(RUNNING.load(Ordering::SeqCst), 0);
+/// ```
+pub fn add_post_execution_hook(
+    hook: impl Fn(&mut EngineState, &mut Stack, &str) + Send + Sync + 'static,
+) {
+    lock(&POST_EXECUTION).push(Arc::new(hook));
+}
+
+fn lock(hooks: &Mutex<Vec<RustHook>>) -> std::sync::MutexGuard<'_, Vec<RustHook>> {
+    hooks.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+// run `$env.config.hooks.pre_execution` and the hooks of `add_pre_execution_hook`
+pub(crate) fn run_pre_execution(engine_state: &mut EngineState, stack: &mut Stack, source: &[u8]) {
+    run_hooks(engine_state, stack, source, "pre_execution", &PRE_EXECUTION);
+}
+
+// run `$env.config.hooks.post_execution` and the hooks of `add_post_execution_hook`
+pub(crate) fn run_post_execution(engine_state: &mut EngineState, stack: &mut Stack, source: &[u8]) {
+    run_hooks(
+        engine_state,
+        stack,
+        source,
+        "post_execution",
+        &POST_EXECUTION,
+    );
+}
+
+// evaluate config files and the like without running the hooks for them, like nu does
+pub(crate) fn without_hooks<T>(f: impl FnOnce() -> T) -> T {
+    let in_hook = IN_HOOK.with(|in_hook| in_hook.replace(true));
+    let result = f();
+    IN_HOOK.with(|cell| cell.set(in_hook));
+
+    result
+}
+
+fn run_hooks(
+    engine_state: &mut EngineState,
+    stack: &mut Stack,
+    source: &[u8],
+    name: &str,
+    rust_hooks: &Mutex<Vec<RustHook>>,
+) {
+    if IN_HOOK.with(Cell::get) {
+        return;
+    }
+    IN_HOOK.with(|in_hook| in_hook.set(true));
+
+    let source = String::from_utf8_lossy(source);
+    if let Some(hook) = config_hook(engine_state, stack, name) {
+        // like in nu's REPL, `commandline` is the source the hook runs for
+        engine_state
+            .repl_state
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .buffer = source.to_string();
+
+        if let Err(err) = eval_hook(engine_state, stack, None, vec![], &hook) {
+            report_error_new(engine_state, &err);
+        }
+    }
+
+    // the lock isn't held while they run, so a hook can add another one
+    let rust_hooks = lock(rust_hooks).clone();
+    for hook in rust_hooks {
+        hook(engine_state, stack, &source);
+    }
+
+    IN_HOOK.with(|in_hook| in_hook.set(false));
+}
+
+/// Hand `pipeline_data` to `$env.config.hooks.display_output` when it has one, and return what
+/// the hook made of it to print instead. Externals print their output themselves, like in nu.
+pub(crate) fn display_output(
+    engine_state: &mut EngineState,
+    stack: &mut Stack,
+    pipeline_data: PipelineData,
+) -> Result<PipelineData, ShellError> {
+    if matches!(pipeline_data, PipelineData::ExternalStream { .. }) {
+        return Ok(pipeline_data);
+    }
+
+    match config_hook(engine_state, stack, "display_output") {
+        Some(hook) => eval_hook(engine_state, stack, Some(pipeline_data), vec![], &hook),
+        None => Ok(pipeline_data),
+    }
+}
+
+// `$env.config.hooks.<name>` as the record has it, the engine's config only knows some of them
+fn config_hook(engine_state: &EngineState, stack: &Stack, name: &str) -> Option<Value> {
+    stack
+        .get_env_var(engine_state, "config")?
+        .get_data_by_key("hooks")?
+        .get_data_by_key(name)
+        .filter(|hook| !matches!(hook, Value::Nothing { .. }))
+}
+
+/// Move the environment of `stack` to the engine like `EngineState::merge_env`, keeping the
+/// `post_execution` hook of `$env.config`, which the engine's config doesn't know and would
+/// drop all the hooks for.
+pub(crate) fn merge_env(
+    engine_state: &mut EngineState,
+    stack: &mut Stack,
+    cwd: impl AsRef<Path>,
+) -> Result<(), ShellError> {
+    let mut config = stack.get_env_var(engine_state, "config");
+    let post_execution = config
+        .as_mut()
+        .and_then(|config| take_hook(config, "post_execution"));
+    if let (Some(config), Some(_)) = (config, &post_execution) {
+        stack.add_env_var("config".into(), config);
+    }
+
+    let result = engine_state.merge_env(stack, cwd);
+
+    if let Some(hook) = post_execution {
+        if let Some(mut config) = engine_state.get_env_var("config").cloned() {
+            if let Some((cols, vals)) = hooks_mut(&mut config) {
+                cols.push("post_execution".into());
+                vals.push(hook);
+            }
+            engine_state.add_env_var("config".into(), config);
+        }
+    }
+
+    result
+}
+
+fn take_hook(config: &mut Value, name: &str) -> Option<Value> {
+    let (cols, vals) = hooks_mut(config)?;
+    let idx = cols.iter().position(|col| col == name)?;
+    cols.remove(idx);
+
+    Some(vals.remove(idx))
+}
+
+fn hooks_mut(config: &mut Value) -> Option<(&mut Vec<String>, &mut Vec<Value>)> {
+    let Value::Record { cols, vals, .. } = config else {
+        return None;
+    };
+    let idx = cols.iter().position(|col| col == "hooks")?;
+
+    match &mut vals[idx] {
+        Value::Record { cols, vals, .. } => Some((cols, vals)),
+        _ => None,
+    }
+}
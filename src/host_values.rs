@@ -0,0 +1,145 @@
+use std::{any::Any, cmp::Ordering, fmt, sync::Arc};
+
+use nu_protocol::{ast::PathMember, CustomValue, ShellError, Span, Value};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// A type of the application whose values flow through pipelines as they are, e.g. handles to
+/// its documents, and that its commands take back with [`as_host_value`].
+///
+/// ```
+/// use nu_app::host_values::{as_host_value, host_value, HostType};
+/// use nu_app::nu_protocol::{ShellError, Span, Value};
+///
+/// #[derive(Debug)]
+/// struct Document {
+///     title: String,
+/// }
+///
+/// impl HostType for Document {
+///     fn type_name(&self) -> String {
+///         "document".into()
+///     }
+///
+///     fn to_base_value(&self, span: Span) -> Result<Value, ShellError> {
+///         Ok(Value::record(
+///             vec!["title".into()],
+///             vec![Value::string(&self.title, span)],
+///             span,
+///         ))
+///     }
+/// }
+///
+/// let value = host_value(Document { title: "notes".into() }, Span::unknown());
+/// let document: &Document = as_host_value(&value).unwrap();
+/// assert_eq!(document.title, "notes");
+/// ```
+pub trait HostType: Any + fmt::Debug + Send + Sync {
+    /// What `describe` calls the values.
+    fn type_name(&self) -> String;
+
+    /// The plain value the value is shown as, and the one commands that don't know the type,
+    /// like `to json`, work with.
+    fn to_base_value(&self, span: Span) -> Result<Value, ShellError>;
+
+    /// The value of `$value.<index>`, the one of the base value by default.
+    fn follow_path_int(&self, index: usize, span: Span) -> Result<Value, ShellError> {
+        let member = PathMember::Int {
+            val: index,
+            span,
+            optional: false,
+        };
+        self.to_base_value(span)?.follow_cell_path(&[member], false)
+    }
+
+    /// The value of `$value.<column>`, the one of the base value by default.
+    fn follow_path_string(&self, column: String, span: Span) -> Result<Value, ShellError> {
+        let member = PathMember::String {
+            val: column,
+            span,
+            optional: false,
+        };
+        self.to_base_value(span)?.follow_cell_path(&[member], false)
+    }
+
+    /// How the value compares to `other`, for `sort` and the comparison operators. Values that
+    /// don't compare are sorted as they come.
+    fn partial_cmp(&self, _other: &Value) -> Option<Ordering> {
+        None
+    }
+}
+
+/// Wrap `val` in a custom value, so it's passed on to the commands as it is.
+pub fn host_value(val: impl HostType, span: Span) -> Value {
+    Value::custom_value(Box::new(HostValue(Arc::new(val))), span)
+}
+
+/// The `T` a value made with [`host_value`] wraps, or an error for any other value.
+pub fn as_host_value<T: HostType>(value: &Value) -> Result<&T, ShellError> {
+    let host_value = match value {
+        Value::CustomValue { val, .. } => val.as_any().downcast_ref::<HostValue>(),
+        _ => None,
+    };
+
+    match host_value.and_then(|host_value| (host_value.0.as_ref() as &dyn Any).downcast_ref()) {
+        Some(val) => Ok(val),
+        None => Err(ShellError::CantConvert {
+            to_type: std::any::type_name::<T>().into(),
+            from_type: value.get_type().to_string(),
+            span: value.span().unwrap_or_else(|_| Span::unknown()),
+            help: None,
+        }),
+    }
+}
+
+// copies share the application's value, like handles do
+#[derive(Debug, Clone)]
+struct HostValue(Arc<dyn HostType>);
+
+#[typetag::serde]
+impl CustomValue for HostValue {
+    fn clone_value(&self, span: Span) -> Value {
+        Value::custom_value(Box::new(self.clone()), span)
+    }
+
+    fn value_string(&self) -> String {
+        self.0.type_name()
+    }
+
+    fn to_base_value(&self, span: Span) -> Result<Value, ShellError> {
+        self.0.to_base_value(span)
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn follow_path_int(&self, count: usize, span: Span) -> Result<Value, ShellError> {
+        self.0.follow_path_int(count, span)
+    }
+
+    fn follow_path_string(&self, column_name: String, span: Span) -> Result<Value, ShellError> {
+        self.0.follow_path_string(column_name, span)
+    }
+
+    fn partial_cmp(&self, other: &Value) -> Option<Ordering> {
+        self.0.partial_cmp(other)
+    }
+}
+
+// plugins get the base value, there's nothing to turn back into the application's
+impl Serialize for HostValue {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.0
+            .to_base_value(Span::unknown())
+            .map_err(serde::ser::Error::custom)?
+            .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for HostValue {
+    fn deserialize<D: Deserializer<'de>>(_deserializer: D) -> Result<Self, D::Error> {
+        Err(serde::de::Error::custom(
+            "values of the application can't be deserialized",
+        ))
+    }
+}
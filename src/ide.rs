@@ -0,0 +1,189 @@
+use nu_parser::{flatten_block, FlatShape};
+use nu_protocol::{
+    ast::Block,
+    engine::{EngineState, StateWorkingSet},
+    Span,
+};
+use serde_json::json;
+
+use crate::{
+    ast::parse_ast,
+    helpers::{check_source, parse_detached, relative_span},
+};
+
+/// What a `--ide-*` flag of the binary asks about a script, see [`ide_json`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IdeRequest {
+    /// The problems the parser finds, like `--ide-check`.
+    Check,
+    /// The tree of the parsed script, like `--ide-ast`.
+    Ast,
+    /// What's at a byte offset, like `--ide-hover <OFFSET>`.
+    Hover(usize),
+}
+
+/// What's at a position of a source, see [`hover`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Hover {
+    /// What to show, as markdown.
+    pub text: String,
+    /// The bytes of the source it's about.
+    pub span: Span,
+}
+
+/// What an editor shows when the pointer is at byte `offset` of `source`: the usage and
+/// signature of the command called there, the type of a variable, or that a call runs an
+/// external. `None` for anything else, like whitespace and literals.
+///
+/// ```
+/// use nu_app::{create_engine_state, ide::hover};
+///
+/// let engine_state = create_engine_state();
+/// let source = b"let count = ls | length; $count + 1";
+///
+/// let hover_ls = hover(&engine_state, source, "script.nu", 12).unwrap();
+/// assert!(hover_ls.text.contains("ls {flags}"));
+///
+/// let hover_count = hover(&engine_state, source, "script.nu", 26).unwrap();
+/// assert_eq!(hover_count.text, "`$count: int`");
+/// assert_eq!((hover_count.span.start, hover_count.span.end), (25, 31));
+/// ```
+pub fn hover(
+    engine_state: &EngineState,
+    source: &[u8],
+    fname: &str,
+    offset: usize,
+) -> Option<Hover> {
+    parse_detached(
+        engine_state,
+        source,
+        fname,
+        |working_set, block, source_span| {
+            let (span, shape) = token_at(working_set, block, source_span, offset)?;
+            let token = String::from_utf8_lossy(&source[span.start..span.end]);
+
+            let text = match shape {
+                FlatShape::InternalCall(decl_id) => {
+                    let decl = working_set.get_decl(decl_id);
+                    let signature = format!("```nu\n{}\n```", decl.signature().call_signature());
+                    [decl.usage(), decl.extra_usage(), &signature]
+                        .into_iter()
+                        .filter(|part| !part.is_empty())
+                        .collect::<Vec<_>>()
+                        .join("\n\n")
+                }
+                FlatShape::Variable(var_id) | FlatShape::VarDecl(var_id) => {
+                    let ty = &working_set.get_variable(var_id).ty;
+                    format!("`${}: {ty}`", token.trim_start_matches('$'))
+                }
+                FlatShape::External => format!("runs the external command `{token}`"),
+                _ => return None,
+            };
+
+            Some(Hover { text, span })
+        },
+    )
+}
+
+/// Where what's at byte `offset` of `source` is defined in it: the body of the `def` of a
+/// command that's called there, or the declaration of a variable. `None` for the commands of
+/// the engine and anything defined in other files.
+///
+/// ```
+/// use nu_app::{create_engine_state, ide::definition};
+///
+/// let engine_state = create_engine_state();
+/// let source = b"let count = 1; $count + 1";
+///
+/// let declaration = definition(&engine_state, source, "script.nu", 16).unwrap();
+/// assert_eq!(&source[declaration.start..declaration.end], b"count");
+/// ```
+pub fn definition(
+    engine_state: &EngineState,
+    source: &[u8],
+    fname: &str,
+    offset: usize,
+) -> Option<Span> {
+    parse_detached(
+        engine_state,
+        source,
+        fname,
+        |working_set, block, source_span| {
+            let span = match token_at(working_set, block, source_span, offset)?.1 {
+                FlatShape::InternalCall(decl_id) => {
+                    let block_id = working_set.get_decl(decl_id).get_block_id()?;
+                    working_set.get_block(block_id).span?
+                }
+                FlatShape::Variable(var_id) | FlatShape::VarDecl(var_id) => {
+                    working_set.get_variable(var_id).declaration_span
+                }
+                _ => return None,
+            };
+
+            let span = relative_span(span, source_span);
+            (span.end > 0).then_some(span)
+        },
+    )
+}
+
+// the token at `offset` of the source, with its span in the source
+fn token_at(
+    working_set: &StateWorkingSet,
+    block: &Block,
+    source_span: Span,
+    offset: usize,
+) -> Option<(Span, FlatShape)> {
+    let offset = source_span.start + offset;
+    let (span, shape) = flatten_block(working_set, block)
+        .into_iter()
+        .find(|(span, _)| span.start <= offset && offset < span.end)?;
+
+    Some((relative_span(span, source_span), shape))
+}
+
+/// Answer `request` about `source` with JSON on a line of its own for each answer, the way
+/// editor extensions read the `--ide-*` flags of the binary:
+///
+/// - `Check` gives an object for each diagnostic, with its `type`, `severity`, `message`,
+///   `label`, `help` and the `span` of bytes it's about;
+/// - `Ast` gives the tree of [`parse_ast`];
+/// - `Hover` gives the `hover` text and its `span`, or nothing when there's none.
+///
+/// ```
+/// use nu_app::{create_engine_state, ide::{ide_json, IdeRequest}};
+///
+/// let engine_state = create_engine_state();
+/// let json = ide_json(&engine_state, b"let x: int = 'one'", "script.nu", IdeRequest::Check);
+/// let diagnostic: serde_json::Value = serde_json::from_str(json.lines().next().unwrap()).unwrap();
+/// assert_eq!(diagnostic["label"], "expected int, found string");
+/// assert_eq!(diagnostic["span"]["start"], 13);
+/// ```
+pub fn ide_json(
+    engine_state: &EngineState,
+    source: &[u8],
+    fname: &str,
+    request: IdeRequest,
+) -> String {
+    let answers = match request {
+        IdeRequest::Check => check_source(engine_state, source, fname)
+            .into_iter()
+            .map(|diagnostic| {
+                json!({
+                    "type": "diagnostic",
+                    "severity": format!("{:?}", diagnostic.severity),
+                    "message": diagnostic.message,
+                    "label": diagnostic.label,
+                    "help": diagnostic.help,
+                    "span": diagnostic.span,
+                })
+            })
+            .collect(),
+        IdeRequest::Ast => vec![json!(parse_ast(engine_state, source, fname).root)],
+        IdeRequest::Hover(offset) => hover(engine_state, source, fname, offset)
+            .map(|hover| json!({ "hover": hover.text, "span": hover.span }))
+            .into_iter()
+            .collect(),
+    };
+
+    answers.iter().map(|answer| format!("{answer}\n")).collect()
+}
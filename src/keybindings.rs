@@ -0,0 +1,451 @@
+use std::sync::{Arc, Mutex, PoisonError};
+
+use nu_protocol::{
+    engine::{EngineState, Stack},
+    extract_value, Config, ParsedKeybinding, ShellError, Span, Value,
+};
+use reedline::{
+    default_emacs_keybindings, default_vi_insert_keybindings, default_vi_normal_keybindings,
+    EditCommand, Keybindings, ReedlineEvent,
+};
+pub use reedline::{KeyCode, KeyModifiers};
+
+use crate::helpers::report_error_new;
+
+type KeyFn = Arc<dyn Fn(&mut EngineState, &mut Stack) + Send + Sync>;
+
+#[derive(Clone)]
+enum KeyAction {
+    Source(String),
+    Fn(KeyFn),
+}
+
+struct HostKeybinding {
+    modifiers: KeyModifiers,
+    key: KeyCode,
+    action: KeyAction,
+}
+
+static HOST_KEYBINDINGS: Mutex<Vec<HostKeybinding>> = Mutex::new(vec![]);
+
+// the line editor hands back the line of a host command as if it was entered, the ones of
+// closures are this and their index, which can't be typed
+const FN_PREFIX: &str = "\0nu_app keybinding ";
+
+/// The name of the REPL's menu of completions, for `{send: menu, name: completion_menu}`.
+pub(crate) const COMPLETION_MENU: &str = "completion_menu";
+
+/// Run `source` in the REPL when `key` is pressed with `modifiers`, as if it was entered.
+///
+/// ```no_run
+/// use nu_app::keybindings::{bind_source, KeyCode, KeyModifiers};
+///
+/// bind_source(KeyModifiers::CONTROL, KeyCode::Char('g'), "git status");
+/// ```
+///
+/// The bindings of `$env.config.keybindings` win over the host's for the same key.
+pub fn bind_source(modifiers: KeyModifiers, key: KeyCode, source: impl Into<String>) {
+    bind(modifiers, key, KeyAction::Source(source.into()));
+}
+
+/// Call `f` with the REPL's engine and stack when `key` is pressed with `modifiers`, leaving
+/// what was typed so far.
+pub fn bind_fn(
+    modifiers: KeyModifiers,
+    key: KeyCode,
+    f: impl Fn(&mut EngineState, &mut Stack) + Send + Sync + 'static,
+) {
+    bind(modifiers, key, KeyAction::Fn(Arc::new(f)));
+}
+
+fn bind(modifiers: KeyModifiers, key: KeyCode, action: KeyAction) {
+    let mut bindings = HOST_KEYBINDINGS
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner);
+    bindings.retain(|binding| (binding.modifiers, binding.key) != (modifiers, key));
+    bindings.push(HostKeybinding {
+        modifiers,
+        key,
+        action,
+    });
+}
+
+/// The closure bound with [`bind_fn`] that the line editor handed back `line` for.
+pub(crate) fn host_fn(line: &str) -> Option<KeyFn> {
+    let index: usize = line.strip_prefix(FN_PREFIX)?.parse().ok()?;
+    let bindings = HOST_KEYBINDINGS
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner);
+
+    match &bindings.get(index)?.action {
+        KeyAction::Fn(f) => Some(f.clone()),
+        KeyAction::Source(_) => None,
+    }
+}
+
+/// The keybindings of each edit mode: reedline's defaults, the host's and the ones of
+/// `$env.config.keybindings`, in that order.
+pub(crate) struct ModeKeybindings {
+    pub(crate) emacs: Keybindings,
+    pub(crate) vi_insert: Keybindings,
+    pub(crate) vi_normal: Keybindings,
+}
+
+impl ModeKeybindings {
+    /// A keybinding of the config that's wrong is reported and left out, the others still work.
+    pub(crate) fn new(engine_state: &EngineState) -> Self {
+        let mut bindings = ModeKeybindings {
+            emacs: default_emacs_keybindings(),
+            vi_insert: default_vi_insert_keybindings(),
+            vi_normal: default_vi_normal_keybindings(),
+        };
+
+        // tab opens the completions and goes through them, like in nu
+        for keybindings in [
+            &mut bindings.emacs,
+            &mut bindings.vi_insert,
+            &mut bindings.vi_normal,
+        ] {
+            keybindings.add_binding(
+                KeyModifiers::NONE,
+                KeyCode::Tab,
+                ReedlineEvent::UntilFound(vec![
+                    ReedlineEvent::Menu(COMPLETION_MENU.into()),
+                    ReedlineEvent::MenuNext,
+                ]),
+            );
+            keybindings.add_binding(
+                KeyModifiers::SHIFT,
+                KeyCode::BackTab,
+                ReedlineEvent::MenuPrevious,
+            );
+        }
+
+        let host_bindings = HOST_KEYBINDINGS
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        for (index, binding) in host_bindings.iter().enumerate() {
+            let event = ReedlineEvent::ExecuteHostCommand(match &binding.action {
+                KeyAction::Source(source) => source.clone(),
+                KeyAction::Fn(_) => format!("{FN_PREFIX}{index}"),
+            });
+            for keybindings in [
+                &mut bindings.emacs,
+                &mut bindings.vi_insert,
+                &mut bindings.vi_normal,
+            ] {
+                keybindings.add_binding(binding.modifiers, binding.key, event.clone());
+            }
+        }
+        drop(host_bindings);
+
+        let config = engine_state.get_config();
+        for keybinding in &config.keybindings {
+            if let Err(err) = bindings.add(&keybinding.mode, keybinding, config) {
+                report_error_new(engine_state, &err);
+            }
+        }
+
+        bindings
+    }
+
+    fn add(
+        &mut self,
+        mode: &Value,
+        keybinding: &ParsedKeybinding,
+        config: &Config,
+    ) -> Result<(), ShellError> {
+        match mode {
+            Value::String { val, span } => {
+                let keybindings = match val.as_str() {
+                    "emacs" => &mut self.emacs,
+                    "vi_insert" => &mut self.vi_insert,
+                    "vi_normal" => &mut self.vi_normal,
+                    mode => {
+                        return Err(ShellError::UnsupportedConfigValue(
+                            "emacs, vi_insert or vi_normal".into(),
+                            mode.into(),
+                            *span,
+                        ))
+                    }
+                };
+                add_keybinding(keybindings, keybinding, config)
+            }
+            Value::List { vals, .. } => vals
+                .iter()
+                .try_for_each(|mode| self.add(mode, keybinding, config)),
+            value => Err(ShellError::UnsupportedConfigValue(
+                "string or list of strings".into(),
+                value.into_abbreviated_string(config),
+                value.span()?,
+            )),
+        }
+    }
+}
+
+// the names are the ones nu's config uses
+fn add_keybinding(
+    keybindings: &mut Keybindings,
+    keybinding: &ParsedKeybinding,
+    config: &Config,
+) -> Result<(), ShellError> {
+    let modifier_name = keybinding.modifier.into_string("", config).to_lowercase();
+    let modifiers = match modifier_name.as_str() {
+        "control" => KeyModifiers::CONTROL,
+        "shift" => KeyModifiers::SHIFT,
+        "alt" => KeyModifiers::ALT,
+        "none" => KeyModifiers::NONE,
+        "shift_alt" | "alt_shift" => KeyModifiers::SHIFT | KeyModifiers::ALT,
+        "control_shift" | "shift_control" => KeyModifiers::CONTROL | KeyModifiers::SHIFT,
+        "control_alt" | "alt_control" => KeyModifiers::CONTROL | KeyModifiers::ALT,
+        "control_alt_shift" | "control_shift_alt" => {
+            KeyModifiers::CONTROL | KeyModifiers::ALT | KeyModifiers::SHIFT
+        }
+        _ => {
+            return Err(ShellError::UnsupportedConfigValue(
+                "CONTROL, SHIFT, ALT or NONE".into(),
+                modifier_name,
+                keybinding.modifier.span()?,
+            ))
+        }
+    };
+
+    let keycode_name = keybinding.keycode.into_string("", config).to_lowercase();
+    let keycode_span = keybinding.keycode.span()?;
+    let unsupported_keycode = |expected: &str| {
+        ShellError::UnsupportedConfigValue(expected.into(), keycode_name.clone(), keycode_span)
+    };
+    let key = match keycode_name.as_str() {
+        "backspace" => KeyCode::Backspace,
+        "enter" => KeyCode::Enter,
+        "space" => KeyCode::Char(' '),
+        "down" => KeyCode::Down,
+        "up" => KeyCode::Up,
+        "left" => KeyCode::Left,
+        "right" => KeyCode::Right,
+        "home" => KeyCode::Home,
+        "end" => KeyCode::End,
+        "pageup" => KeyCode::PageUp,
+        "pagedown" => KeyCode::PageDown,
+        "tab" => KeyCode::Tab,
+        "backtab" => KeyCode::BackTab,
+        "delete" => KeyCode::Delete,
+        "insert" => KeyCode::Insert,
+        "null" => KeyCode::Null,
+        "esc" | "escape" => KeyCode::Esc,
+        name => {
+            if let Some(char) = name.strip_prefix("char_") {
+                let mut chars = char.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(char), None) => KeyCode::Char(char),
+                    _ => return Err(unsupported_keycode("char_<character>")),
+                }
+            } else if let Some(number) = name.strip_prefix('f') {
+                match number.parse() {
+                    Ok(number @ 1..=20) => KeyCode::F(number),
+                    _ => return Err(unsupported_keycode("f1 to f20")),
+                }
+            } else {
+                return Err(unsupported_keycode("a key name like char_a or enter"));
+            }
+        }
+    };
+
+    match parse_event(&keybinding.event, config)? {
+        Some(event) => keybindings.add_binding(modifiers, key, event),
+        // `event: null` unbinds the key
+        None => {
+            keybindings.remove_binding(modifiers, key);
+        }
+    }
+
+    Ok(())
+}
+
+fn parse_event(value: &Value, config: &Config) -> Result<Option<ReedlineEvent>, ShellError> {
+    match value {
+        Value::Record { cols, vals, span } => {
+            if let Ok(name) = extract_value("send", cols, vals, *span) {
+                let name = name.into_string("", config).to_lowercase();
+                send_event(&name, cols, vals, config, *span).map(Some)
+            } else if let Ok(name) = extract_value("edit", cols, vals, *span) {
+                let name = name.into_string("", config).to_lowercase();
+                let edit = edit_command(&name, cols, vals, config, *span)?;
+                Ok(Some(ReedlineEvent::Edit(vec![edit])))
+            } else if let Ok(events) = extract_value("until", cols, vals, *span) {
+                Ok(Some(ReedlineEvent::UntilFound(parse_events(
+                    events, config,
+                )?)))
+            } else {
+                Err(ShellError::MissingConfigValue(
+                    "send, edit or until".into(),
+                    *span,
+                ))
+            }
+        }
+        Value::List { .. } => Ok(Some(ReedlineEvent::Multiple(parse_events(value, config)?))),
+        Value::Nothing { .. } => Ok(None),
+        value => Err(ShellError::UnsupportedConfigValue(
+            "record or list of records, null to unbind key".into(),
+            value.into_abbreviated_string(config),
+            value.span()?,
+        )),
+    }
+}
+
+fn parse_events(value: &Value, config: &Config) -> Result<Vec<ReedlineEvent>, ShellError> {
+    let Value::List { vals, .. } = value else {
+        return Err(ShellError::UnsupportedConfigValue(
+            "list of events".into(),
+            value.into_abbreviated_string(config),
+            value.span()?,
+        ));
+    };
+
+    vals.iter()
+        .map(|value| match parse_event(value, config)? {
+            Some(event) => Ok(event),
+            None => Err(ShellError::UnsupportedConfigValue(
+                "list of events".into(),
+                "null".into(),
+                value.span()?,
+            )),
+        })
+        .collect()
+}
+
+fn send_event(
+    name: &str,
+    cols: &[String],
+    vals: &[Value],
+    config: &Config,
+    span: Span,
+) -> Result<ReedlineEvent, ShellError> {
+    Ok(match name {
+        "none" => ReedlineEvent::None,
+        "clearscreen" => ReedlineEvent::ClearScreen,
+        "clearscrollback" => ReedlineEvent::ClearScrollback,
+        "historyhintcomplete" => ReedlineEvent::HistoryHintComplete,
+        "historyhintwordcomplete" => ReedlineEvent::HistoryHintWordComplete,
+        "ctrld" => ReedlineEvent::CtrlD,
+        "ctrlc" => ReedlineEvent::CtrlC,
+        "enter" => ReedlineEvent::Enter,
+        "submit" => ReedlineEvent::Submit,
+        "submitornewline" => ReedlineEvent::SubmitOrNewline,
+        "esc" | "escape" => ReedlineEvent::Esc,
+        "up" => ReedlineEvent::Up,
+        "down" => ReedlineEvent::Down,
+        "right" => ReedlineEvent::Right,
+        "left" => ReedlineEvent::Left,
+        "searchhistory" => ReedlineEvent::SearchHistory,
+        "nexthistory" => ReedlineEvent::NextHistory,
+        "previoushistory" => ReedlineEvent::PreviousHistory,
+        "repaint" => ReedlineEvent::Repaint,
+        "openeditor" => ReedlineEvent::OpenEditor,
+        "menu" => {
+            let menu = extract_value("name", cols, vals, span)?;
+            ReedlineEvent::Menu(menu.into_string("", config))
+        }
+        "menudown" => ReedlineEvent::MenuDown,
+        "menuup" => ReedlineEvent::MenuUp,
+        "menuleft" => ReedlineEvent::MenuLeft,
+        "menuright" => ReedlineEvent::MenuRight,
+        "menunext" => ReedlineEvent::MenuNext,
+        "menuprevious" => ReedlineEvent::MenuPrevious,
+        "menupagenext" => ReedlineEvent::MenuPageNext,
+        "menupageprevious" => ReedlineEvent::MenuPagePrevious,
+        "executehostcommand" => {
+            let cmd = extract_value("cmd", cols, vals, span)?;
+            ReedlineEvent::ExecuteHostCommand(cmd.into_string("", config))
+        }
+        name => {
+            return Err(ShellError::UnsupportedConfigValue(
+                "reedline event".into(),
+                name.into(),
+                span,
+            ))
+        }
+    })
+}
+
+fn edit_command(
+    name: &str,
+    cols: &[String],
+    vals: &[Value],
+    config: &Config,
+    span: Span,
+) -> Result<EditCommand, ShellError> {
+    let value = || extract_value("value", cols, vals, span);
+    let char = || {
+        let value = value()?;
+        let span = value.span()?;
+        value
+            .into_string("", config)
+            .chars()
+            .next()
+            .ok_or_else(|| ShellError::MissingConfigValue("a character".into(), span))
+    };
+
+    Ok(match name {
+        "movetostart" => EditCommand::MoveToStart,
+        "movetolinestart" => EditCommand::MoveToLineStart,
+        "movetoend" => EditCommand::MoveToEnd,
+        "movetolineend" => EditCommand::MoveToLineEnd,
+        "moveleft" => EditCommand::MoveLeft,
+        "moveright" => EditCommand::MoveRight,
+        "movewordleft" => EditCommand::MoveWordLeft,
+        "movebigwordleft" => EditCommand::MoveBigWordLeft,
+        "movewordright" => EditCommand::MoveWordRight,
+        "movewordrightend" => EditCommand::MoveWordRightEnd,
+        "movebigwordrightend" => EditCommand::MoveBigWordRightEnd,
+        "movewordrightstart" => EditCommand::MoveWordRightStart,
+        "movebigwordrightstart" => EditCommand::MoveBigWordRightStart,
+        "movetoposition" => EditCommand::MoveToPosition(value()?.as_int()? as usize),
+        "insertchar" => EditCommand::InsertChar(char()?),
+        "insertstring" => EditCommand::InsertString(value()?.into_string("", config)),
+        "insertnewline" => EditCommand::InsertNewline,
+        "backspace" => EditCommand::Backspace,
+        "delete" => EditCommand::Delete,
+        "cutchar" => EditCommand::CutChar,
+        "backspaceword" => EditCommand::BackspaceWord,
+        "deleteword" => EditCommand::DeleteWord,
+        "clear" => EditCommand::Clear,
+        "cleartolineend" => EditCommand::ClearToLineEnd,
+        "cutcurrentline" => EditCommand::CutCurrentLine,
+        "cutfromstart" => EditCommand::CutFromStart,
+        "cutfromlinestart" => EditCommand::CutFromLineStart,
+        "cuttoend" => EditCommand::CutToEnd,
+        "cuttolineend" => EditCommand::CutToLineEnd,
+        "cutwordleft" => EditCommand::CutWordLeft,
+        "cutbigwordleft" => EditCommand::CutBigWordLeft,
+        "cutwordright" => EditCommand::CutWordRight,
+        "cutbigwordright" => EditCommand::CutBigWordRight,
+        "cutwordrighttonext" => EditCommand::CutWordRightToNext,
+        "cutbigwordrighttonext" => EditCommand::CutBigWordRightToNext,
+        "pastecutbufferbefore" => EditCommand::PasteCutBufferBefore,
+        "pastecutbufferafter" => EditCommand::PasteCutBufferAfter,
+        "uppercaseword" => EditCommand::UppercaseWord,
+        "lowercaseword" => EditCommand::LowercaseWord,
+        "capitalizechar" => EditCommand::CapitalizeChar,
+        "swapwords" => EditCommand::SwapWords,
+        "swapgraphemes" => EditCommand::SwapGraphemes,
+        "undo" => EditCommand::Undo,
+        "redo" => EditCommand::Redo,
+        "cutrightuntil" => EditCommand::CutRightUntil(char()?),
+        "cutrightbefore" => EditCommand::CutRightBefore(char()?),
+        "moverightuntil" => EditCommand::MoveRightUntil(char()?),
+        "moverightbefore" => EditCommand::MoveRightBefore(char()?),
+        "cutleftuntil" => EditCommand::CutLeftUntil(char()?),
+        "cutleftbefore" => EditCommand::CutLeftBefore(char()?),
+        "moveleftuntil" => EditCommand::MoveLeftUntil(char()?),
+        "moveleftbefore" => EditCommand::MoveLeftBefore(char()?),
+        "complete" => EditCommand::Complete,
+        name => {
+            return Err(ShellError::UnsupportedConfigValue(
+                "reedline edit command".into(),
+                name.into(),
+                span,
+            ))
+        }
+    })
+}
@@ -0,0 +1,88 @@
+//! The Nushell engine, set up to be embedded in a Rust application.
+//!
+//! [`create_engine_state`] gives an engine with the default commands, [`create_stack`] the
+//! variables and environment of one session, and [`eval_source`] runs nu code in them. The
+//! `nu_app` binary is built from the same functions.
+//!
+//! ```no_run
+//! use nu_app::{create_engine_state, create_stack, eval_source, nu_protocol::PipelineData};
+//!
+//! let mut engine_state = create_engine_state();
+//! let mut stack = create_stack();
+//!
+//! // prints the number of files in the current directory
+//! eval_source(&mut engine_state, &mut stack, b"ls | length", "app", PipelineData::Empty, true);
+//! ```
+//!
+//! [`eval_source_to_value`] hands the output back as a `Value` instead of printing it,
+//! [`eval_source_into`] as Rust data that implements `serde::Deserialize`, and
+//! [`eval_source_for_each`] one value at a time, as the pipeline has them. To have the printed
+//! output written somewhere else, see [`output::EvalOutput`].
+//!
+//! Values from the application reach the scripts as variables, see [`set_variable`], or as
+//! environment variables, see [`set_env_var`]. What the scripts leave behind is read back with
+//! [`get_variable`] and [`get_env_var`].
+//!
+//! Errors are handed back as an [`EvalError`], or printed to stderr the way nu prints them, or
+//! as JSON for tools that read them, see [`set_error_format`].
+//!
+//! The nu crates are re-exported, so the types the functions take match the engine's version.
+
+pub mod ast;
+pub mod autoload;
+pub mod cancel;
+pub mod cli;
+pub mod color_scheme;
+pub mod commands;
+pub mod completions;
+pub mod console_encoding;
+pub mod crash_report;
+pub mod create_default_context;
+pub mod daemon;
+pub mod deprecation;
+pub mod errors;
+pub mod helpers;
+pub mod highlight;
+pub mod history;
+pub mod hooks;
+pub mod host_values;
+pub mod ide;
+pub mod keybindings;
+pub mod lsp;
+pub mod output;
+pub mod pager;
+#[cfg(feature = "plugin")]
+pub mod plugins;
+pub mod reload;
+pub mod repl;
+pub mod resize;
+#[cfg(feature = "serve")]
+pub mod serve;
+pub mod session;
+pub mod source_map;
+pub mod streams;
+pub mod table_style;
+pub mod test_support;
+pub mod theme;
+pub mod timezone;
+pub mod units;
+pub mod values;
+
+pub use create_default_context::{
+    add_experimental_context, create_default_context, create_sandboxed_context,
+};
+pub use errors::{set_error_format, ErrorFormat, ErrorReporter, EvalError};
+pub use helpers::{
+    add_commands, add_process_env, check_source, convert_env_vars, create_engine_state,
+    create_list_input, create_ndjson_input, create_stack, create_stdin_input, eval_source,
+    eval_source_for_each, eval_source_into, eval_source_result, eval_source_to_pipeline,
+    eval_source_to_value, eval_source_with_reporter, eval_source_with_timeout, format_error,
+    get_env_var, get_variable, install_ctrlc_handler, print_pipeline_data, register_fn,
+    report_error, report_error_new, set_env_var, set_lib_dirs, set_plugin_dirs, set_use_colors,
+    set_variable, supports_colors, variables, ParseDiagnostic, StdinFormat,
+};
+
+pub use miette;
+pub use nu_engine;
+pub use nu_parser;
+pub use nu_protocol;
@@ -0,0 +1,328 @@
+use std::{
+    collections::HashMap,
+    io::{self, BufRead, Read, Write},
+};
+
+use nu_protocol::{engine::EngineState, Span};
+use serde_json::{json, Value as Json};
+
+use crate::{
+    check_source,
+    completions::complete,
+    create_stack,
+    ide::{definition, hover},
+};
+
+// JSON-RPC's code for a message that isn't valid JSON
+const PARSE_ERROR: i64 = -32700;
+// JSON-RPC's code for a request the server doesn't know
+const METHOD_NOT_FOUND: i64 = -32601;
+// the largest message the server reads, 64 MiB
+const MAX_MESSAGE_LEN: usize = 64 * 1024 * 1024;
+
+/// Speak the Language Server Protocol over stdin and stdout, like the `--lsp` flag of the binary
+/// does, until the client sends `exit`. See [`serve_lsp`].
+pub fn run_lsp(engine_state: &EngineState) -> io::Result<()> {
+    serve_lsp(engine_state, io::stdin().lock(), io::stdout().lock())
+}
+
+/// Answer the Language Server Protocol messages of an editor read from `input` on `output`,
+/// until the client sends `exit` or closes `input`. A message that can't be read is answered
+/// with a parse error, and the server goes on with the next one.
+///
+/// The documents the editor opens are parsed with the commands of `engine_state`, without
+/// running them. The server publishes their diagnostics whenever they change, and answers
+/// hovers with [`hover`], completions with [`complete`] and goto-definition with
+/// [`definition`].
+///
+/// ```
+/// use nu_app::{create_engine_state, lsp::serve_lsp};
+///
+/// fn message(json: &str) -> String {
+///     format!("Content-Length: {}\r\n\r\n{json}", json.len())
+/// }
+///
+/// let input = [
+///     message(r#"{"jsonrpc":"2.0","id":1,"method":"initialize","params":{}}"#),
+///     message(r#"{"jsonrpc":"2.0","method":"textDocument/didOpen","params":{"textDocument":{"uri":"file:///a.nu","languageId":"nu","version":1,"text":"let x: int = 'one'"}}}"#),
+///     message(r#"{"jsonrpc":"2.0","method":"exit"}"#),
+/// ]
+/// .concat();
+///
+/// let engine_state = create_engine_state();
+/// let mut output = vec![];
+/// serve_lsp(&engine_state, input.as_bytes(), &mut output).unwrap();
+///
+/// let output = String::from_utf8(output).unwrap();
+/// assert!(output.contains(r#""hoverProvider":true"#));
+/// assert!(output.contains(r#""method":"textDocument/publishDiagnostics""#));
+/// ```
+pub fn serve_lsp(
+    engine_state: &EngineState,
+    mut input: impl BufRead,
+    output: impl Write,
+) -> io::Result<()> {
+    let mut server = Server {
+        engine_state,
+        documents: HashMap::new(),
+        output,
+    };
+
+    while let Some(message) = read_message(&mut input)? {
+        match message {
+            Ok(message) => {
+                if !server.handle(message)? {
+                    break;
+                }
+            }
+            Err(error) => server.send(json!({
+                "jsonrpc": "2.0",
+                "id": null,
+                "error": { "code": PARSE_ERROR, "message": error },
+            }))?,
+        }
+    }
+
+    Ok(())
+}
+
+struct Server<'a, W> {
+    engine_state: &'a EngineState,
+    // the text of each open document by its uri
+    documents: HashMap<String, String>,
+    output: W,
+}
+
+impl<W: Write> Server<'_, W> {
+    // false once the client asks the server to exit
+    fn handle(&mut self, message: Json) -> io::Result<bool> {
+        // a response to a request of the server, which doesn't send any
+        let Some(method) = message["method"].as_str() else {
+            return Ok(true);
+        };
+        let id = message.get("id").cloned();
+        let params = &message["params"];
+        let uri = params["textDocument"]["uri"].as_str().unwrap_or_default();
+
+        let result = match method {
+            "initialize" => json!({
+                "capabilities": {
+                    // the whole text comes with each change
+                    "textDocumentSync": 1,
+                    "hoverProvider": true,
+                    "completionProvider": {},
+                    "definitionProvider": true,
+                },
+                "serverInfo": { "name": "nu_app", "version": env!("CARGO_PKG_VERSION") },
+            }),
+            "shutdown" => Json::Null,
+            "exit" => return Ok(false),
+            "textDocument/didOpen" => {
+                let text = params["textDocument"]["text"].as_str().unwrap_or_default();
+                self.documents.insert(uri.into(), text.into());
+                self.publish_diagnostics(uri)?;
+                return Ok(true);
+            }
+            "textDocument/didChange" => {
+                let changes = params["contentChanges"].as_array();
+                if let Some(text) = changes.and_then(|changes| changes.last()) {
+                    let text = text["text"].as_str().unwrap_or_default();
+                    self.documents.insert(uri.into(), text.into());
+                }
+                self.publish_diagnostics(uri)?;
+                return Ok(true);
+            }
+            "textDocument/didClose" => {
+                self.documents.remove(uri);
+                self.publish_diagnostics(uri)?;
+                return Ok(true);
+            }
+            "textDocument/hover" => self.hover(uri, &params["position"]),
+            "textDocument/completion" => self.completion(uri, &params["position"]),
+            "textDocument/definition" => self.definition(uri, &params["position"]),
+            _ => {
+                // notifications the server doesn't know are left alone
+                if let Some(id) = id {
+                    self.send(json!({
+                        "jsonrpc": "2.0",
+                        "id": id,
+                        "error": {
+                            "code": METHOD_NOT_FOUND,
+                            "message": format!("nu_app doesn't know '{method}'"),
+                        },
+                    }))?;
+                }
+                return Ok(true);
+            }
+        };
+
+        if let Some(id) = id {
+            self.send(json!({ "jsonrpc": "2.0", "id": id, "result": result }))?;
+        }
+        Ok(true)
+    }
+
+    fn publish_diagnostics(&mut self, uri: &str) -> io::Result<()> {
+        let diagnostics: Vec<Json> = match self.documents.get(uri) {
+            Some(text) => check_source(self.engine_state, text.as_bytes(), uri)
+                .into_iter()
+                .map(|diagnostic| {
+                    let message = match diagnostic.label {
+                        Some(label) => format!("{}\n{label}", diagnostic.message),
+                        None => diagnostic.message,
+                    };
+                    let severity = match diagnostic.severity {
+                        miette::Severity::Error => 1,
+                        miette::Severity::Warning => 2,
+                        miette::Severity::Advice => 3,
+                    };
+
+                    json!({
+                        "range": range(text, diagnostic.span),
+                        "severity": severity,
+                        "source": "nu_app",
+                        "message": message,
+                    })
+                })
+                .collect(),
+            // a closed document keeps no diagnostics
+            None => vec![],
+        };
+
+        self.send(json!({
+            "jsonrpc": "2.0",
+            "method": "textDocument/publishDiagnostics",
+            "params": { "uri": uri, "diagnostics": diagnostics },
+        }))
+    }
+
+    fn hover(&self, uri: &str, position: &Json) -> Json {
+        let Some(text) = self.documents.get(uri) else {
+            return Json::Null;
+        };
+
+        let offset = offset(text, position);
+        match hover(self.engine_state, text.as_bytes(), uri, offset) {
+            Some(hover) => json!({
+                "contents": { "kind": "markdown", "value": hover.text },
+                "range": range(text, hover.span),
+            }),
+            None => Json::Null,
+        }
+    }
+
+    fn completion(&self, uri: &str, position: &Json) -> Json {
+        let Some(text) = self.documents.get(uri) else {
+            return Json::Null;
+        };
+
+        // paths are completed in the directory the server was started in
+        let stack = create_stack();
+        complete(self.engine_state, &stack, text, offset(text, position))
+            .into_iter()
+            .map(|suggestion| {
+                let span = Span::new(suggestion.span.start, suggestion.span.end);
+                json!({
+                    "label": suggestion.value,
+                    "detail": suggestion.description,
+                    "textEdit": { "range": range(text, span), "newText": suggestion.value },
+                })
+            })
+            .collect()
+    }
+
+    fn definition(&self, uri: &str, position: &Json) -> Json {
+        let Some(text) = self.documents.get(uri) else {
+            return Json::Null;
+        };
+
+        let offset = offset(text, position);
+        match definition(self.engine_state, text.as_bytes(), uri, offset) {
+            Some(span) => json!({ "uri": uri, "range": range(text, span) }),
+            None => Json::Null,
+        }
+    }
+
+    fn send(&mut self, message: Json) -> io::Result<()> {
+        let body = message.to_string();
+        write!(self.output, "Content-Length: {}\r\n\r\n{body}", body.len())?;
+        self.output.flush()
+    }
+}
+
+// the JSON of the next message or why it can't be read, `None` once the client closed the input
+fn read_message(input: &mut impl BufRead) -> io::Result<Option<Result<Json, String>>> {
+    let mut length = None;
+    loop {
+        let mut header = String::new();
+        if input.read_line(&mut header)? == 0 {
+            return Ok(None);
+        }
+
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                length = value.trim().parse::<usize>().ok();
+            }
+        }
+    }
+
+    let Some(length) = length else {
+        return Ok(Some(Err("a message without a Content-Length".into())));
+    };
+    if length > MAX_MESSAGE_LEN {
+        // skipped, so the next message is read from where it starts
+        io::copy(&mut input.take(length as u64), &mut io::sink())?;
+        let error = format!("a message larger than {MAX_MESSAGE_LEN} bytes");
+        return Ok(Some(Err(error)));
+    }
+    let mut body = vec![0; length];
+    input.read_exact(&mut body)?;
+
+    Ok(Some(
+        serde_json::from_slice(&body).map_err(|err| format!("the message isn't JSON: {err}")),
+    ))
+}
+
+// the byte offset of an LSP position, whose character counts UTF-16 code units
+fn offset(text: &str, position: &Json) -> usize {
+    let line = position["line"].as_u64().unwrap_or_default() as usize;
+    let character = position["character"].as_u64().unwrap_or_default() as usize;
+
+    let line_start: usize = text
+        .split_inclusive('\n')
+        .take(line)
+        .map(|line| line.len())
+        .sum();
+    let mut units = 0;
+    for (idx, c) in text[line_start..].char_indices() {
+        if units >= character || c == '\n' {
+            return line_start + idx;
+        }
+        units += c.len_utf16();
+    }
+
+    text.len()
+}
+
+// the LSP position of a byte offset, or of the character it's in the middle of
+fn position(text: &str, offset: usize) -> Json {
+    let mut offset = offset.min(text.len());
+    while !text.is_char_boundary(offset) {
+        offset -= 1;
+    }
+    let before = &text[..offset];
+    let line_start = before.rfind('\n').map_or(0, |idx| idx + 1);
+
+    json!({
+        "line": before.matches('\n').count(),
+        "character": before[line_start..].encode_utf16().count(),
+    })
+}
+
+fn range(text: &str, span: Span) -> Json {
+    json!({ "start": position(text, span.start), "end": position(text, span.end) })
+}
@@ -1,12 +1,328 @@
-mod create_default_context;
-mod helpers;
+use std::{
+    io::IsTerminal,
+    path::{Path, PathBuf},
+    time::Instant,
+};
 
-use helpers::{create_engine_state, create_stack, create_stdin_input, eval_source};
+use nu_app::{
+    add_process_env,
+    autoload::{source_autoload_dir, user_autoload_dir, vendor_autoload_dirs},
+    cli::{parse_args, USAGE},
+    convert_env_vars,
+    crash_report::{default_crash_report_dir, enable_crash_reports, set_crash_report_history},
+    create_default_context::ContextBuilder,
+    create_stack, create_stdin_input, eval_source,
+    helpers::{default_config_dir, eval_config_file, install_ctrlc_handler},
+    ide::ide_json,
+    lsp::run_lsp,
+    repl::evaluate_repl,
+    report_error_new, set_error_format, set_use_colors, supports_colors,
+    timezone::set_timezone,
+    EvalError,
+};
+use nu_parser::{escape_for_script_arg, parse};
+use nu_protocol::{
+    engine::{EngineState, Stack, StateWorkingSet},
+    PipelineData, ShellError,
+};
 
 fn main() {
-    let mut engine_state = create_engine_state();
+    let started = Instant::now();
+    let cli_args = match parse_args(std::env::args().skip(1)) {
+        Ok(cli_args) => cli_args,
+        Err(msg) => {
+            eprintln!("Error: {msg}\n\n{USAGE}");
+            std::process::exit(1);
+        }
+    };
+    if cli_args.help {
+        println!("{USAGE}");
+        return;
+    }
+    set_error_format(cli_args.error_format);
+    // `TZ` is set before the plugins and the ctrl-c handler start threads that read it
+    if let Some(timezone) = &cli_args.timezone {
+        if let Err(err) = set_timezone(timezone) {
+            report_error_new(&EngineState::new(), &err);
+            std::process::exit(1);
+        }
+    }
+    if let Some(dir) = default_crash_report_dir() {
+        enable_crash_reports(dir);
+    }
+    set_crash_report_history(!cli_args.no_crash_history);
+
+    // the sources `serve` is sent get the commands that can't touch the machine, unless asked
+    let sandboxed = cli_args.serve.is_some() && !cli_args.unsandboxed;
+    let mut context = match sandboxed {
+        true => ContextBuilder::sandboxed(),
+        false => ContextBuilder::with_defaults(),
+    };
+    context = context.removed_commands(cli_args.removed_commands);
+    if cli_args.experimental {
+        context = context.with_experimental();
+    }
+    #[cfg(feature = "plugin")]
+    {
+        let plugin_file = match &cli_args.plugin_file {
+            Some(path) => Some(PathBuf::from(path)),
+            None if cli_args.no_config_file || sandboxed => None,
+            None => nu_app::plugins::default_plugin_file(),
+        };
+        if let Some(path) = plugin_file {
+            context = context.plugin_file(path);
+        }
+    }
+    let mut engine_state = match context.build() {
+        Ok(engine_state) => engine_state,
+        Err(err) => {
+            report_error_new(&EngineState::new(), &err);
+            std::process::exit(1);
+        }
+    };
+    // ctrl-c stops the servers, each source they evaluate gets a flag of its own
+    if cli_args.daemon.is_none() && cli_args.serve.is_none() {
+        if let Err(err) = install_ctrlc_handler(&mut engine_state) {
+            report_error_new(&engine_state, &err);
+        }
+    }
     let mut stack = create_stack();
-    let input = create_stdin_input();
+    // the parser resolves the paths of `register` and `use` against the engine's PWD
+    if let Some(pwd) = stack.get_env_var(&engine_state, "PWD") {
+        engine_state.add_env_var("PWD".into(), pwd);
+    }
+    add_process_env(&mut engine_state);
+
+    // servers answer their clients instead, and only read the config files they're given
+    let serving = cli_args.lsp || cli_args.daemon.is_some() || cli_args.serve.is_some();
+    // without anything to run, a terminal gets the REPL
+    let nothing_to_run = cli_args.script.is_none() && cli_args.commands.is_none() && !serving;
+    // the REPL reads stdin, so it can't be the input of the script too
+    let interactive = cli_args.interactive
+        || (nothing_to_run && (!cli_args.execute.is_empty() || std::io::stdin().is_terminal()));
+    engine_state.is_interactive = interactive;
+
+    // like nu, scripts and commands only read the config files they're given
+    let default_config = |file: &str| {
+        (interactive && !cli_args.no_config_file)
+            .then(default_config_dir)
+            .flatten()
+            .map(|dir| dir.join(file))
+            .filter(|path| path.is_file())
+    };
+    let env_file = cli_args.env_file.as_ref().map(PathBuf::from);
+    let config_file = cli_args.config_file.as_ref().map(PathBuf::from);
+    let config_files = [
+        (env_file.or_else(|| default_config("env.nu")), "env-path"),
+        (
+            config_file.or_else(|| default_config("config.nu")),
+            "config-path",
+        ),
+    ];
+    for (path, key) in config_files {
+        if let Some(path) = path {
+            if let Err(err) = eval_config_file(&mut engine_state, &mut stack, &path, key) {
+                report_error_new(&engine_state, &err);
+                if !interactive {
+                    std::process::exit(1);
+                }
+            }
+        }
+
+        // like nu, `env.nu` sets up the conversions before `config.nu` sees the variables
+        if key == "env-path" {
+            if let Err(err) = convert_env_vars(&mut engine_state, &stack) {
+                report_error_new(&engine_state, &err);
+            }
+        }
+    }
+
+    // startup scripts customize the REPL, a script runs the same wherever it's started
+    if interactive {
+        // the user's own scripts come last, so they can override what packages set up
+        let dirs = vendor_autoload_dirs()
+            .into_iter()
+            .chain(user_autoload_dir());
+        for dir in dirs {
+            source_autoload_dir(&mut engine_state, &mut stack, &dir);
+        }
+    }
+    // after the config files, which turn the colors on
+    if cli_args.no_color || !supports_colors() {
+        set_use_colors(&mut engine_state, &mut stack, false);
+    }
+    // piped stdin is the input unless it's turned off, `--stdin` takes a terminal as well
+    let stdin_input = |engine_state: &EngineState| match cli_args.stdin {
+        Some(false) => PipelineData::Empty,
+        stdin => create_stdin_input(
+            cli_args.stdin_format,
+            stdin == Some(true),
+            engine_state.ctrlc.clone(),
+        ),
+    };
+    // `$nu.startup-time`, how long it took until the first thing the user asked for runs
+    engine_state.set_startup_time(started.elapsed().as_nanos() as i64);
+
+    if cli_args.lsp {
+        if let Err(err) = run_lsp(&engine_state) {
+            report_error_new(&engine_state, &ShellError::IOError(err.to_string()));
+            std::process::exit(1);
+        }
+        return;
+    }
+    if let Some(socket) = &cli_args.daemon {
+        #[cfg(unix)]
+        let result = {
+            let timeout = cli_args
+                .timeout
+                .unwrap_or(nu_app::daemon::DEFAULT_EVAL_TIMEOUT);
+            nu_app::daemon::serve_daemon(engine_state.clone(), socket, timeout)
+        };
+        #[cfg(not(unix))]
+        let result = Err::<(), _>(std::io::Error::new(
+            std::io::ErrorKind::Unsupported,
+            format!("can't serve {socket}, --daemon needs Unix sockets"),
+        ));
+
+        if let Err(err) = result {
+            report_error_new(&engine_state, &ShellError::IOError(err.to_string()));
+            std::process::exit(1);
+        }
+        return;
+    }
+    if let Some(addr) = &cli_args.serve {
+        #[cfg(feature = "serve")]
+        let result = {
+            let mut options = nu_app::serve::ServeOptions::new();
+            if let Some(token) = &cli_args.token {
+                options = options.token(token);
+            }
+            if let Some(timeout) = cli_args.timeout {
+                options = options.timeout(timeout);
+            }
+            nu_app::serve::serve(engine_state.clone(), addr.as_str(), options)
+        };
+        #[cfg(not(feature = "serve"))]
+        let result = Err::<(), _>(std::io::Error::new(
+            std::io::ErrorKind::Unsupported,
+            format!("can't serve {addr}, nu_app is built without the serve feature"),
+        ));
+
+        if let Err(err) = result {
+            report_error_new(&engine_state, &ShellError::IOError(err.to_string()));
+            std::process::exit(1);
+        }
+        return;
+    }
+    // an editor asks about the script, which isn't run
+    if let (Some(request), Some(script)) = (cli_args.ide, &cli_args.script) {
+        let source = read_source(&engine_state, script);
+        print!("{}", ide_json(&engine_state, &source, script, request));
+        return;
+    }
+
+    // each sees what the ones before it set up, like the env vars and aliases of a wrapper
+    for execute in &cli_args.execute {
+        // a path to a file runs what's in it, anything else runs as commands
+        let (source, fname) = match Path::new(execute).is_file() {
+            true => (read_source(&engine_state, execute), execute.as_str()),
+            false => (execute.as_bytes().to_vec(), "execute"),
+        };
+
+        eval_source(
+            &mut engine_state,
+            &mut stack,
+            &source,
+            fname,
+            PipelineData::Empty,
+            false,
+        );
+    }
+
+    if let Some(commands) = &cli_args.commands {
+        let input = match interactive {
+            true => PipelineData::Empty,
+            false => stdin_input(&engine_state),
+        };
+
+        eval_source(
+            &mut engine_state,
+            &mut stack,
+            commands.as_bytes(),
+            "<commandline>",
+            input,
+            true,
+        );
+    }
+
+    if let Some(script) = &cli_args.script {
+        let input = match interactive {
+            true => PipelineData::Empty,
+            false => stdin_input(&engine_state),
+        };
+        let mut source = read_source(&engine_state, script);
+
+        // blank out a shebang line, so it isn't taken for the doc comment of a `def` below it
+        if source.starts_with(b"#!") {
+            let end = source
+                .iter()
+                .position(|b| *b == b'\n')
+                .unwrap_or(source.len());
+            source[..end].fill(b' ');
+        }
+
+        // a script with a `def main` hands its input and arguments to that instead of its body
+        if defines_main(&engine_state, &source, script) {
+            let ran = eval_source(
+                &mut engine_state,
+                &mut stack,
+                &source,
+                script,
+                PipelineData::Empty,
+                true,
+            );
+
+            if ran {
+                let mut call = String::from("main");
+                for arg in &cli_args.script_args {
+                    call.push(' ');
+                    call.push_str(&escape_for_script_arg(arg));
+                }
+
+                eval_source(
+                    &mut engine_state,
+                    &mut stack,
+                    call.as_bytes(),
+                    "<commandline>",
+                    input,
+                    true,
+                );
+            }
+        } else {
+            eval_source(&mut engine_state, &mut stack, &source, script, input, true);
+        }
+    }
+
+    if interactive {
+        let result = evaluate_repl(&mut engine_state, &mut stack);
+
+        // don't leave a plugin behind that kept running after its call was interrupted
+        #[cfg(feature = "plugin")]
+        nu_app::plugins::stop_plugins();
+
+        if let Err(err) = result {
+            eprintln!("Error: {err}");
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    // a script fails like the last thing it ran did
+    if cli_args.script.is_some() || cli_args.commands.is_some() {
+        std::process::exit(last_exit_code(&engine_state, &stack));
+    }
+
+    let input = stdin_input(&engine_state);
 
     //For fancier source you may want to use heavy duty quoting like this:
     //let source = br#"""ls | length"""#;
@@ -21,4 +337,29 @@ fn main() {
         input,
         true,
     );
+    std::process::exit(last_exit_code(&engine_state, &stack));
+}
+
+fn defines_main(engine_state: &EngineState, source: &[u8], fname: &str) -> bool {
+    let mut working_set = StateWorkingSet::new(engine_state);
+    parse(&mut working_set, Some(fname), source, false);
+
+    working_set.find_decl(b"main").is_some()
+}
+
+fn last_exit_code(engine_state: &EngineState, stack: &Stack) -> i32 {
+    stack
+        .get_env_var(engine_state, "LAST_EXIT_CODE")
+        .and_then(|exit_code| exit_code.as_i64().ok())
+        .unwrap_or(0) as i32
+}
+
+fn read_source(engine_state: &EngineState, path: &str) -> Vec<u8> {
+    match std::fs::read(path) {
+        Ok(contents) => contents,
+        Err(err) => {
+            report_error_new(engine_state, &EvalError::io(path, err));
+            std::process::exit(1);
+        }
+    }
 }
@@ -0,0 +1,234 @@
+use std::io::{self, Write};
+
+use nu_engine::env::get_config;
+use nu_protocol::{
+    ast::Call,
+    engine::{EngineState, Stack, StateWorkingSet},
+    PipelineData, RawStream, ShellError, Span, Value,
+};
+
+use crate::helpers::{eval_source_redirected, format_error, set_last_exit_code};
+
+/// Where the output of an evaluation is written, the process' stdout and stderr unless the
+/// application wants it somewhere else, e.g. in a buffer or a widget.
+///
+/// ```
+/// use nu_app::{create_engine_state, create_stack, nu_protocol::PipelineData};
+/// use nu_app::output::{eval_source_with_output, EvalOutput};
+///
+/// let mut engine_state = create_engine_state();
+/// let mut stack = create_stack();
+/// let (mut out, mut err) = (vec![], vec![]);
+///
+/// let mut output = EvalOutput::new(&mut out, &mut err);
+/// eval_source_with_output(
+///     &mut engine_state,
+///     &mut stack,
+///     b"[1 2 3] | math sum",
+///     "app",
+///     PipelineData::Empty,
+///     true,
+///     &mut output,
+/// );
+/// drop(output);
+///
+/// assert_eq!(String::from_utf8(out).unwrap(), "6");
+/// ```
+pub struct EvalOutput<'a> {
+    /// Gets the rendered values and the output of externals.
+    pub stdout: Box<dyn Write + Send + 'a>,
+    /// Gets the errors and what externals write to their stderr.
+    pub stderr: Box<dyn Write + Send + 'a>,
+}
+
+impl<'a> EvalOutput<'a> {
+    pub fn new(stdout: impl Write + Send + 'a, stderr: impl Write + Send + 'a) -> Self {
+        EvalOutput {
+            stdout: Box::new(stdout),
+            stderr: Box::new(stderr),
+        }
+    }
+
+    /// The process' own stdout and stderr, where [`eval_source`](crate::eval_source) writes.
+    pub fn stdio() -> EvalOutput<'static> {
+        EvalOutput::new(io::stdout(), io::stderr())
+    }
+}
+
+impl Default for EvalOutput<'static> {
+    fn default() -> Self {
+        EvalOutput::stdio()
+    }
+}
+
+/// Parse and evaluate `source` like [`eval_source`](crate::eval_source), writing the output and
+/// the errors to `output` instead of the process' stdout and stderr.
+///
+/// Externals the source ends with write into `output` as well, the output of externals that
+/// run earlier in the source is dropped. Returns whether the source ran without errors.
+pub fn eval_source_with_output(
+    engine_state: &mut EngineState,
+    stack: &mut Stack,
+    source: &[u8],
+    fname: &str,
+    input: PipelineData,
+    allow_return: bool,
+    output: &mut EvalOutput,
+) -> bool {
+    let result = eval_source_redirected(
+        engine_state,
+        stack,
+        source,
+        fname,
+        input,
+        allow_return,
+        true,
+        true,
+    );
+
+    match result {
+        Ok(pipeline_data) => write_pipeline_data(engine_state, stack, pipeline_data, true, output),
+        Err(err) => {
+            write_error(engine_state, &err, output);
+            false
+        }
+    }
+}
+
+/// Write the output of an evaluation to `output` like
+/// [`print_pipeline_data`](crate::print_pipeline_data) prints it, values as tables and externals
+/// as they are, and record its exit code.
+pub fn write_pipeline_data(
+    engine_state: &EngineState,
+    stack: &mut Stack,
+    pipeline_data: PipelineData,
+    no_newline: bool,
+    output: &mut EvalOutput,
+) -> bool {
+    // externals write in the console's codepage, which can't be written as UTF-8 as-is
+    #[cfg(windows)]
+    let pipeline_data = crate::console_encoding::decode_pipeline_data(
+        pipeline_data,
+        crate::console_encoding::ConsoleDecoder::for_console,
+    );
+
+    let result = match pipeline_data {
+        PipelineData::ExternalStream {
+            stdout,
+            stderr,
+            exit_code,
+            ..
+        } => write_streams(stdout, stderr, output).map(|()| {
+            exit_code
+                .and_then(|exit_code| exit_code.into_iter().last())
+                .and_then(|exit_code| exit_code.as_i64().ok())
+                .unwrap_or(0)
+        }),
+        pipeline_data => write_values(engine_state, stack, pipeline_data, no_newline, output),
+    };
+
+    match result {
+        Ok(exit_code) => {
+            set_last_exit_code(stack, exit_code);
+            true
+        }
+        Err(err) => {
+            set_last_exit_code(stack, 1);
+            write_error(engine_state, &err, output);
+            false
+        }
+    }
+}
+
+fn write_values(
+    engine_state: &EngineState,
+    stack: &mut Stack,
+    pipeline_data: PipelineData,
+    no_newline: bool,
+    output: &mut EvalOutput,
+) -> Result<i64, ShellError> {
+    // rendered by the `table` command, unless it's been replaced by a custom one
+    let table = match engine_state.table_decl_id {
+        Some(decl_id) if engine_state.get_decl(decl_id).get_block_id().is_none() => {
+            let mut call = Call::new(Span::new(0, 0));
+            call.redirect_stdout = false;
+            engine_state
+                .get_decl(decl_id)
+                .run(engine_state, stack, &call, pipeline_data)?
+        }
+        _ => pipeline_data,
+    };
+
+    let config = get_config(engine_state, stack);
+    let mut exit_code = 0;
+    for item in table {
+        if let Value::Error { error } = item {
+            write_error(engine_state, &*error, output);
+            exit_code = 1;
+            continue;
+        }
+
+        let mut out = item.into_string(if no_newline { "" } else { "\n" }, &config);
+        if !no_newline {
+            out.push('\n');
+        }
+        write_all(&mut output.stdout, out.as_bytes())?;
+    }
+
+    Ok(exit_code)
+}
+
+// stderr is written on another thread, an external that fills the pipe of one while the other
+// is read from would never finish
+fn write_streams(
+    stdout: Option<RawStream>,
+    stderr: Option<RawStream>,
+    output: &mut EvalOutput,
+) -> Result<(), ShellError> {
+    let EvalOutput {
+        stdout: stdout_writer,
+        stderr: stderr_writer,
+    } = output;
+
+    std::thread::scope(|scope| {
+        let stderr = stderr.map(|stderr| scope.spawn(|| write_stream(stderr, stderr_writer)));
+        let stdout = match stdout {
+            Some(stdout) => write_stream(stdout, stdout_writer),
+            None => Ok(()),
+        };
+        let stderr = match stderr {
+            Some(stderr) => stderr.join().unwrap_or(Ok(())),
+            None => Ok(()),
+        };
+
+        stdout.and(stderr)
+    })
+}
+
+fn write_stream(stream: RawStream, writer: &mut (dyn Write + Send)) -> Result<(), ShellError> {
+    for chunk in stream {
+        match chunk? {
+            Value::String { val, .. } => write_all(writer, val.as_bytes())?,
+            Value::Binary { val, .. } => write_all(writer, &val)?,
+            _ => {}
+        }
+    }
+
+    Ok(())
+}
+
+fn write_all(writer: &mut (dyn Write + Send), bytes: &[u8]) -> Result<(), ShellError> {
+    writer
+        .write_all(bytes)
+        .and_then(|()| writer.flush())
+        .map_err(|err| ShellError::IOError(err.to_string()))
+}
+
+fn write_error(
+    engine_state: &EngineState,
+    error: &(dyn miette::Diagnostic + Send + Sync + 'static),
+    output: &mut EvalOutput,
+) {
+    let working_set = StateWorkingSet::new(engine_state);
+    let _ = writeln!(output.stderr, "{}", format_error(&working_set, error));
+}
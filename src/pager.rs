@@ -0,0 +1,230 @@
+use std::io::{self, BufRead, Write};
+
+use crossterm::{
+    cursor::{Hide, MoveTo, Show},
+    event::{Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
+    queue,
+    style::{Attribute, Print, SetAttribute},
+    terminal::{
+        self, Clear, ClearType, DisableLineWrap, EnableLineWrap, EnterAlternateScreen,
+        LeaveAlternateScreen,
+    },
+};
+
+/// Show `lines`, followed by whatever is left to read from `rest`, one screen at a time.
+///
+/// Lines are only read from `rest` as scrolling reaches them, so endless output can be paged
+/// too. Keys work like `less`: arrows, `j`/`k`, space and `b` scroll, `g`/`G` jump to either
+/// end, `/` searches, `n`/`N` repeat the search and `q` quits.
+pub fn page<R: BufRead>(lines: Vec<String>, rest: R) -> io::Result<()> {
+    let mut pager = Pager {
+        lines,
+        rest: Some(rest),
+        top: 0,
+        search: None,
+        message: None,
+    };
+
+    let mut stdout = io::stdout();
+    terminal::enable_raw_mode()?;
+    queue!(stdout, EnterAlternateScreen, DisableLineWrap, Hide)?;
+
+    let result = pager.run(&mut stdout);
+
+    queue!(stdout, Show, EnableLineWrap, LeaveAlternateScreen)?;
+    stdout.flush()?;
+    terminal::disable_raw_mode()?;
+
+    result
+}
+
+struct Pager<R> {
+    lines: Vec<String>,
+    rest: Option<R>,
+    top: usize,
+    search: Option<String>,
+    message: Option<String>,
+}
+
+impl<R: BufRead> Pager<R> {
+    fn run(&mut self, stdout: &mut impl Write) -> io::Result<()> {
+        loop {
+            let height = page_height();
+            self.draw(stdout, height)?;
+
+            let key = match crossterm::event::read()? {
+                Event::Key(key) if key.kind != KeyEventKind::Release => key,
+                _ => continue,
+            };
+            self.message = None;
+
+            match key.code {
+                KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+                KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    return Ok(())
+                }
+                KeyCode::Char('j') | KeyCode::Down | KeyCode::Enter => self.scroll_down(1, height),
+                KeyCode::Char('k') | KeyCode::Up => self.top = self.top.saturating_sub(1),
+                KeyCode::Char(' ') | KeyCode::Char('f') | KeyCode::PageDown => {
+                    self.scroll_down(height, height)
+                }
+                KeyCode::Char('b') | KeyCode::PageUp => self.top = self.top.saturating_sub(height),
+                KeyCode::Char('g') | KeyCode::Home => self.top = 0,
+                KeyCode::Char('G') | KeyCode::End => {
+                    self.read_until(usize::MAX);
+                    self.top = self.lines.len().saturating_sub(height);
+                }
+                KeyCode::Char('/') => {
+                    if let Some(pattern) = self.prompt(stdout, height)? {
+                        self.search = Some(pattern);
+                        self.find(true, height);
+                    }
+                }
+                KeyCode::Char('n') => self.find(true, height),
+                KeyCode::Char('N') => self.find(false, height),
+                _ => {}
+            }
+        }
+    }
+
+    fn draw(&mut self, stdout: &mut impl Write, height: usize) -> io::Result<()> {
+        self.read_until(self.top + height);
+
+        queue!(stdout, MoveTo(0, 0), Clear(ClearType::All))?;
+        for (row, line) in self.lines.iter().skip(self.top).take(height).enumerate() {
+            queue!(stdout, MoveTo(0, row as u16), Print(line))?;
+        }
+
+        let at_end = self.rest.is_none() && self.top + height >= self.lines.len();
+        let status = match &self.message {
+            Some(message) => message.as_str(),
+            None if at_end => "(END)",
+            None => ":",
+        };
+        queue!(
+            stdout,
+            MoveTo(0, height as u16),
+            SetAttribute(Attribute::Reverse),
+            Print(status),
+            SetAttribute(Attribute::Reset),
+        )?;
+
+        stdout.flush()
+    }
+
+    // read the search pattern on the status line, `None` if it was cancelled
+    fn prompt(&mut self, stdout: &mut impl Write, height: usize) -> io::Result<Option<String>> {
+        let mut pattern = String::new();
+        loop {
+            queue!(
+                stdout,
+                MoveTo(0, height as u16),
+                Clear(ClearType::CurrentLine),
+                Print(format!("/{pattern}")),
+                Show,
+            )?;
+            stdout.flush()?;
+
+            let key = match crossterm::event::read()? {
+                Event::Key(key) if key.kind != KeyEventKind::Release => key,
+                _ => continue,
+            };
+            match key {
+                KeyEvent {
+                    code: KeyCode::Enter,
+                    ..
+                } => break,
+                KeyEvent {
+                    code: KeyCode::Esc, ..
+                } => {
+                    queue!(stdout, Hide)?;
+                    return Ok(None);
+                }
+                KeyEvent {
+                    code: KeyCode::Backspace,
+                    ..
+                } => {
+                    pattern.pop();
+                }
+                KeyEvent {
+                    code: KeyCode::Char(c),
+                    ..
+                } => pattern.push(c),
+                _ => {}
+            }
+        }
+
+        queue!(stdout, Hide)?;
+        // an empty pattern repeats the last search, like it does in `less`
+        Ok(match pattern.is_empty() {
+            true => self.search.clone(),
+            false => Some(pattern),
+        })
+    }
+
+    fn find(&mut self, forward: bool, height: usize) {
+        let Some(pattern) = self.search.clone() else {
+            return;
+        };
+        let matches = |line: &String| nu_utils::strip_ansi_unlikely(line).contains(&pattern);
+
+        let found = if forward {
+            let mut row = self.top + 1;
+            loop {
+                self.read_until(row + 1);
+                match self.lines.get(row) {
+                    Some(line) if matches(line) => break Some(row),
+                    Some(_) => row += 1,
+                    None => break None,
+                }
+            }
+        } else {
+            (0..self.top).rev().find(|row| matches(&self.lines[*row]))
+        };
+
+        match found {
+            Some(row) => self.top = row,
+            None => self.message = Some(format!("Pattern not found: {pattern}")),
+        }
+        self.read_until(self.top + height);
+    }
+
+    fn scroll_down(&mut self, rows: usize, height: usize) {
+        self.read_until(self.top + rows + height);
+        let last_top = self.lines.len().saturating_sub(height);
+        self.top = (self.top + rows).min(last_top.max(self.top));
+    }
+
+    fn read_until(&mut self, len: usize) {
+        while self.lines.len() < len {
+            let Some(rest) = &mut self.rest else {
+                return;
+            };
+
+            match read_line(rest) {
+                Some(line) => self.lines.push(line),
+                None => self.rest = None,
+            }
+        }
+    }
+}
+
+/// Read a line without its line ending, or `None` at the end of the input.
+pub fn read_line(reader: &mut impl BufRead) -> Option<String> {
+    let mut line = vec![];
+    match reader.read_until(b'\n', &mut line) {
+        Ok(0) | Err(_) => None,
+        Ok(_) => {
+            while matches!(line.last(), Some(b'\n' | b'\r')) {
+                line.pop();
+            }
+            Some(String::from_utf8_lossy(&line).into_owned())
+        }
+    }
+}
+
+// the rows available for output, leaving the last one for the status line
+fn page_height() -> usize {
+    let rows = terminal::size().map(|(_, rows)| rows).unwrap_or(24);
+    (rows as usize).saturating_sub(1).max(1)
+}
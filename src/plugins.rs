@@ -0,0 +1,550 @@
+use std::{
+    cell::RefCell,
+    collections::BTreeMap,
+    io::Write,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex, Once,
+    },
+    time::Duration,
+};
+
+use nu_engine::env::env_to_strings;
+use nu_parser::escape_quote_string;
+use nu_plugin::{get_signature, PluginDeclaration};
+use nu_protocol::{
+    engine::{Command, EngineState, Stack, StateWorkingSet},
+    PipelineData, PluginExample, PluginSignature, ShellError,
+};
+use sysinfo::{Pid, PidExt, ProcessExt, System, SystemExt};
+
+use crate::{
+    errors::EvalError,
+    helpers::{create_stack, eval_source_result},
+};
+
+/// The nu version whose plugin protocol the engine speaks.
+///
+/// A plugin announces whether it talks json or msgpack when it starts, and gets its calls in that
+/// encoding, but the protocol has no version of its own. A plugin built against another version
+/// of nu-plugin has to be rebuilt with this one.
+pub const PLUGIN_PROTOCOL_VERSION: &str = "0.84";
+
+thread_local! {
+    // commands run with a shared engine, so `plugin add` and `plugin rm` leave their changes here
+    // for the evaluation to apply once it's done; an evaluation runs on one thread, so the ones
+    // on other threads don't see them
+    static PENDING: RefCell<Vec<Change>> = const { RefCell::new(vec![]) };
+}
+
+// the commands of the plugins removed from each registry, which `register` would otherwise
+// write back, as it writes every plugin the engine has seen
+static REMOVED: Mutex<BTreeMap<PathBuf, Vec<String>>> = Mutex::new(BTreeMap::new());
+
+enum Change {
+    Add(Vec<PluginDeclaration>),
+    Remove(Vec<String>),
+}
+
+// the executables of the plugins engines know about, to tell plugin processes from other children
+static PLUGIN_FILES: Mutex<Vec<PathBuf>> = Mutex::new(vec![]);
+// how many seconds a plugin process may run, 0 for as long as it likes
+static PLUGIN_TIMEOUT: AtomicU64 = AtomicU64::new(0);
+static REAPER: Once = Once::new();
+
+/// A plugin process that's answering a call.
+#[derive(Debug, Clone)]
+pub struct PluginProcess {
+    pub pid: u32,
+    /// The plugin's executable.
+    pub filename: PathBuf,
+    /// How long the call has been running, to the second.
+    pub running: Duration,
+}
+
+/// The plugin registry the app reads and writes unless it's given another one,
+/// `<config dir>/nu_app/plugin.nu`.
+pub fn default_plugin_file() -> Option<PathBuf> {
+    nu_path::config_dir().map(|dir| dir.join("nu_app").join("plugin.nu"))
+}
+
+/// Add the plugins of the registry at `path` to the engine, and keep `register` and `plugin add`
+/// writing to it.
+///
+/// The registry has a `register` call with the signatures for each command, so the plugins
+/// aren't spawned until their commands run. A registry that isn't there yet starts out empty.
+pub fn load_plugin_file(engine_state: &mut EngineState, path: &Path) -> Result<(), EvalError> {
+    engine_state.plugin_signatures = Some(path.to_path_buf());
+    if !path.exists() {
+        return Ok(());
+    }
+
+    let contents = std::fs::read(path).map_err(|err| EvalError::io(path, err))?;
+
+    // `register` does its work while parsing, what's left to run does nothing
+    eval_source_result(
+        engine_state,
+        &mut create_stack(),
+        &contents,
+        &path.to_string_lossy(),
+        PipelineData::Empty,
+        false,
+    )
+    .map(|_| ())
+}
+
+/// Spawn the plugin at `filename` for its signatures, add its commands to the engine and to the
+/// registry file, and return their names.
+///
+/// This is what `plugin add` does, for hosts that manage plugins from code. See
+/// [`PLUGIN_PROTOCOL_VERSION`] for the plugins that can be added.
+pub fn add_plugin(
+    engine_state: &mut EngineState,
+    stack: &Stack,
+    filename: &Path,
+    shell: Option<&Path>,
+) -> Result<Vec<String>, ShellError> {
+    let names = queue_add_plugin(engine_state, stack, filename, shell)?;
+    apply_plugin_changes(engine_state)?;
+
+    Ok(names)
+}
+
+/// Take the commands of `plugin`, a plugin's path or name like `nu_plugin_gstat` or `gstat`, out
+/// of the engine and the registry file, and return their names.
+///
+/// This is what `plugin rm` does, for hosts that manage plugins from code.
+pub fn remove_plugin(
+    engine_state: &mut EngineState,
+    plugin: &str,
+) -> Result<Vec<String>, ShellError> {
+    let names = queue_remove_plugin(engine_state, plugin)?;
+    apply_plugin_changes(engine_state)?;
+
+    Ok(names)
+}
+
+/// Apply the plugins added and removed while the engine was evaluating on this thread.
+///
+/// [`eval_source`](crate::helpers::eval_source) calls this once it has evaluated the source, so
+/// the commands of `plugin add` are there from the next line on.
+pub fn apply_plugin_changes(engine_state: &mut EngineState) -> Result<(), ShellError> {
+    let pending = PENDING.with(|pending| std::mem::take(&mut *pending.borrow_mut()));
+
+    if !pending.is_empty() {
+        let delta = {
+            let mut working_set = StateWorkingSet::new(engine_state);
+            for change in pending {
+                match change {
+                    Change::Add(decls) => {
+                        for decl in decls {
+                            working_set.add_decl(Box::new(decl));
+                        }
+                    }
+                    Change::Remove(names) => {
+                        for name in names {
+                            working_set.hide_decl(name.as_bytes());
+                        }
+                    }
+                }
+            }
+            working_set.render()
+        };
+        engine_state.merge_delta(delta)?;
+    }
+
+    let plugins = registry(engine_state, &[], &removed_plugins(engine_state));
+    let mut files = PLUGIN_FILES
+        .lock()
+        .expect("plugin files are never poisoned");
+    for (filename, _) in plugins.iter().filter_map(|decl| decl.is_plugin()) {
+        if !files.contains(filename) {
+            files.push(filename.clone());
+        }
+    }
+
+    Ok(())
+}
+
+// `register` rewrites the registry with every plugin the engine has seen, the removed ones too,
+// so when it added plugins since the engine had `decls_before` commands the registry is written
+// again without them
+pub(crate) fn restore_registry(
+    engine_state: &EngineState,
+    decls_before: usize,
+) -> Result<(), ShellError> {
+    let Some(path) = &engine_state.plugin_signatures else {
+        return Ok(());
+    };
+    let registered: Vec<String> = (decls_before..engine_state.num_decls())
+        .map(|decl_id| engine_state.get_decl(decl_id))
+        .filter(|decl| decl.is_plugin().is_some())
+        .map(|decl| decl.name().to_string())
+        .collect();
+
+    let removed = {
+        let mut removed = REMOVED.lock().expect("removed plugins are never poisoned");
+        let Some(removed) = removed.get_mut(path) else {
+            return Ok(());
+        };
+        // registered again, like with `plugin add`
+        removed.retain(|name| !registered.contains(name));
+        removed.clone()
+    };
+    if registered.is_empty() {
+        return Ok(());
+    }
+
+    write_registry(engine_state, &registry(engine_state, &[], &removed))
+}
+
+/// Stop plugin processes that are still running after `timeout`, or let them run as long as they
+/// like with `None`, the default.
+///
+/// Plugins get a process of their own for every call, which exits once it has answered. One that's
+/// still around after the timeout has most likely hung, and the call it was answering fails when
+/// it's stopped. This is checked about once a second.
+pub fn set_plugin_timeout(timeout: Option<Duration>) {
+    let secs = timeout.map_or(0, |timeout| timeout.as_secs().max(1));
+    PLUGIN_TIMEOUT.store(secs, Ordering::Relaxed);
+
+    if secs > 0 {
+        REAPER.call_once(|| {
+            let _ = std::thread::Builder::new()
+                .name("plugin reaper".into())
+                .spawn(reap_plugins);
+        });
+    }
+}
+
+/// The plugin processes running right now.
+pub fn plugin_processes() -> Vec<PluginProcess> {
+    let system = processes();
+    find_plugin_processes(&system)
+        .into_iter()
+        .map(|(_, process)| process)
+        .collect()
+}
+
+/// Stop the processes of `plugin`, a plugin's path or name like `nu_plugin_gstat` or `gstat`, and
+/// return the ones that were stopped. The calls they were answering fail.
+pub fn stop_plugin(plugin: &str) -> Vec<PluginProcess> {
+    let system = processes();
+    find_plugin_processes(&system)
+        .into_iter()
+        .filter(|(_, process)| is_plugin_named(&process.filename, plugin))
+        .map(|(pid, process)| {
+            kill_tree(&system, pid);
+            process
+        })
+        .collect()
+}
+
+/// Stop every plugin process, so none are left behind when the host exits in the middle of a call.
+pub fn stop_plugins() -> Vec<PluginProcess> {
+    let system = processes();
+    find_plugin_processes(&system)
+        .into_iter()
+        .map(|(pid, process)| {
+            kill_tree(&system, pid);
+            process
+        })
+        .collect()
+}
+
+fn reap_plugins() {
+    loop {
+        std::thread::sleep(Duration::from_secs(1));
+
+        let timeout = PLUGIN_TIMEOUT.load(Ordering::Relaxed);
+        if timeout == 0 {
+            continue;
+        }
+
+        let system = processes();
+        for (pid, process) in find_plugin_processes(&system) {
+            if process.running.as_secs() >= timeout {
+                kill_tree(&system, pid);
+            }
+        }
+    }
+}
+
+fn processes() -> System {
+    let mut system = System::new();
+    system.refresh_processes();
+    system
+}
+
+// our children running a plugin executable, directly or through the shell they were added with
+fn find_plugin_processes(system: &System) -> Vec<(Pid, PluginProcess)> {
+    let Ok(own_pid) = sysinfo::get_current_pid() else {
+        return vec![];
+    };
+    let files = PLUGIN_FILES
+        .lock()
+        .expect("plugin files are never poisoned")
+        .clone();
+
+    system
+        .processes()
+        .iter()
+        .filter(|(_, process)| process.parent() == Some(own_pid))
+        .filter_map(|(pid, process)| {
+            let filename = files.iter().find(|file| {
+                process.exe() == file.as_path()
+                    || process
+                        .cmd()
+                        .iter()
+                        .any(|arg| Path::new(arg) == file.as_path())
+            })?;
+
+            Some((
+                *pid,
+                PluginProcess {
+                    pid: pid.as_u32(),
+                    filename: filename.clone(),
+                    running: Duration::from_secs(process.run_time()),
+                },
+            ))
+        })
+        .collect()
+}
+
+// a plugin run through `sh -c` is a grandchild, so its children go first
+fn kill_tree(system: &System, pid: Pid) {
+    for (child, process) in system.processes() {
+        if process.parent() == Some(pid) {
+            kill_tree(system, *child);
+        }
+    }
+
+    if let Some(process) = system.process(pid) {
+        process.kill();
+    }
+}
+
+pub(crate) fn queue_add_plugin(
+    engine_state: &EngineState,
+    stack: &Stack,
+    filename: &Path,
+    shell: Option<&Path>,
+) -> Result<Vec<String>, ShellError> {
+    let filename = filename.canonicalize().map_err(|err| {
+        ShellError::PluginFailedToLoad(format!("can't find {}: {err}", filename.display()))
+    })?;
+    let shell = shell.map(Path::to_path_buf);
+
+    let envs = env_to_strings(engine_state, stack)?;
+    let signatures = get_signature(&filename, &shell, &envs).map_err(|err| {
+        ShellError::GenericError(
+            format!("Unable to load the plugin {}", filename.display()),
+            err.to_string(),
+            None,
+            Some(format!(
+                "plugins need to speak the json or msgpack plugin protocol of nu {PLUGIN_PROTOCOL_VERSION}"
+            )),
+            vec![err],
+        )
+    })?;
+    let decls: Vec<PluginDeclaration> = signatures
+        .into_iter()
+        .map(|signature| PluginDeclaration::new(filename.clone(), signature, shell.clone()))
+        .collect();
+    let names: Vec<String> = decls.iter().map(|decl| decl.name().to_string()).collect();
+
+    if let Some(path) = &engine_state.plugin_signatures {
+        let mut removed = REMOVED.lock().expect("removed plugins are never poisoned");
+        if let Some(removed) = removed.get_mut(path) {
+            removed.retain(|name| !names.contains(name));
+        }
+    }
+    PENDING.with(|pending| pending.borrow_mut().push(Change::Add(decls)));
+
+    let plugins = PENDING.with(|pending| {
+        registry(
+            engine_state,
+            &pending.borrow(),
+            &removed_plugins(engine_state),
+        )
+    });
+    write_registry(engine_state, &plugins)?;
+
+    Ok(names)
+}
+
+pub(crate) fn queue_remove_plugin(
+    engine_state: &EngineState,
+    plugin: &str,
+) -> Result<Vec<String>, ShellError> {
+    let plugins = PENDING.with(|pending| {
+        registry(
+            engine_state,
+            &pending.borrow(),
+            &removed_plugins(engine_state),
+        )
+    });
+    let names: Vec<String> = plugins
+        .into_iter()
+        .filter(|decl| {
+            decl.is_plugin()
+                .is_some_and(|(filename, _)| is_plugin_named(filename, plugin))
+        })
+        .map(|decl| decl.name().to_string())
+        .collect();
+    if names.is_empty() {
+        return Err(ShellError::GenericError(
+            "Plugin not found".into(),
+            format!("no plugin named '{plugin}' is registered"),
+            None,
+            Some("give the plugin's path, or its file name like nu_plugin_gstat".into()),
+            vec![],
+        ));
+    }
+
+    if let Some(path) = &engine_state.plugin_signatures {
+        let mut removed = REMOVED.lock().expect("removed plugins are never poisoned");
+        removed
+            .entry(path.clone())
+            .or_default()
+            .extend(names.iter().cloned());
+    }
+    PENDING.with(|pending| pending.borrow_mut().push(Change::Remove(names.clone())));
+
+    let plugins = PENDING.with(|pending| {
+        registry(
+            engine_state,
+            &pending.borrow(),
+            &removed_plugins(engine_state),
+        )
+    });
+    write_registry(engine_state, &plugins)?;
+
+    Ok(names)
+}
+
+// the commands of the plugins removed from the engine's registry
+fn removed_plugins(engine_state: &EngineState) -> Vec<String> {
+    let Some(path) = &engine_state.plugin_signatures else {
+        return vec![];
+    };
+    let removed = REMOVED.lock().expect("removed plugins are never poisoned");
+    removed.get(path).cloned().unwrap_or_default()
+}
+
+// the plugin's path, or its file name with or without `nu_plugin_` and `.exe`
+fn is_plugin_named(filename: &Path, plugin: &str) -> bool {
+    if filename == Path::new(plugin) {
+        return true;
+    }
+
+    let stem = filename.file_stem().and_then(|stem| stem.to_str());
+    stem == Some(plugin) || stem.and_then(|stem| stem.strip_prefix("nu_plugin_")) == Some(plugin)
+}
+
+// the plugin commands of the engine, with the pending changes applied, ordered by name
+fn registry(
+    engine_state: &EngineState,
+    pending: &[Change],
+    removed: &[String],
+) -> Vec<Box<dyn Command>> {
+    let mut plugins: BTreeMap<String, Box<dyn Command>> = engine_state
+        .plugin_decls()
+        .map(|decl| (decl.name().to_string(), decl.clone()))
+        .collect();
+
+    for change in pending {
+        if let Change::Add(decls) = change {
+            for decl in decls {
+                plugins.insert(decl.name().to_string(), Box::new(decl.clone()));
+            }
+        }
+    }
+    plugins.retain(|name, _| !removed.contains(name));
+
+    plugins.into_values().collect()
+}
+
+// the registry in the format `register` writes it, one `register` call per command, so the
+// plugins can be loaded without spawning them
+fn write_registry(
+    engine_state: &EngineState,
+    plugins: &[Box<dyn Command>],
+) -> Result<(), ShellError> {
+    let Some(path) = &engine_state.plugin_signatures else {
+        return Ok(());
+    };
+
+    let mut contents = String::new();
+    for decl in plugins {
+        let Some((filename, shell)) = decl.is_plugin() else {
+            continue;
+        };
+
+        let filename = path_arg(filename);
+        let shell = shell
+            .as_ref()
+            .map(|shell| format!("-s {}", path_arg(shell)))
+            .unwrap_or_default();
+
+        let examples = decl
+            .examples()
+            .into_iter()
+            .map(|example| PluginExample {
+                example: example.example.into(),
+                description: example.description.into(),
+                result: example.result,
+            })
+            .collect();
+        let signature =
+            serde_json::to_string_pretty(&PluginSignature::new(decl.signature(), examples))
+                .map_err(|err| ShellError::PluginFailedToLoad(err.to_string()))?;
+
+        contents.push_str(&format!("register {filename} {shell} {signature}\n\n"));
+    }
+
+    write_file(path, contents.as_bytes()).map_err(|err| {
+        ShellError::PluginFailedToLoad(format!(
+            "could not write the plugin registry {}: {err}",
+            path.display()
+        ))
+    })
+}
+
+// a path as an argument of `register`, quoted when it has more than the characters of a
+// bare word in it
+fn path_arg(path: &Path) -> String {
+    let path = path.to_string_lossy();
+    let bare = path
+        .chars()
+        .all(|c| c.is_alphanumeric() || "/\\._-:+~".contains(c));
+    match bare {
+        true => path.into_owned(),
+        false if !path.contains('`') => format!("`{path}`"),
+        false => escape_quote_string(&path),
+    }
+}
+
+// written next to the file and renamed over it, so it's never left half written
+fn write_file(path: &Path, contents: &[u8]) -> std::io::Result<()> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+
+    let mut temp_name = path.file_name().unwrap_or_default().to_os_string();
+    temp_name.push(format!(".{}.tmp", std::process::id()));
+    let temp_path = path.with_file_name(temp_name);
+
+    let written = std::fs::File::create(&temp_path).and_then(|mut file| {
+        file.write_all(contents)?;
+        file.sync_all()
+    });
+    match written.and_then(|_| std::fs::rename(&temp_path, path)) {
+        Ok(()) => Ok(()),
+        Err(err) => {
+            let _ = std::fs::remove_file(&temp_path);
+            Err(err)
+        }
+    }
+}
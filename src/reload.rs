@@ -0,0 +1,144 @@
+use std::{
+    cell::RefCell,
+    path::{Path, PathBuf},
+};
+
+use nu_engine::eval_block;
+use nu_parser::{escape_quote_string, parse};
+use nu_protocol::{
+    engine::{EngineState, Stack, StateWorkingSet},
+    ModuleId, PipelineData, ShellError, Span,
+};
+
+use crate::helpers::{report_error_new, sync_pwd};
+
+thread_local! {
+    // `overlay reload` runs with a shared engine, so the files wait here for the evaluation to
+    // finish; an evaluation runs on one thread, so the ones on other threads don't see them
+    static RELOADS: RefCell<Vec<PathBuf>> = const { RefCell::new(vec![]) };
+}
+
+/// Parse the module file at `path` again and swap the definitions and environment of its overlay
+/// for the new ones, like `overlay use --reload` does in the REPL.
+///
+/// The overlay is named after the file, and added if it isn't there yet. Unlike with `use`, the
+/// commands the file no longer defines are gone afterwards.
+pub fn reload_module(
+    engine_state: &mut EngineState,
+    stack: &mut Stack,
+    path: &Path,
+) -> Result<(), ShellError> {
+    let path = path.canonicalize().map_err(|err| {
+        ShellError::IOError(format!("could not reload {}: {err}", path.display()))
+    })?;
+    let source = format!(
+        "overlay use --reload {}",
+        escape_quote_string(&path.to_string_lossy())
+    );
+
+    // the overlay is named after the file, like the module is
+    let name = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let exported_before = match engine_state.find_overlay(name.as_bytes()) {
+        Some(_) => latest_exports(engine_state, &name, 0),
+        None => vec![],
+    };
+    let first_new_module = engine_state.num_modules();
+
+    sync_pwd(engine_state, stack);
+
+    let (block, delta) = {
+        let mut working_set = StateWorkingSet::new(engine_state);
+        let block = parse(&mut working_set, Some("reload"), source.as_bytes(), false);
+
+        // the module's source goes away with the working set, so the error says where it was
+        if let Some(err) = working_set.parse_errors.first() {
+            let start = err.span().start;
+            let label = working_set
+                .files()
+                .find(|(_, file_start, file_end)| (*file_start..=*file_end).contains(&start))
+                .map(|(file, file_start, _)| {
+                    let before = working_set.get_span_contents(Span::new(*file_start, start));
+                    let line = before.iter().filter(|b| **b == b'\n').count() + 1;
+                    format!("{file}:{line}: {err}")
+                })
+                .unwrap_or_else(|| err.to_string());
+            return Err(ShellError::GenericError(
+                format!("Could not reload {}", path.display()),
+                label,
+                None,
+                None,
+                vec![],
+            ));
+        }
+
+        (block, working_set.render())
+    };
+
+    engine_state.merge_delta(delta)?;
+    eval_block(
+        engine_state,
+        stack,
+        &block,
+        PipelineData::empty(),
+        false,
+        false,
+    )?;
+
+    // `overlay use --reload` adds the new definitions to the old ones
+    let exported = latest_exports(engine_state, &name, first_new_module);
+    let stale: Vec<Vec<u8>> = exported_before
+        .into_iter()
+        .filter(|command| !exported.contains(command))
+        .collect();
+    if !stale.is_empty() {
+        let mut working_set = StateWorkingSet::new(engine_state);
+        working_set.hide_decls(&stale);
+        let delta = working_set.render();
+        engine_state.merge_delta(delta)?;
+    }
+
+    Ok(())
+}
+
+// the commands exported by the module named `name` that was parsed last, the overlay's origin
+// stays at the one it was first added from
+fn latest_exports(engine_state: &EngineState, name: &str, from: ModuleId) -> Vec<Vec<u8>> {
+    (from..engine_state.num_modules())
+        .rev()
+        .map(|module_id| engine_state.get_module(module_id))
+        .find(|module| module.name == name.as_bytes())
+        .map(|module| module.decls.keys().cloned().collect())
+        .unwrap_or_default()
+}
+
+pub(crate) fn queue_reload(path: PathBuf) {
+    RELOADS.with(|reloads| reloads.borrow_mut().push(path));
+}
+
+// the reloads queued so far, so an evaluation within another one only applies its own
+pub(crate) fn take_pending_reloads() -> Vec<PathBuf> {
+    RELOADS.with(|reloads| std::mem::take(&mut *reloads.borrow_mut()))
+}
+
+// put back the reloads of an outer evaluation once the inner one applied its own
+pub(crate) fn restore_pending_reloads(paths: Vec<PathBuf>) {
+    RELOADS.with(|reloads| *reloads.borrow_mut() = paths);
+}
+
+/// Reload the modules `overlay reload` asked for while the engine was evaluating on this thread.
+///
+/// [`eval_source`](crate::helpers::eval_source) calls this once it has evaluated the source,
+/// so each evaluation applies the reloads it asked for, even with clones of the engine
+/// evaluating on other threads. Failures are reported, the other modules are still reloaded.
+pub fn apply_pending_reloads(engine_state: &mut EngineState, stack: &mut Stack) {
+    let paths = take_pending_reloads();
+
+    for path in paths {
+        if let Err(err) = reload_module(engine_state, stack, &path) {
+            report_error_new(engine_state, &err);
+        }
+    }
+}
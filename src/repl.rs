@@ -0,0 +1,538 @@
+use std::{
+    borrow::Cow,
+    io::{self, BufRead, BufReader, IsTerminal, Read, Write},
+    process::{Command, Stdio},
+    str::FromStr,
+    sync::{atomic::Ordering, Arc, Mutex, PoisonError, RwLock},
+};
+
+use chrono::Utc;
+use crossterm::terminal;
+use nu_cli::NuValidator;
+use nu_engine::{env::get_config, eval_subexpression};
+use nu_protocol::{
+    engine::{EngineState, Stack},
+    Config, HistoryFileFormat, PipelineData, PipelineIterator, ShellError, Value,
+};
+use reedline::{
+    ColumnarMenu, Emacs, Prompt, PromptEditMode, PromptHistorySearch, PromptHistorySearchStatus,
+    PromptViMode, Reedline, ReedlineMenu, Signal, Vi,
+};
+
+use crate::{
+    completions::completer,
+    helpers::{
+        eval_source_to_pipeline, print_pipeline_data, render_item, render_table, report_error_new,
+        set_last_exit_code,
+    },
+    history::open_repl_history,
+    hooks::display_output,
+    keybindings::{host_fn, ModeKeybindings, COMPLETION_MENU},
+    pager,
+};
+
+/// Read entries and evaluate each one with the state left by the entries before it, until
+/// ctrl-d or the end of stdin.
+///
+/// A terminal gets a line editor, anything else is read line by line.
+pub fn evaluate_repl(engine_state: &mut EngineState, stack: &mut Stack) -> io::Result<()> {
+    if !io::stdin().is_terminal() {
+        return evaluate_lines(engine_state, stack);
+    }
+
+    let mut line_editor = Reedline::create();
+    match open_repl_history(engine_state) {
+        Ok(history) => line_editor = line_editor.with_history(history),
+        Err(err) => report_error_new(engine_state, &err),
+    }
+    line_editor = line_editor.with_menu(ReedlineMenu::EngineCompleter(Box::new(
+        ColumnarMenu::default().with_name(COMPLETION_MENU),
+    )));
+    let mut entry_num = 0;
+
+    loop {
+        // an entry with an open bracket or string continues on the next line
+        line_editor = line_editor
+            .with_validator(Box::new(NuValidator {
+                engine_state: Arc::new(engine_state.clone()),
+            }))
+            .with_completer(Box::new(completer(engine_state, stack)));
+
+        // the config can change the bindings and the mode with each entry
+        let keybindings = ModeKeybindings::new(engine_state);
+        line_editor = line_editor.with_edit_mode(match edit_mode(engine_state, stack) {
+            EditMode::Emacs => Box::new(Emacs::new(keybindings.emacs)),
+            EditMode::Vi => Box::new(Vi::new(keybindings.vi_insert, keybindings.vi_normal)),
+        });
+
+        let prompt = ReplPrompt::new(engine_state, stack);
+        match line_editor.read_line(&prompt)? {
+            Signal::Success(line) => {
+                if let Some(f) = host_fn(&line) {
+                    f(engine_state, stack);
+                    continue;
+                }
+                if line.trim().is_empty() {
+                    continue;
+                }
+
+                // only a sqlite history keeps more than the entry itself
+                if matches!(
+                    engine_state.get_config().history_file_format,
+                    HistoryFileFormat::Sqlite
+                ) && line_editor.has_last_command_context()
+                {
+                    let cwd = stack
+                        .get_env_var(engine_state, "PWD")
+                        .and_then(|pwd| pwd.as_string().ok());
+                    let started = Utc::now();
+                    let result = line_editor.update_last_command_context(&|mut item| {
+                        item.start_timestamp = Some(started);
+                        item.cwd = cwd.clone();
+                        item
+                    });
+                    if let Err(err) = result {
+                        report_error_new(engine_state, &ShellError::IOError(err.to_string()));
+                    }
+                }
+
+                // other sessions see the entry right away, not only once this one ends
+                if engine_state.get_config().sync_history_on_enter {
+                    if let Err(err) = line_editor.sync_history() {
+                        report_error_new(engine_state, &ShellError::IOError(err.to_string()));
+                    }
+                }
+
+                entry_num += 1;
+                evaluate_entry(engine_state, stack, &line, entry_num);
+            }
+            // drops what was typed so far, like in other shells
+            Signal::CtrlC => {}
+            Signal::CtrlD => return Ok(()),
+        }
+    }
+}
+
+fn evaluate_lines(engine_state: &mut EngineState, stack: &mut Stack) -> io::Result<()> {
+    let mut stdin = io::stdin().lock();
+    let mut line = String::new();
+    let mut entry_num = 0;
+
+    loop {
+        print!("> ");
+        io::stdout().flush()?;
+
+        line.clear();
+        if stdin.read_line(&mut line)? == 0 {
+            // leave the prompt on its own line after ctrl-d
+            println!();
+            return Ok(());
+        }
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        entry_num += 1;
+        evaluate_entry(engine_state, stack, &line, entry_num);
+    }
+}
+
+fn evaluate_entry(engine_state: &mut EngineState, stack: &mut Stack, line: &str, entry_num: usize) {
+    // a ctrl-c stops the entry it was pressed during, not the ones after it
+    if let Some(ctrlc) = &engine_state.ctrlc {
+        ctrlc.store(false, Ordering::SeqCst);
+    }
+
+    let pipeline_data = eval_source_to_pipeline(
+        engine_state,
+        stack,
+        line.as_bytes(),
+        &format!("entry #{entry_num}"),
+        PipelineData::Empty,
+        false,
+    )
+    .and_then(
+        |pipeline_data| match display_output(engine_state, stack, pipeline_data) {
+            Ok(pipeline_data) => Some(pipeline_data),
+            Err(err) => {
+                set_last_exit_code(stack, 1);
+                report_error_new(engine_state, &err);
+                None
+            }
+        },
+    );
+
+    if let Some(pipeline_data) = pipeline_data {
+        // externals are left to page their own output
+        match pipeline_data {
+            PipelineData::ExternalStream { .. } => {
+                print_pipeline_data(engine_state, stack, pipeline_data, false);
+            }
+            _ if io::stdout().is_terminal() => {
+                page_pipeline_data(engine_state, stack, pipeline_data)
+            }
+            _ => {
+                print_pipeline_data(engine_state, stack, pipeline_data, false);
+            }
+        }
+    }
+
+    // externals started by later entries should see a `cd` from this one
+    match stack
+        .get_env_var(engine_state, "PWD")
+        .map(|pwd| pwd.as_string())
+    {
+        Some(Ok(pwd)) => {
+            if let Err(err) = std::env::set_current_dir(pwd) {
+                report_error_new(engine_state, &ShellError::IOError(err.to_string()));
+            }
+        }
+        Some(Err(err)) => report_error_new(engine_state, &err),
+        None => {}
+    }
+}
+
+/// The keys the REPL edits entries with.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum EditMode {
+    /// The keys of emacs and most shells.
+    #[default]
+    Emacs,
+    /// vi's insert and normal modes, starting in insert mode.
+    Vi,
+}
+
+impl FromStr for EditMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "emacs" => Ok(EditMode::Emacs),
+            "vi" => Ok(EditMode::Vi),
+            _ => Err(format!("unknown edit mode '{s}', expected emacs or vi")),
+        }
+    }
+}
+
+static REPL_SETTINGS: RwLock<Option<ReplSettings>> = RwLock::new(None);
+
+/// How the host wants the REPL set up. Users can still change a setting in their config, the
+/// host's is what they get when they don't:
+///
+/// ```no_run
+/// use nu_app::repl::{set_repl_settings, EditMode, ReplSettings};
+///
+/// set_repl_settings(ReplSettings::new().edit_mode(EditMode::Vi));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct ReplSettings {
+    edit_mode: Option<EditMode>,
+}
+
+impl ReplSettings {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Edit entries in `mode` unless `$env.config.edit_mode` is set.
+    pub fn edit_mode(mut self, mode: EditMode) -> Self {
+        self.edit_mode = Some(mode);
+        self
+    }
+}
+
+/// Set up the REPL with `settings` from now on, for all engines of the process.
+pub fn set_repl_settings(settings: ReplSettings) {
+    *REPL_SETTINGS
+        .write()
+        .unwrap_or_else(PoisonError::into_inner) = Some(settings);
+}
+
+// the engine's config always has an edit mode, only the record tells whether the user set one
+fn edit_mode(engine_state: &EngineState, stack: &Stack) -> EditMode {
+    let config_mode = stack
+        .get_env_var(engine_state, "config")
+        .and_then(|config| config.get_data_by_key("edit_mode"))
+        .and_then(|mode| mode.as_string().ok()?.parse().ok());
+    let host_mode = || {
+        let settings = REPL_SETTINGS.read().unwrap_or_else(PoisonError::into_inner);
+        settings.as_ref()?.edit_mode
+    };
+
+    config_mode.or_else(host_mode).unwrap_or_default()
+}
+
+/// A prompt of the host application, for the REPL to show when `$env.PROMPT_COMMAND` and
+/// `$env.PROMPT_COMMAND_RIGHT` aren't set. It's asked again before each entry, so it can show
+/// the state the entries before it left, like the directory they `cd`'d to.
+///
+/// A closure is a prompt with only the left side:
+///
+/// ```no_run
+/// use nu_app::repl::set_host_prompt;
+///
+/// set_host_prompt(|_engine_state: &_, _stack: &_| "my_app> ".to_string());
+/// ```
+pub trait HostPrompt: Send + Sync {
+    /// The prompt before the entry.
+    fn left(&self, engine_state: &EngineState, stack: &Stack) -> String;
+
+    /// The prompt at the right end of the line, nothing by default.
+    fn right(&self, _engine_state: &EngineState, _stack: &Stack) -> String {
+        String::new()
+    }
+}
+
+impl<F> HostPrompt for F
+where
+    F: Fn(&EngineState, &Stack) -> String + Send + Sync,
+{
+    fn left(&self, engine_state: &EngineState, stack: &Stack) -> String {
+        self(engine_state, stack)
+    }
+}
+
+static HOST_PROMPT: Mutex<Option<Arc<dyn HostPrompt>>> = Mutex::new(None);
+
+/// Show `prompt` in the REPL where the environment doesn't set one.
+pub fn set_host_prompt(prompt: impl HostPrompt + 'static) {
+    *HOST_PROMPT.lock().unwrap_or_else(PoisonError::into_inner) = Some(Arc::new(prompt));
+}
+
+// the prompts of `$env.PROMPT_COMMAND` and friends, or of the host, rendered for one entry
+struct ReplPrompt {
+    left: String,
+    right: String,
+    indicator: String,
+    vi_insert_indicator: String,
+    vi_normal_indicator: String,
+    multiline_indicator: String,
+}
+
+impl ReplPrompt {
+    fn new(engine_state: &EngineState, stack: &Stack) -> Self {
+        let host_prompt = HOST_PROMPT
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone();
+        // what the prompts set in the environment is thrown away with the clone, like in nu
+        let mut prompt_stack = stack.clone();
+        let mut prompt = |name: &str| prompt_string(engine_state, &mut prompt_stack, name);
+
+        ReplPrompt {
+            left: prompt("PROMPT_COMMAND")
+                .or_else(|| Some(host_prompt.as_ref()?.left(engine_state, stack)))
+                .unwrap_or_default(),
+            right: prompt("PROMPT_COMMAND_RIGHT")
+                .or_else(|| Some(host_prompt.as_ref()?.right(engine_state, stack)))
+                .unwrap_or_default(),
+            // the same `> ` the line by line REPL prints
+            indicator: prompt("PROMPT_INDICATOR").unwrap_or_else(|| "> ".into()),
+            // nu's indicators, which tell the vi modes apart
+            vi_insert_indicator: prompt("PROMPT_INDICATOR_VI_INSERT")
+                .unwrap_or_else(|| ": ".into()),
+            vi_normal_indicator: prompt("PROMPT_INDICATOR_VI_NORMAL")
+                .unwrap_or_else(|| "> ".into()),
+            multiline_indicator: prompt("PROMPT_MULTILINE_INDICATOR")
+                .unwrap_or_else(|| "::: ".into()),
+        }
+    }
+}
+
+// `$env.<name>` as a string, running it first if it's a closure
+fn prompt_string(engine_state: &EngineState, stack: &mut Stack, name: &str) -> Option<String> {
+    let pipeline_data = match stack.get_env_var(engine_state, name)? {
+        Value::Closure {
+            val: block_id,
+            captures,
+            ..
+        } => {
+            let block = engine_state.get_block(block_id);
+            let mut stack = stack.captures_to_stack(&captures);
+            // a subexpression collects the output of externals too, like `git branch`
+            match eval_subexpression(engine_state, &mut stack, block, PipelineData::empty()) {
+                Ok(pipeline_data) => pipeline_data,
+                Err(err) => {
+                    report_error_new(engine_state, &err);
+                    return None;
+                }
+            }
+        }
+        value @ Value::String { .. } => PipelineData::Value(value, None),
+        _ => return None,
+    };
+
+    let prompt = pipeline_data
+        .collect_string("", engine_state.get_config())
+        .ok()?;
+    Some(prompt.trim_end_matches(['\n', '\r']).to_string())
+}
+
+impl Prompt for ReplPrompt {
+    fn render_prompt_left(&self) -> Cow<'_, str> {
+        Cow::Borrowed(&self.left)
+    }
+
+    fn render_prompt_right(&self) -> Cow<'_, str> {
+        Cow::Borrowed(&self.right)
+    }
+
+    fn render_prompt_indicator(&self, prompt_mode: PromptEditMode) -> Cow<'_, str> {
+        Cow::Borrowed(match prompt_mode {
+            PromptEditMode::Vi(PromptViMode::Insert) => &self.vi_insert_indicator,
+            PromptEditMode::Vi(PromptViMode::Normal) => &self.vi_normal_indicator,
+            _ => &self.indicator,
+        })
+    }
+
+    fn render_prompt_multiline_indicator(&self) -> Cow<'_, str> {
+        Cow::Borrowed(&self.multiline_indicator)
+    }
+
+    fn render_prompt_history_search_indicator(
+        &self,
+        history_search: PromptHistorySearch,
+    ) -> Cow<'_, str> {
+        let prefix = match history_search.status {
+            PromptHistorySearchStatus::Passing => "",
+            PromptHistorySearchStatus::Failing => "failing ",
+        };
+        Cow::Owned(format!(
+            "({prefix}reverse-search: {}) ",
+            history_search.term
+        ))
+    }
+}
+
+/// Print `pipeline_data` as a table, going through a pager when it doesn't fit on the screen.
+///
+/// `$env.config.pager` picks the pager: a command line runs that program with the output on
+/// its stdin, `false` turns paging off and anything else uses the built-in one.
+fn page_pipeline_data(engine_state: &EngineState, stack: &mut Stack, pipeline_data: PipelineData) {
+    let pager_command = match stack
+        .get_env_var(engine_state, "config")
+        .and_then(|config| config.get_data_by_key("pager"))
+    {
+        Some(Value::Bool { val: false, .. }) => {
+            print_pipeline_data(engine_state, stack, pipeline_data, false);
+            return;
+        }
+        Some(Value::String { val, .. }) => Some(val),
+        _ => None,
+    };
+
+    let table = match render_table(engine_state, stack, pipeline_data) {
+        Ok(table) => table,
+        Err(err) => {
+            set_last_exit_code(stack, 1);
+            report_error_new(engine_state, &err);
+            return;
+        }
+    };
+
+    // only as much is read up front as it takes to know whether the output fits
+    let mut output = BufReader::new(OutputReader {
+        engine_state,
+        config: get_config(engine_state, stack),
+        values: table.into_iter(),
+        chunk: vec![],
+        pos: 0,
+        errors: vec![],
+    });
+    let rows = terminal::size().map(|(_, rows)| rows).unwrap_or(24) as usize;
+    let mut lines = vec![];
+    let result = loop {
+        if lines.len() >= rows {
+            break match pager_command {
+                Some(command) => external_pager(&command, lines, &mut output),
+                None => pager::page(lines, &mut output),
+            };
+        }
+
+        match pager::read_line(&mut output) {
+            Some(line) => lines.push(line),
+            None => {
+                let mut stdout = io::stdout().lock();
+                break lines
+                    .iter()
+                    .try_for_each(|line| writeln!(stdout, "{line}"))
+                    .and_then(|_| stdout.flush());
+            }
+        }
+    };
+
+    // the errors in the output go to stderr once it's shown, so they don't end up in the pager
+    let errors = std::mem::take(&mut output.get_mut().errors);
+    let mut stderr = io::stderr().lock();
+    let written = errors
+        .iter()
+        .try_for_each(|error| writeln!(stderr, "{error}"))
+        .and_then(|_| stderr.flush());
+
+    if let Err(err) = result.and(written) {
+        report_error_new(engine_state, &ShellError::IOError(err.to_string()));
+    }
+    // an error in the output fails the entry like an error while evaluating it
+    set_last_exit_code(stack, if errors.is_empty() { 0 } else { 1 });
+}
+
+fn external_pager(command: &str, lines: Vec<String>, mut rest: impl BufRead) -> io::Result<()> {
+    let mut words = command.split_whitespace();
+    let Some(program) = words.next() else {
+        return pager::page(lines, rest);
+    };
+
+    let mut child = Command::new(program)
+        .args(words)
+        .stdin(Stdio::piped())
+        .spawn()?;
+    let mut stdin = child.stdin.take().expect("the pager's stdin is piped");
+
+    let written = lines
+        .iter()
+        .try_for_each(|line| writeln!(stdin, "{line}"))
+        .and_then(|_| io::copy(&mut rest, &mut stdin).map(|_| ()));
+    drop(stdin);
+    child.wait()?;
+
+    match written {
+        // quitting the pager before the end closes the pipe
+        Err(err) if err.kind() != io::ErrorKind::BrokenPipe => Err(err),
+        _ => Ok(()),
+    }
+}
+
+// the rendered output as text, with a line break after every value like `print` writes it,
+// and the errors in it set aside for stderr
+struct OutputReader<'a> {
+    engine_state: &'a EngineState,
+    config: Config,
+    values: PipelineIterator,
+    chunk: Vec<u8>,
+    pos: usize,
+    errors: Vec<String>,
+}
+
+impl Read for OutputReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.pos == self.chunk.len() {
+            let Some(value) = self.values.next() else {
+                return Ok(0);
+            };
+
+            let (mut text, is_error) = render_item(self.engine_state, &self.config, value, "\n");
+            if is_error {
+                self.errors.push(text);
+                continue;
+            }
+            text.push('\n');
+            self.chunk = text.into_bytes();
+            self.pos = 0;
+        }
+
+        let len = buf.len().min(self.chunk.len() - self.pos);
+        buf[..len].copy_from_slice(&self.chunk[self.pos..self.pos + len]);
+        self.pos += len;
+        Ok(len)
+    }
+}
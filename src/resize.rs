@@ -0,0 +1,73 @@
+#[cfg(not(unix))]
+use std::sync::Mutex;
+#[cfg(unix)]
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+
+/// Notices when the terminal changes size, so output that's drawn to its width, like the pages
+/// of a streaming table, can be drawn to the new one.
+///
+/// On Unix it's set by `SIGWINCH`, as long as it's kept. Windows has no such signal, so there
+/// [`take`](Self::take) compares the size with the one it saw last.
+///
+/// ```
+/// use nu_app::resize::ResizeSignal;
+///
+/// let resized = ResizeSignal::register();
+/// // nothing changed since it was registered
+/// assert!(!resized.take());
+/// ```
+#[cfg(unix)]
+pub struct ResizeSignal {
+    resized: Arc<AtomicBool>,
+    id: Option<signal_hook::SigId>,
+}
+
+#[cfg(unix)]
+impl ResizeSignal {
+    pub fn register() -> Self {
+        let resized = Arc::new(AtomicBool::new(false));
+        let id = signal_hook::flag::register(signal_hook::consts::SIGWINCH, resized.clone()).ok();
+        ResizeSignal { resized, id }
+    }
+
+    /// Whether the terminal changed size since the last call.
+    pub fn take(&self) -> bool {
+        self.resized.swap(false, Ordering::SeqCst)
+    }
+}
+
+#[cfg(unix)]
+impl Drop for ResizeSignal {
+    fn drop(&mut self) {
+        if let Some(id) = self.id {
+            signal_hook::low_level::unregister(id);
+        }
+    }
+}
+
+#[cfg(not(unix))]
+pub struct ResizeSignal {
+    size: Mutex<Option<(u16, u16)>>,
+}
+
+#[cfg(not(unix))]
+impl ResizeSignal {
+    pub fn register() -> Self {
+        let size = crossterm::terminal::size().ok();
+        ResizeSignal {
+            size: Mutex::new(size),
+        }
+    }
+
+    /// Whether the terminal changed size since the last call.
+    pub fn take(&self) -> bool {
+        let size = crossterm::terminal::size().ok();
+        match self.size.lock() {
+            Ok(mut last) => std::mem::replace(&mut *last, size) != size,
+            Err(_) => false,
+        }
+    }
+}
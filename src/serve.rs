@@ -0,0 +1,402 @@
+use std::{
+    io::{self, BufRead, BufReader, BufWriter, Read, Write},
+    net::{TcpListener, ToSocketAddrs},
+    sync::{atomic::AtomicUsize, Arc},
+    thread,
+    time::Duration,
+};
+
+use nu_protocol::engine::EngineState;
+use serde_json::{json, Value as Json};
+
+pub use crate::daemon::DEFAULT_EVAL_TIMEOUT;
+use crate::{
+    create_stack,
+    daemon::{evaluate, ClientSlot, EvalRequest, Evaluation},
+    set_use_colors,
+};
+
+// the largest body a request may have, 16 MiB
+const MAX_BODY_LEN: usize = 16 * 1024 * 1024;
+// the most the request line and the headers may have together, 64 KiB
+const MAX_HEAD_LEN: usize = 64 * 1024;
+// how long a connection may take to send its request or read the response
+const IO_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// What [`serve`] and [`handle_http`] ask of a request, and how long its source may run.
+///
+/// With a token, only requests that send it as `Authorization: Bearer <token>` are evaluated:
+///
+/// ```
+/// use nu_app::{create_sandboxed_context, serve::{handle_http, ServeOptions}};
+///
+/// let options = ServeOptions::new().token("s3cret");
+/// let request = "POST /eval HTTP/1.1\r\nContent-Type: application/json\r\nContent-Length: 2\r\n\r\n{}";
+///
+/// let mut response = vec![];
+/// handle_http(&create_sandboxed_context(), &options, request.as_bytes(), &mut response).unwrap();
+/// assert!(response.starts_with(b"HTTP/1.1 401 Unauthorized\r\n"));
+/// ```
+#[derive(Debug, Clone)]
+pub struct ServeOptions {
+    token: Option<String>,
+    timeout: Duration,
+}
+
+impl Default for ServeOptions {
+    fn default() -> Self {
+        Self {
+            token: None,
+            timeout: DEFAULT_EVAL_TIMEOUT,
+        }
+    }
+}
+
+impl ServeOptions {
+    /// Options without a token, and with [`DEFAULT_EVAL_TIMEOUT`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Only evaluate the requests that send `token` as `Authorization: Bearer <token>`.
+    pub fn token(mut self, token: impl Into<String>) -> Self {
+        self.token = Some(token.into());
+        self
+    }
+
+    /// Stop a source once it has run for `timeout`, see
+    /// [`eval_source_with_timeout`](crate::eval_source_with_timeout).
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+}
+
+/// Keep `engine_state` warm and evaluate the sources other services `POST` to `/eval` on
+/// `addr`, like the `serve` subcommand of the binary does. See [`handle_http`].
+///
+/// Each connection is served on a thread of its own and closed after its response, or once it
+/// has taken 30 seconds to send its request. At most 64 are served at once, the ones after
+/// them are answered with `503 Service Unavailable`. Whatever `engine_state` has, the sources can run,
+/// so give it [`create_sandboxed_context`](crate::create_sandboxed_context) unless the
+/// services sending them are trusted with the machine.
+pub fn serve(
+    mut engine_state: EngineState,
+    addr: impl ToSocketAddrs,
+    options: ServeOptions,
+) -> io::Result<()> {
+    let listener = TcpListener::bind(addr)?;
+
+    // the answers are JSON, the errors in them aren't shown on a terminal
+    set_use_colors(&mut engine_state, &mut create_stack(), false);
+    let engine_state = Arc::new(engine_state);
+    let options = Arc::new(options);
+
+    let clients = Arc::new(AtomicUsize::new(0));
+
+    for stream in listener.incoming() {
+        let stream = stream?;
+        // a client that sends its request slowly mustn't keep the thread forever
+        stream.set_read_timeout(Some(IO_TIMEOUT))?;
+        stream.set_write_timeout(Some(IO_TIMEOUT))?;
+        let Some(slot) = ClientSlot::take(&clients) else {
+            let busy = Response::error(503, "the server is serving too many clients");
+            let _ = busy.write_to(BufWriter::new(stream));
+            continue;
+        };
+        let (engine_state, options) = (engine_state.clone(), options.clone());
+        thread::Builder::new()
+            .name("serve client".into())
+            .spawn(move || {
+                let _slot = slot;
+                let input = BufReader::new(stream.try_clone()?);
+                handle_http(&engine_state, &options, input, BufWriter::new(stream))
+            })?;
+    }
+
+    Ok(())
+}
+
+/// Answer the HTTP request read from `input` on `output`.
+///
+/// `POST /eval` takes a `Content-Type: application/json` body like
+/// `{"source": "$in | lines | length", "input": "a\nb", "env": {"PWD": "/tmp"}}` and evaluates
+/// the source on a copy of `engine_state`. The response is the JSON of
+/// [`eval_request`](crate::daemon::eval_request), `{"value": 2, "exit_code": 0}` or the
+/// rendered error under `error`. With `Accept: application/x-ndjson` it's the JSON of each
+/// item of a list on a line of its own instead, with a line holding the `error` when there's
+/// one. Either way, the `Nu-Exit-Code` header has the exit code of the source.
+///
+/// Requests with an `Origin` header are refused: they come from a web page, which could
+/// otherwise have the browser of anyone who opens it send sources to a server on their machine.
+///
+/// ```
+/// use nu_app::{create_sandboxed_context, serve::{handle_http, ServeOptions}};
+///
+/// let body = r#"{"source": "$in | lines", "input": "a\nb"}"#;
+/// let request = format!(
+///     "POST /eval HTTP/1.1\r\nAccept: application/x-ndjson\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{body}",
+///     body.len()
+/// );
+///
+/// let engine_state = create_sandboxed_context();
+/// let mut response = vec![];
+/// handle_http(&engine_state, &ServeOptions::new(), request.as_bytes(), &mut response).unwrap();
+///
+/// let response = String::from_utf8(response).unwrap();
+/// assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+/// assert!(response.contains("Nu-Exit-Code: 0\r\n"));
+/// assert!(response.ends_with("\r\n\r\n\"a\"\n\"b\"\n"));
+/// ```
+pub fn handle_http(
+    engine_state: &EngineState,
+    options: &ServeOptions,
+    mut input: impl BufRead,
+    output: impl Write,
+) -> io::Result<()> {
+    let response = match read_request(&mut input)? {
+        Some(Ok(request)) => respond(engine_state, options, &request),
+        Some(Err(response)) => response,
+        // the client closed the connection without asking anything
+        None => return Ok(()),
+    };
+
+    response.write_to(output)
+}
+
+struct Request {
+    method: String,
+    path: String,
+    ndjson: bool,
+    content_type: Option<String>,
+    authorization: Option<String>,
+    // whether it came from a web page
+    origin: bool,
+    body: Vec<u8>,
+}
+
+struct Response {
+    status: u16,
+    content_type: &'static str,
+    body: String,
+    exit_code: Option<i64>,
+}
+
+impl Response {
+    fn error(status: u16, error: impl Into<String>) -> Self {
+        Self {
+            status,
+            content_type: "application/json",
+            body: json!({ "error": error.into() }).to_string(),
+            exit_code: None,
+        }
+    }
+
+    fn write_to(&self, mut output: impl Write) -> io::Result<()> {
+        let status = match self.status {
+            200 => "OK",
+            400 => "Bad Request",
+            401 => "Unauthorized",
+            403 => "Forbidden",
+            404 => "Not Found",
+            405 => "Method Not Allowed",
+            411 => "Length Required",
+            413 => "Payload Too Large",
+            415 => "Unsupported Media Type",
+            431 => "Request Header Fields Too Large",
+            503 => "Service Unavailable",
+            _ => "",
+        };
+        write!(output, "HTTP/1.1 {} {status}\r\n", self.status)?;
+        write!(output, "Content-Type: {}\r\n", self.content_type)?;
+        write!(output, "Content-Length: {}\r\n", self.body.len())?;
+        if self.status == 401 {
+            write!(output, "WWW-Authenticate: Bearer\r\n")?;
+        }
+        if self.status == 405 {
+            write!(output, "Allow: POST\r\n")?;
+        }
+        if let Some(exit_code) = self.exit_code {
+            write!(output, "Nu-Exit-Code: {exit_code}\r\n")?;
+        }
+        write!(output, "Connection: close\r\n\r\n{}", self.body)?;
+        output.flush()
+    }
+}
+
+// the request, a response for one that's not valid HTTP, or `None` when there's none
+fn read_request(input: &mut impl BufRead) -> io::Result<Option<Result<Request, Response>>> {
+    let head = match read_head(input)? {
+        Some(Ok(head)) => head,
+        Some(Err(response)) => return Ok(Some(Err(response))),
+        None => return Ok(None),
+    };
+    let (request_line, headers) = head.split_first().expect("the head has the request line");
+
+    let mut parts = request_line.split_whitespace();
+    let (Some(method), Some(target), Some(_version)) = (parts.next(), parts.next(), parts.next())
+    else {
+        return Ok(Some(Err(Response::error(400, "not an HTTP request"))));
+    };
+    let path = target.split('?').next().unwrap_or_default();
+
+    let mut length = None;
+    let mut ndjson = false;
+    let mut content_type = None;
+    let mut authorization = None;
+    let mut origin = false;
+    for header in headers {
+        if let Some((name, value)) = header.split_once(':') {
+            let value = value.trim();
+            if name.eq_ignore_ascii_case("content-length") {
+                length = value.parse::<usize>().ok();
+            } else if name.eq_ignore_ascii_case("accept") {
+                ndjson = value.contains("application/x-ndjson");
+            } else if name.eq_ignore_ascii_case("content-type") {
+                content_type = Some(value.into());
+            } else if name.eq_ignore_ascii_case("authorization") {
+                authorization = Some(value.into());
+            } else if name.eq_ignore_ascii_case("origin") {
+                origin = true;
+            }
+        }
+    }
+
+    let body = match length {
+        Some(length) if length > MAX_BODY_LEN => {
+            let error = format!("the body is larger than {MAX_BODY_LEN} bytes");
+            return Ok(Some(Err(Response::error(413, error))));
+        }
+        Some(length) => {
+            let mut body = vec![0; length];
+            input.read_exact(&mut body)?;
+            body
+        }
+        None if method == "POST" => {
+            let error = "the request needs a Content-Length";
+            return Ok(Some(Err(Response::error(411, error))));
+        }
+        None => vec![],
+    };
+
+    Ok(Some(Ok(Request {
+        method: method.into(),
+        path: path.into(),
+        ndjson,
+        content_type,
+        authorization,
+        origin,
+        body,
+    })))
+}
+
+// the request line and the headers, without the empty line after them
+fn read_head(input: &mut impl BufRead) -> io::Result<Option<Result<Vec<String>, Response>>> {
+    let mut head = vec![];
+    let mut left = MAX_HEAD_LEN;
+    loop {
+        let mut line = vec![];
+        let read = input
+            .by_ref()
+            .take(left as u64)
+            .read_until(b'\n', &mut line)?;
+        if !line.ends_with(b"\n") {
+            if read < left {
+                // the client closed the connection before the end of the headers
+                return Ok(None);
+            }
+            let error =
+                format!("the request line and headers are larger than {MAX_HEAD_LEN} bytes");
+            return Ok(Some(Err(Response::error(431, error))));
+        }
+        left -= read;
+
+        let Ok(line) = String::from_utf8(line) else {
+            let error = "the request line and headers have to be UTF-8";
+            return Ok(Some(Err(Response::error(400, error))));
+        };
+        let line = line.trim_end();
+        match line.is_empty() {
+            // empty lines before the request line are left out, like HTTP asks servers to
+            true if head.is_empty() => {}
+            true => return Ok(Some(Ok(head))),
+            false => head.push(line.to_string()),
+        }
+    }
+}
+
+fn respond(engine_state: &EngineState, options: &ServeOptions, request: &Request) -> Response {
+    if request.origin {
+        return Response::error(403, "requests from web pages aren't evaluated");
+    }
+    if let Some(token) = &options.token {
+        let bearer = request
+            .authorization
+            .as_deref()
+            .and_then(|authorization| authorization.strip_prefix("Bearer "));
+        let bearer = bearer.map(str::trim).unwrap_or_default();
+        if !same_token(bearer.as_bytes(), token.as_bytes()) {
+            return Response::error(
+                401,
+                "the request needs the token as Authorization: Bearer <token>",
+            );
+        }
+    }
+    if request.path != "/eval" {
+        return Response::error(404, format!("there's nothing at {}", request.path));
+    }
+    if request.method != "POST" {
+        return Response::error(405, "/eval takes a POST");
+    }
+    // a web page can't send JSON without the browser asking the server first, unlike text
+    let json = request.content_type.as_deref().is_some_and(|content_type| {
+        let media_type = content_type.split(';').next().unwrap_or_default();
+        media_type.trim().eq_ignore_ascii_case("application/json")
+    });
+    if !json {
+        return Response::error(415, "the body has to be Content-Type: application/json");
+    }
+    let eval_request: EvalRequest = match serde_json::from_slice(&request.body) {
+        Ok(eval_request) => eval_request,
+        Err(err) => return Response::error(400, format!("the request isn't valid: {err}")),
+    };
+
+    let evaluation = evaluate(
+        engine_state,
+        &eval_request,
+        "request",
+        Some(options.timeout),
+    );
+    let (content_type, body) = match request.ndjson {
+        true => ("application/x-ndjson", ndjson_lines(&evaluation)),
+        false => ("application/json", evaluation.to_json().to_string()),
+    };
+
+    Response {
+        status: 200,
+        content_type,
+        body,
+        exit_code: Some(evaluation.exit_code),
+    }
+}
+
+// compares every byte, so how long it takes doesn't tell how much of a token was right
+fn same_token(sent: &[u8], token: &[u8]) -> bool {
+    let diff = sent
+        .iter()
+        .zip(token)
+        .fold(0, |diff, (sent, token)| diff | (sent ^ token));
+    sent.len() == token.len() && diff == 0
+}
+
+// a line for each item of a list, a single one for any other value and none for nothing
+fn ndjson_lines(evaluation: &Evaluation) -> String {
+    let values = match &evaluation.value {
+        Ok(Json::Array(items)) => items.clone(),
+        Ok(Json::Null) => vec![],
+        Ok(value) => vec![value.clone()],
+        Err(_) => vec![evaluation.to_json()],
+    };
+
+    values.iter().map(|value| format!("{value}\n")).collect()
+}
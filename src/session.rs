@@ -0,0 +1,126 @@
+use std::path::Path;
+
+use nu_protocol::{
+    engine::{EngineState, Stack},
+    IntoPipelineData, PipelineData, ShellError, Value,
+};
+
+use crate::{
+    errors::EvalError,
+    helpers::{create_engine_state, create_stack, eval_source_to_value},
+};
+
+/// An engine and a stack that sources are evaluated one after another in, like the entries of
+/// the REPL or the cells of a notebook. The definitions, variables and environment of a source
+/// are there for the ones after it.
+///
+/// ```
+/// use nu_app::session::Session;
+///
+/// let mut session = Session::new().chain_output(true);
+/// session.run("let base = 10").unwrap();
+/// session.run("[1 2 3]").unwrap();
+///
+/// // `$in` is the output of the source before
+/// let sum = session.run("$in | math sum | $in + $base").unwrap();
+/// assert_eq!(sum.as_i64().unwrap(), 16);
+/// ```
+pub struct Session {
+    engine_state: EngineState,
+    stack: Stack,
+    chain_output: bool,
+    last_output: Option<Value>,
+    runs: usize,
+}
+
+impl Session {
+    /// A session with the engine of [`create_engine_state`] and the stack of [`create_stack`].
+    pub fn new() -> Self {
+        Session::with_engine(create_engine_state(), create_stack())
+    }
+
+    /// A session that carries on with an engine and a stack the application set up itself.
+    pub fn with_engine(engine_state: EngineState, stack: Stack) -> Self {
+        Session {
+            engine_state,
+            stack,
+            chain_output: false,
+            last_output: None,
+            runs: 0,
+        }
+    }
+
+    /// Whether each source gets the output of the one before it as its input, `$in`. They get no
+    /// input by default.
+    pub fn chain_output(mut self, chain_output: bool) -> Self {
+        self.chain_output = chain_output;
+        self
+    }
+
+    /// Parse and evaluate `source` like [`eval_source_to_value`], named `entry #<n>` in errors.
+    ///
+    /// A source that fails leaves the output of the one before it as the last output.
+    pub fn run(&mut self, source: &str) -> Result<Value, EvalError> {
+        let input = match (&self.last_output, self.chain_output) {
+            (Some(value), true) => value.clone().into_pipeline_data(),
+            _ => PipelineData::Empty,
+        };
+
+        self.runs += 1;
+        let value = eval_source_to_value(
+            &mut self.engine_state,
+            &mut self.stack,
+            source.as_bytes(),
+            &format!("entry #{}", self.runs),
+            input,
+            true,
+        )?;
+
+        self.last_output = Some(value.clone());
+        Ok(value)
+    }
+
+    /// The output of the last source that ran without errors.
+    pub fn last_output(&self) -> Option<&Value> {
+        self.last_output.as_ref()
+    }
+
+    /// Forget the last output, so the next source gets no input.
+    pub fn clear_last_output(&mut self) {
+        self.last_output = None;
+    }
+
+    /// Load the module file at `path` again, see [`reload_module`](crate::reload::reload_module).
+    pub fn reload_module(&mut self, path: &Path) -> Result<(), ShellError> {
+        crate::reload::reload_module(&mut self.engine_state, &mut self.stack, path)
+    }
+
+    pub fn engine_state(&self) -> &EngineState {
+        &self.engine_state
+    }
+
+    /// The engine, e.g. to add the application's commands to with
+    /// [`add_commands`](crate::add_commands).
+    pub fn engine_state_mut(&mut self) -> &mut EngineState {
+        &mut self.engine_state
+    }
+
+    pub fn stack(&self) -> &Stack {
+        &self.stack
+    }
+
+    pub fn stack_mut(&mut self) -> &mut Stack {
+        &mut self.stack
+    }
+
+    /// The engine and the stack, with what the sources left in them.
+    pub fn into_parts(self) -> (EngineState, Stack) {
+        (self.engine_state, self.stack)
+    }
+}
+
+impl Default for Session {
+    fn default() -> Self {
+        Session::new()
+    }
+}
@@ -0,0 +1,142 @@
+use std::ops::Range;
+
+use nu_protocol::{engine::EngineState, Span};
+
+/// A source put together from fragments, like the cells of a notebook or the pieces of a
+/// template, that finds the fragment and line an offset into it is in.
+///
+/// The engine only knows the source as a whole, so the spans of errors point into that. The
+/// map takes them back to the fragments:
+///
+/// ```
+/// use nu_app::source_map::SourceMap;
+/// use nu_app::{create_engine_state, create_stack, eval_source_result, nu_protocol::PipelineData};
+///
+/// let mut sources = SourceMap::new("notebook");
+/// sources.push("cell 1", "let x = 1\n");
+/// sources.push("cell 2", "let y = 2\n$x + $z\n");
+///
+/// let mut engine_state = create_engine_state();
+/// let mut stack = create_stack();
+/// let err = eval_source_result(
+///     &mut engine_state,
+///     &mut stack,
+///     sources.source(),
+///     sources.name(),
+///     PipelineData::Empty,
+///     true,
+/// )
+/// .unwrap_err();
+///
+/// let location = sources.locate_span(&engine_state, err.span().unwrap()).unwrap();
+/// assert_eq!(location.fragment, "cell 2");
+/// assert_eq!((location.line, location.column), (2, 6));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct SourceMap {
+    name: String,
+    source: Vec<u8>,
+    fragments: Vec<Fragment>,
+}
+
+#[derive(Debug, Clone)]
+struct Fragment {
+    name: Option<String>,
+    range: Range<usize>,
+}
+
+/// Where in its fragment an offset is, see [`SourceMap::locate`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceLocation {
+    /// The name the fragment was pushed with.
+    pub fragment: String,
+    /// The offset in bytes from the start of the fragment.
+    pub offset: usize,
+    /// The line in the fragment, from 1.
+    pub line: usize,
+    /// The character in the line, from 1.
+    pub column: usize,
+}
+
+impl SourceMap {
+    /// An empty source, evaluated as `name`.
+    pub fn new(name: impl Into<String>) -> Self {
+        SourceMap {
+            name: name.into(),
+            ..Default::default()
+        }
+    }
+
+    /// Add `source` to the end, as the fragment `name`.
+    pub fn push(&mut self, name: impl Into<String>, source: impl AsRef<[u8]>) {
+        self.push_fragment(Some(name.into()), source.as_ref());
+    }
+
+    /// Add code the host generated to the end, like the glue between the pieces of a template.
+    /// It isn't any fragment, so offsets in it aren't located.
+    pub fn push_generated(&mut self, source: impl AsRef<[u8]>) {
+        self.push_fragment(None, source.as_ref());
+    }
+
+    fn push_fragment(&mut self, name: Option<String>, source: &[u8]) {
+        let start = self.source.len();
+        self.source.extend_from_slice(source);
+        self.fragments.push(Fragment {
+            name,
+            range: start..self.source.len(),
+        });
+    }
+
+    /// The name to evaluate the source as, which [`locate_span`](Self::locate_span) finds it
+    /// in the engine by.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The fragments one after the other, to evaluate.
+    pub fn source(&self) -> &[u8] {
+        &self.source
+    }
+
+    /// The fragment and line of `offset`, an offset in bytes into [`source`](Self::source).
+    /// The end of the source is in the last fragment.
+    pub fn locate(&self, offset: usize) -> Option<SourceLocation> {
+        let fragment = match offset == self.source.len() {
+            true => self.fragments.last()?,
+            false => self
+                .fragments
+                .iter()
+                .find(|fragment| fragment.range.contains(&offset))?,
+        };
+        let name = fragment.name.as_ref()?;
+
+        let before = &self.source[fragment.range.start..offset];
+        let line_start = before
+            .iter()
+            .rposition(|b| *b == b'\n')
+            .map_or(0, |pos| pos + 1);
+
+        Some(SourceLocation {
+            fragment: name.clone(),
+            offset: before.len(),
+            line: before.iter().filter(|b| **b == b'\n').count() + 1,
+            column: String::from_utf8_lossy(&before[line_start..])
+                .chars()
+                .count()
+                + 1,
+        })
+    }
+
+    /// The fragment and line of the start of `span`, a span of the engine the source was
+    /// evaluated in, like the ones of errors. Spans in other files aren't located.
+    pub fn locate_span(&self, engine_state: &EngineState, span: Span) -> Option<SourceLocation> {
+        let (_, start, _) = engine_state.files().find(|(name, start, end)| {
+            *name == self.name
+                && end - start == self.source.len()
+                && *start <= span.start
+                && span.end <= *end
+        })?;
+
+        self.locate(span.start - start)
+    }
+}
@@ -0,0 +1,114 @@
+use std::{
+    io::{self, Read},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread,
+};
+
+use nu_protocol::{PipelineData, PipelineIterator, RawStream, ShellError, Value};
+
+/// Reads the raw bytes of an external command's output without collecting them first.
+pub struct RawStreamReader {
+    stream: RawStream,
+    buffer: Vec<u8>,
+    pos: usize,
+}
+
+impl RawStreamReader {
+    pub fn new(mut stream: RawStream) -> Self {
+        let buffer = std::mem::take(&mut stream.leftover);
+
+        RawStreamReader {
+            stream,
+            buffer,
+            pos: 0,
+        }
+    }
+
+    /// Take the stdout of an `ExternalStream`, or `None` if the output isn't an external stream.
+    ///
+    /// Stderr is drained on a background thread so the external can't block on a full pipe.
+    pub fn from_pipeline_data(pipeline_data: PipelineData) -> Option<Self> {
+        match pipeline_data {
+            PipelineData::ExternalStream {
+                stdout: Some(stdout),
+                stderr,
+                ..
+            } => {
+                thread::Builder::new()
+                    .name("stderr consumer".to_string())
+                    .spawn(move || stderr.map(|x| x.into_bytes()))
+                    .expect("could not create thread");
+
+                Some(RawStreamReader::new(stdout))
+            }
+            _ => None,
+        }
+    }
+}
+
+impl Read for RawStreamReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.pos >= self.buffer.len() {
+            if was_pressed(&self.stream.ctrlc) {
+                return Ok(0);
+            }
+
+            match self.stream.stream.next() {
+                Some(Ok(chunk)) => {
+                    self.buffer = chunk;
+                    self.pos = 0;
+                }
+                Some(Err(err)) => return Err(io::Error::other(err)),
+                None => return Ok(0),
+            }
+        }
+
+        let len = buf.len().min(self.buffer.len() - self.pos);
+        buf[..len].copy_from_slice(&self.buffer[self.pos..self.pos + len]);
+        self.pos += len;
+
+        Ok(len)
+    }
+}
+
+/// Lazily drains evaluation output one row at a time, so large streams run in constant memory.
+///
+/// Iteration stops as soon as `ctrlc` is set, and `Value::Error` rows are surfaced as `Err`.
+pub struct ValueIter {
+    iter: PipelineIterator,
+    ctrlc: Option<Arc<AtomicBool>>,
+}
+
+impl ValueIter {
+    pub fn new(pipeline_data: PipelineData, ctrlc: Option<Arc<AtomicBool>>) -> Self {
+        ValueIter {
+            iter: pipeline_data.into_interruptible_iter(ctrlc.clone()),
+            ctrlc,
+        }
+    }
+}
+
+impl Iterator for ValueIter {
+    type Item = Result<Value, ShellError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if was_pressed(&self.ctrlc) {
+            return None;
+        }
+
+        self.iter.next().map(|value| match value {
+            Value::Error { error } => Err(*error),
+            value => Ok(value),
+        })
+    }
+}
+
+fn was_pressed(ctrlc: &Option<Arc<AtomicBool>>) -> bool {
+    match ctrlc {
+        Some(ctrlc) => ctrlc.load(Ordering::SeqCst),
+        None => false,
+    }
+}
@@ -0,0 +1,130 @@
+use nu_protocol::{engine::EngineState, ShellError, Span, TableIndexMode, TrimStrategy, Value};
+
+/// The table modes `table` can draw, as accepted by [`TableStyle::mode`].
+pub const TABLE_MODES: &[&str] = &[
+    "basic",
+    "compact",
+    "compact_double",
+    "default",
+    "heavy",
+    "light",
+    "none",
+    "reinforced",
+    "rounded",
+    "thin",
+    "with_love",
+];
+
+/// How tables are drawn, for hosts that configure the engine from code instead of a `config.nu`.
+///
+/// Only the options that were set are changed, everything else keeps its current config value:
+///
+/// ```no_run
+/// # let mut engine_state = nu_app::helpers::create_engine_state();
+/// use nu_app::table_style::TableStyle;
+///
+/// TableStyle::new()
+///     .mode("light")
+///     .show_index(false)
+///     .truncate(Some("…".into()))
+///     .header_color("cyan_bold")
+///     .apply(&mut engine_state)
+///     .expect("light is a table mode");
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct TableStyle {
+    mode: Option<String>,
+    index: Option<TableIndexMode>,
+    trim: Option<TrimStrategy>,
+    header_color: Option<String>,
+    header_on_separator: Option<bool>,
+}
+
+impl TableStyle {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The borders to draw, one of [`TABLE_MODES`].
+    pub fn mode(mut self, mode: impl Into<String>) -> Self {
+        self.mode = Some(mode.into());
+        self
+    }
+
+    /// When to show the `#` column.
+    pub fn index(mut self, index: TableIndexMode) -> Self {
+        self.index = Some(index);
+        self
+    }
+
+    /// Always or never show the `#` column.
+    pub fn show_index(self, show: bool) -> Self {
+        self.index(match show {
+            true => TableIndexMode::Always,
+            false => TableIndexMode::Never,
+        })
+    }
+
+    /// How cells are shortened when a table is wider than the terminal.
+    pub fn trim(mut self, trim: TrimStrategy) -> Self {
+        self.trim = Some(trim);
+        self
+    }
+
+    /// Wrap cells onto more lines, keeping words together if possible.
+    pub fn wrap(self, try_to_keep_words: bool) -> Self {
+        self.trim(TrimStrategy::wrap(try_to_keep_words))
+    }
+
+    /// Cut cells off, ending them with `suffix` if there's room for it.
+    pub fn truncate(self, suffix: Option<String>) -> Self {
+        self.trim(TrimStrategy::truncate(suffix))
+    }
+
+    /// The style of the header row, as a color name like `green_bold` or a hex color like `#ff8800`.
+    pub fn header_color(mut self, color: impl Into<String>) -> Self {
+        self.header_color = Some(color.into());
+        self
+    }
+
+    /// Draw the column names into the line under the header instead of on a row of their own.
+    pub fn header_on_separator(mut self, on_separator: bool) -> Self {
+        self.header_on_separator = Some(on_separator);
+        self
+    }
+
+    /// Change the engine's config to draw tables this way.
+    pub fn apply(&self, engine_state: &mut EngineState) -> Result<(), ShellError> {
+        let mut config = engine_state.get_config().clone();
+
+        if let Some(mode) = &self.mode {
+            if !TABLE_MODES.contains(&mode.as_str()) {
+                return Err(ShellError::GenericError(
+                    "Unknown table mode".into(),
+                    format!("'{mode}' is not a table mode"),
+                    None,
+                    Some(format!("table modes are {}", TABLE_MODES.join(", "))),
+                    vec![],
+                ));
+            }
+            config.table_mode = mode.clone();
+        }
+        if let Some(index) = &self.index {
+            config.table_index_mode = index.clone();
+        }
+        if let Some(trim) = &self.trim {
+            config.trim_strategy = trim.clone();
+        }
+        if let Some(color) = &self.header_color {
+            config
+                .color_config
+                .insert("header".into(), Value::string(color, Span::unknown()));
+        }
+        if let Some(on_separator) = self.header_on_separator {
+            config.table_move_header = on_separator;
+        }
+
+        engine_state.set_config(&config);
+        Ok(())
+    }
+}
@@ -0,0 +1,146 @@
+use std::path::PathBuf;
+
+use nu_protocol::{FooterMode, PipelineData, Span, Value};
+
+use crate::helpers::{create_engine_state, create_stack, eval_source_to_pipeline};
+
+/// Terminal width snapshots are rendered at, so they don't depend on the machine running the test.
+pub const SNAPSHOT_WIDTH: usize = 80;
+
+/// Set this environment variable to write fresh snapshot files instead of comparing against them.
+pub const BLESS_ENV_VAR: &str = "NU_APP_BLESS";
+
+/// The result of evaluating a snippet with [`nu_eval!`](crate::nu_eval).
+pub struct Outcome {
+    /// The collected output, or `None` if parsing or evaluation failed.
+    pub value: Option<Value>,
+    /// The output as plain text, one line per list item (empty on failure).
+    pub out: String,
+}
+
+impl Outcome {
+    pub fn is_success(&self) -> bool {
+        self.value.is_some()
+    }
+}
+
+/// Evaluate `source` in a fresh default context and collect whatever it produces.
+///
+/// Errors are reported to stderr as usual, so they show up in the failing test's output.
+pub fn eval_snippet(source: &str) -> Outcome {
+    let mut engine_state = create_engine_state();
+    let mut stack = create_stack();
+
+    let value = eval_source_to_pipeline(
+        &mut engine_state,
+        &mut stack,
+        source.as_bytes(),
+        "nu_eval",
+        PipelineData::empty(),
+        true,
+    )
+    .map(|pipeline_data| pipeline_data.into_value(Span::unknown()));
+
+    let out = match &value {
+        Some(value) => value.into_string("\n", engine_state.get_config()),
+        None => String::new(),
+    };
+
+    Outcome { value, out }
+}
+
+/// Evaluate a snippet in a fresh default context, e.g. `assert_eq!(nu_eval!("[1 2] | length").out, "2")`.
+///
+/// Extra arguments are passed through `format!`: `nu_eval!("{} | length", list)`.
+#[macro_export]
+macro_rules! nu_eval {
+    ( $source:expr ) => {
+        $crate::test_support::eval_snippet(&$source)
+    };
+    ( $( $arg:tt )* ) => {
+        $crate::test_support::eval_snippet(&::std::format!($( $arg )*))
+    };
+}
+
+/// Render the output of `source` the way `table` would, but deterministically: a fixed
+/// [`SNAPSHOT_WIDTH`], no ANSI colors, no footers and no trailing whitespace.
+pub fn render_snippet(source: &str) -> Option<String> {
+    let mut engine_state = create_engine_state();
+    let mut stack = create_stack();
+
+    let mut config = engine_state.get_config().clone();
+    config.use_ansi_coloring = false;
+    config.footer_mode = FooterMode::Never;
+    engine_state.set_config(&config);
+
+    let output = eval_source_to_pipeline(
+        &mut engine_state,
+        &mut stack,
+        source.as_bytes(),
+        "snapshot",
+        PipelineData::empty(),
+        true,
+    )?;
+
+    let table = format!("table --width {SNAPSHOT_WIDTH}");
+    let rendered = eval_source_to_pipeline(
+        &mut engine_state,
+        &mut stack,
+        table.as_bytes(),
+        "snapshot",
+        output,
+        true,
+    )?
+    .collect_string("\n", &config)
+    .ok()?;
+
+    let mut out = rendered
+        .lines()
+        .map(str::trim_end)
+        .collect::<Vec<_>>()
+        .join("\n");
+    out.push('\n');
+
+    Some(out)
+}
+
+/// Compare the rendered output of `source` with `snapshots/<name>.snap` in the calling crate.
+///
+/// Run with `NU_APP_BLESS=1` to create or update the snapshot file instead.
+pub fn assert_snapshot(name: &str, source: &str) {
+    let actual = match render_snippet(source) {
+        Some(actual) => actual,
+        None => panic!("snapshot '{name}': evaluating the snippet failed"),
+    };
+
+    let path = snapshot_dir().join(format!("{name}.snap"));
+
+    if std::env::var_os(BLESS_ENV_VAR).is_some() {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).expect("could not create snapshot directory");
+        }
+        std::fs::write(&path, actual).expect("could not write snapshot");
+        return;
+    }
+
+    match std::fs::read_to_string(&path) {
+        Ok(expected) => assert_eq!(
+            expected,
+            actual,
+            "snapshot '{name}' does not match {}; rerun with {BLESS_ENV_VAR}=1 to update it",
+            path.display()
+        ),
+        Err(_) => panic!(
+            "missing snapshot {}; rerun with {BLESS_ENV_VAR}=1 to create it",
+            path.display()
+        ),
+    }
+}
+
+fn snapshot_dir() -> PathBuf {
+    let root = std::env::var_os("CARGO_MANIFEST_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(crate::helpers::get_init_cwd);
+
+    root.join("snapshots")
+}
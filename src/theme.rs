@@ -0,0 +1,73 @@
+use nu_protocol::{engine::EngineState, Span, Type, Value};
+
+/// The colors tables are drawn with, for embedders that brand their output without asking
+/// users to write a `config.nu`.
+///
+/// [`apply`](Self::apply) and
+/// [`ContextBuilder::output_theme`](crate::create_default_context::ContextBuilder::output_theme)
+/// put the theme into the engine's config, which `eval_source` and the REPL print with. A
+/// `color_config` users do set in `$env.config` goes over it. Colors are names like
+/// `green_bold`, abbreviations like `gb` or hex colors like `#ff8800`:
+///
+/// ```
+/// use nu_app::{create_engine_state, nu_protocol::Type, theme::OutputTheme};
+///
+/// let mut engine_state = create_engine_state();
+/// OutputTheme::new()
+///     .header("#ff8800_bold")
+///     .separator("dark_gray")
+///     .value(Type::Filesize, "cyan")
+///     .value(Type::Date, "purple_italic")
+///     .apply(&mut engine_state);
+///
+/// let filesize = &engine_state.get_config().color_config["filesize"];
+/// assert_eq!(filesize.as_string().unwrap(), "cyan");
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct OutputTheme {
+    colors: Vec<(String, String)>,
+}
+
+impl OutputTheme {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The color of the column names.
+    pub fn header(self, color: impl Into<String>) -> Self {
+        self.color("header", color)
+    }
+
+    /// The color of the `#` column.
+    pub fn row_index(self, color: impl Into<String>) -> Self {
+        self.color("row_index", color)
+    }
+
+    /// The color of the borders and the lines between the rows and columns.
+    pub fn separator(self, color: impl Into<String>) -> Self {
+        self.color("separator", color)
+    }
+
+    /// The color of the cells holding values of type `ty`, like [`Type::Filesize`]. Lists and
+    /// records are colored the same whatever they hold, e.g. `Type::List(Box::new(Type::Any))`.
+    pub fn value(self, ty: Type, color: impl Into<String>) -> Self {
+        self.color(&ty.get_non_specified_string(), color)
+    }
+
+    fn color(mut self, key: &str, color: impl Into<String>) -> Self {
+        self.colors.push((key.into(), color.into()));
+        self
+    }
+
+    /// Change the engine's config to draw tables with these colors.
+    pub fn apply(&self, engine_state: &mut EngineState) {
+        let mut config = engine_state.get_config().clone();
+        for (key, color) in &self.colors {
+            config
+                .color_config
+                .insert(key.clone(), Value::string(color, Span::unknown()));
+        }
+
+        engine_state.set_config(&config);
+    }
+}
@@ -0,0 +1,37 @@
+use chrono_tz::Tz;
+use nu_protocol::ShellError;
+
+/// Use `timezone` instead of the machine's zone for `date now`, `into datetime` and the dates
+/// shown in tables, e.g. to keep a server's output in `UTC`.
+///
+/// `timezone` is an IANA name like `UTC` or `Europe/Berlin`. The zone is pinned with the `TZ`
+/// environment variable, so externals inherit it too. Call this early, before other threads are
+/// started, like the ctrl-c handler and plugins. This only works on Unix, Windows ignores `TZ`
+/// and it's an error there.
+pub fn set_timezone(timezone: &str) -> Result<(), ShellError> {
+    if cfg!(not(unix)) {
+        return Err(ShellError::GenericError(
+            "Can't set the timezone".into(),
+            format!("'{timezone}' can't be pinned on this platform"),
+            None,
+            Some("dates are shown in the system's timezone on windows".into()),
+            vec![],
+        ));
+    }
+
+    if timezone.parse::<Tz>().is_err() {
+        return Err(ShellError::GenericError(
+            "Unknown timezone".into(),
+            format!("'{timezone}' is not a timezone"),
+            None,
+            Some(
+                "use an IANA name like UTC or Europe/Berlin, `date list-timezone` lists them"
+                    .into(),
+            ),
+            vec![],
+        ));
+    }
+
+    std::env::set_var("TZ", timezone);
+    Ok(())
+}
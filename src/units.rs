@@ -0,0 +1,196 @@
+use std::{
+    str::FromStr,
+    sync::atomic::{AtomicI64, Ordering},
+};
+
+use nu_protocol::{engine::EngineState, ShellError, Value};
+
+// the nanoseconds durations are rounded to before they're shown
+static DURATION_PRECISION: AtomicI64 = AtomicI64::new(1);
+
+/// The filesize units `filesize_format` accepts, besides `auto`.
+pub const FILESIZE_UNITS: &[&str] = &[
+    "b", "kb", "kib", "mb", "mib", "gb", "gib", "tb", "tib", "pb", "pib", "eb", "eib",
+];
+
+/// The smallest unit a duration is shown with, e.g. `1hr 2min 3sec` instead of
+/// `1hr 2min 3sec 456ms 789µs 12ns` with [`DurationPrecision::Second`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DurationPrecision {
+    #[default]
+    Nanosecond,
+    Microsecond,
+    Millisecond,
+    Second,
+    Minute,
+    Hour,
+    Day,
+}
+
+impl DurationPrecision {
+    fn nanos(self) -> i64 {
+        match self {
+            DurationPrecision::Nanosecond => 1,
+            DurationPrecision::Microsecond => 1_000,
+            DurationPrecision::Millisecond => 1_000_000,
+            DurationPrecision::Second => 1_000_000_000,
+            DurationPrecision::Minute => 60 * 1_000_000_000,
+            DurationPrecision::Hour => 60 * 60 * 1_000_000_000,
+            DurationPrecision::Day => 24 * 60 * 60 * 1_000_000_000,
+        }
+    }
+
+    fn from_nanos(nanos: i64) -> Self {
+        [
+            DurationPrecision::Microsecond,
+            DurationPrecision::Millisecond,
+            DurationPrecision::Second,
+            DurationPrecision::Minute,
+            DurationPrecision::Hour,
+            DurationPrecision::Day,
+        ]
+        .into_iter()
+        .find(|precision| precision.nanos() == nanos)
+        .unwrap_or_default()
+    }
+}
+
+impl FromStr for DurationPrecision {
+    type Err = String;
+
+    // the unit names of nu's duration literals
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "ns" => Ok(DurationPrecision::Nanosecond),
+            "us" | "µs" => Ok(DurationPrecision::Microsecond),
+            "ms" => Ok(DurationPrecision::Millisecond),
+            "sec" => Ok(DurationPrecision::Second),
+            "min" => Ok(DurationPrecision::Minute),
+            "hr" => Ok(DurationPrecision::Hour),
+            "day" => Ok(DurationPrecision::Day),
+            _ => Err(format!(
+                "unknown duration unit '{s}', expected ns, us, ms, sec, min, hr or day"
+            )),
+        }
+    }
+}
+
+/// How filesizes and durations are shown, for hosts that configure the engine from code.
+///
+/// Only the options that were set are changed:
+///
+/// ```no_run
+/// # let mut engine_state = nu_app::helpers::create_engine_state();
+/// use nu_app::units::{DisplayUnits, DurationPrecision};
+///
+/// DisplayUnits::new()
+///     .metric(true)
+///     .filesize_unit("mb")
+///     .duration_precision(DurationPrecision::Second)
+///     .apply(&mut engine_state)
+///     .expect("mb is a filesize unit");
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct DisplayUnits {
+    metric: Option<bool>,
+    filesize_unit: Option<String>,
+    duration_precision: Option<DurationPrecision>,
+}
+
+impl DisplayUnits {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Count filesizes in powers of 1000 (kB, MB) instead of 1024 (KiB, MiB).
+    pub fn metric(mut self, metric: bool) -> Self {
+        self.metric = Some(metric);
+        self
+    }
+
+    /// Show every filesize in this unit, one of [`FILESIZE_UNITS`], or `auto` to pick one per
+    /// filesize. Whether it's `kb` or `kib` doesn't matter, [`metric`](Self::metric) decides.
+    pub fn filesize_unit(mut self, unit: impl Into<String>) -> Self {
+        self.filesize_unit = Some(unit.into());
+        self
+    }
+
+    /// Round durations to this unit before showing them.
+    pub fn duration_precision(mut self, precision: DurationPrecision) -> Self {
+        self.duration_precision = Some(precision);
+        self
+    }
+
+    /// Change the engine's config to show filesizes and durations this way.
+    ///
+    /// The duration precision is the same for every engine in the process.
+    pub fn apply(&self, engine_state: &mut EngineState) -> Result<(), ShellError> {
+        let mut config = engine_state.get_config().clone();
+
+        if let Some(unit) = &self.filesize_unit {
+            let unit = unit.to_lowercase();
+            if unit != "auto" && !FILESIZE_UNITS.contains(&unit.as_str()) {
+                return Err(ShellError::GenericError(
+                    "Unknown filesize unit".into(),
+                    format!("'{unit}' is not a filesize unit"),
+                    None,
+                    Some(format!(
+                        "filesize units are auto, {}",
+                        FILESIZE_UNITS.join(", ")
+                    )),
+                    vec![],
+                ));
+            }
+            config.filesize_format = unit;
+        }
+        if let Some(metric) = self.metric {
+            config.filesize_metric = metric;
+        }
+        if let Some(precision) = self.duration_precision {
+            set_duration_precision(precision);
+        }
+
+        engine_state.set_config(&config);
+        Ok(())
+    }
+}
+
+pub fn set_duration_precision(precision: DurationPrecision) {
+    DURATION_PRECISION.store(precision.nanos(), Ordering::Relaxed);
+}
+
+pub fn duration_precision() -> DurationPrecision {
+    DurationPrecision::from_nanos(DURATION_PRECISION.load(Ordering::Relaxed))
+}
+
+/// Round the durations in `value`, and in the records and lists inside it, to `precision`.
+pub fn round_durations(value: Value, precision: DurationPrecision) -> Value {
+    let unit = precision.nanos();
+
+    match value {
+        Value::Duration { val, span } if unit > 1 => {
+            // halves round away from zero, like `math round` does
+            let rounded = (val as i128 + (unit as i128 / 2) * val.signum() as i128) / unit as i128;
+            Value::Duration {
+                val: i64::try_from(rounded * unit as i128).unwrap_or(val),
+                span,
+            }
+        }
+        Value::Record { cols, vals, span } => Value::Record {
+            cols,
+            vals: vals
+                .into_iter()
+                .map(|val| round_durations(val, precision))
+                .collect(),
+            span,
+        },
+        Value::List { vals, span } => Value::List {
+            vals: vals
+                .into_iter()
+                .map(|val| round_durations(val, precision))
+                .collect(),
+            span,
+        },
+        value => value,
+    }
+}
@@ -0,0 +1,390 @@
+use nu_protocol::{ast::PathMember, ShellError, Span, Value};
+use serde::de::DeserializeOwned;
+
+/// Follow a cell path like `users.0.name` into a value, the same way `get` would.
+pub fn get_path(value: &Value, path: &str) -> Result<Value, ShellError> {
+    let members = parse_cell_path(path)?;
+
+    value.clone().follow_cell_path(&members, false)
+}
+
+/// Parse nu cell-path syntax (`a.0.b`, `"quoted name"`, optional `b?` members) into members.
+pub fn parse_cell_path(path: &str) -> Result<Vec<PathMember>, ShellError> {
+    let mut members = vec![];
+    if path.is_empty() {
+        return Ok(members);
+    }
+
+    let mut chars = path.chars().peekable();
+    loop {
+        let (name, quoted) = match chars.peek() {
+            Some(&quote @ ('"' | '\'' | '`')) => {
+                chars.next();
+
+                let mut name = String::new();
+                loop {
+                    match chars.next() {
+                        Some(c) if c == quote => break,
+                        Some(c) => name.push(c),
+                        None => return Err(invalid_cell_path(path, "unterminated quote")),
+                    }
+                }
+                (name, true)
+            }
+            _ => {
+                let mut name = String::new();
+                while let Some(c) = chars.next_if(|c| *c != '.' && *c != '?') {
+                    name.push(c);
+                }
+                (name, false)
+            }
+        };
+
+        if name.is_empty() && !quoted {
+            return Err(invalid_cell_path(path, "empty path member"));
+        }
+
+        let optional = chars.next_if_eq(&'?').is_some();
+        let span = Span::unknown();
+
+        members.push(match name.parse::<usize>() {
+            Ok(val) if !quoted => PathMember::Int {
+                val,
+                span,
+                optional,
+            },
+            _ => PathMember::String {
+                val: name,
+                span,
+                optional,
+            },
+        });
+
+        match chars.next() {
+            None => break,
+            Some('.') => {}
+            Some(c) => {
+                return Err(invalid_cell_path(
+                    path,
+                    &format!("unexpected character '{c}'"),
+                ))
+            }
+        }
+    }
+
+    Ok(members)
+}
+
+fn invalid_cell_path(path: &str, reason: &str) -> ShellError {
+    ShellError::GenericError(
+        "Invalid cell path".into(),
+        format!("{reason} in '{path}'"),
+        None,
+        None,
+        vec![],
+    )
+}
+
+/// Convert a `Value` into Rust data with serde, e.g. a record into a struct with the same fields
+/// or a table into a `Vec` of them.
+///
+/// The value takes the same shape as with `to json`: dates are RFC 3339 strings, file sizes are
+/// bytes, durations are nanoseconds and binary is a list of bytes.
+pub fn from_value<T: DeserializeOwned>(value: &Value) -> Result<T, ShellError> {
+    let json = value_to_json(value)?;
+
+    serde_json::from_value(json).map_err(|err| ShellError::CantConvert {
+        to_type: std::any::type_name::<T>().into(),
+        from_type: value.get_type().to_string(),
+        span: value.span().unwrap_or_else(|_| Span::unknown()),
+        help: Some(err.to_string()),
+    })
+}
+
+fn value_to_json(value: &Value) -> Result<serde_json::Value, ShellError> {
+    Ok(match value {
+        Value::Nothing { .. } => serde_json::Value::Null,
+        Value::Bool { val, .. } => (*val).into(),
+        Value::Int { val, .. } | Value::Filesize { val, .. } | Value::Duration { val, .. } => {
+            (*val).into()
+        }
+        // NaN and infinity have no number in JSON
+        Value::Float { val, .. } => serde_json::Number::from_f64(*val)
+            .map(serde_json::Value::Number)
+            .unwrap_or(serde_json::Value::Null),
+        Value::String { val, .. } => val.clone().into(),
+        Value::Date { val, .. } => val.to_rfc3339().into(),
+        Value::CellPath { val, .. } => val.into_string().into(),
+        Value::Binary { val, .. } => val.clone().into(),
+        Value::List { vals, .. } => vals.iter().map(value_to_json).collect::<Result<_, _>>()?,
+        Value::Record { cols, vals, .. } => serde_json::Value::Object(
+            cols.iter()
+                .cloned()
+                .zip(vals.iter().map(value_to_json))
+                .map(|(col, val)| Ok((col, val?)))
+                .collect::<Result<_, ShellError>>()?,
+        ),
+        Value::Range { val, span } => {
+            if matches!(val.to, Value::Nothing { .. }) {
+                return Err(cant_convert(value, *span, "the range has no end"));
+            }
+            val.clone()
+                .into_range_iter(None)?
+                .map(|val| value_to_json(&val))
+                .collect::<Result<_, _>>()?
+        }
+        Value::LazyRecord { val, .. } => value_to_json(&val.collect()?)?,
+        Value::CustomValue { val, span } => value_to_json(&val.to_base_value(*span)?)?,
+        Value::Error { error } => return Err(*error.clone()),
+        Value::Block { span, .. }
+        | Value::Closure { span, .. }
+        | Value::MatchPattern { span, .. } => {
+            return Err(cant_convert(value, *span, "only data can be converted"))
+        }
+    })
+}
+
+// JSON text as a value, the way `from json` makes it, but without taking the Hjson it also
+// reads, like unquoted keys or comments
+pub(crate) fn json_to_value(text: &str, span: Span) -> Result<Value, ShellError> {
+    let json: serde_json::Value = serde_json::from_str(text).map_err(|err| {
+        ShellError::GenericError(
+            "Error while parsing JSON text".into(),
+            err.to_string(),
+            Some(span),
+            None,
+            vec![],
+        )
+    })?;
+
+    Ok(serde_json_to_value(json, span))
+}
+
+pub(crate) fn serde_json_to_value(json: serde_json::Value, span: Span) -> Value {
+    match json {
+        serde_json::Value::Null => Value::nothing(span),
+        serde_json::Value::Bool(val) => Value::bool(val, span),
+        serde_json::Value::Number(number) => match (number.as_i64(), number.as_u64()) {
+            (Some(val), _) => Value::int(val, span),
+            (None, Some(_)) => Value::Error {
+                error: Box::new(ShellError::CantConvert {
+                    to_type: "i64 sized integer".into(),
+                    from_type: "value larger than i64".into(),
+                    span,
+                    help: None,
+                }),
+            },
+            (None, None) => Value::float(number.as_f64().unwrap_or(f64::NAN), span),
+        },
+        serde_json::Value::String(val) => Value::string(val, span),
+        serde_json::Value::Array(vals) => Value::list(
+            vals.into_iter()
+                .map(|val| serde_json_to_value(val, span))
+                .collect(),
+            span,
+        ),
+        serde_json::Value::Object(map) => {
+            let (cols, vals) = map
+                .into_iter()
+                .map(|(col, val)| (col, serde_json_to_value(val, span)))
+                .unzip();
+            Value::record(cols, vals, span)
+        }
+    }
+}
+
+fn cant_convert(value: &Value, span: Span, help: &str) -> ShellError {
+    ShellError::CantConvert {
+        to_type: "Rust data".into(),
+        from_type: value.get_type().to_string(),
+        span,
+        help: Some(help.into()),
+    }
+}
+
+/// Conversion from plain Rust data into a `Value`, used by the `record!`/`list!`/`table!` macros.
+///
+/// Values built this way carry `Span::unknown()`, as they don't come from any parsed source.
+/// Unsigned numbers too large for nu's `i64` ints become an error value instead of wrapping
+/// around:
+///
+/// ```
+/// use nu_app::{nu_protocol::Value, values::IntoValue};
+///
+/// assert_eq!(42u64.into_value().as_i64().unwrap(), 42);
+/// assert!(matches!(u64::MAX.into_value(), Value::Error { .. }));
+/// ```
+pub trait IntoValue {
+    fn into_value(self) -> Value;
+}
+
+impl IntoValue for Value {
+    fn into_value(self) -> Value {
+        self
+    }
+}
+
+impl IntoValue for bool {
+    fn into_value(self) -> Value {
+        Value::bool(self, Span::unknown())
+    }
+}
+
+macro_rules! into_int_value {
+    ( $( $ty:ty ),* ) => {
+        $(
+            impl IntoValue for $ty {
+                fn into_value(self) -> Value {
+                    match i64::try_from(self) {
+                        Ok(val) => Value::int(val, Span::unknown()),
+                        // nu's ints are i64, a larger number would wrap around
+                        Err(_) => Value::Error {
+                            error: Box::new(ShellError::CantConvert {
+                                to_type: "int".into(),
+                                from_type: stringify!($ty).into(),
+                                span: Span::unknown(),
+                                help: Some(format!(
+                                    "{self} is larger than the largest int, {}",
+                                    i64::MAX
+                                )),
+                            }),
+                        },
+                    }
+                }
+            }
+        )*
+    };
+}
+
+into_int_value!(i8, i16, i32, i64, u8, u16, u32, u64, usize);
+
+impl IntoValue for f32 {
+    fn into_value(self) -> Value {
+        Value::float(self as f64, Span::unknown())
+    }
+}
+
+impl IntoValue for f64 {
+    fn into_value(self) -> Value {
+        Value::float(self, Span::unknown())
+    }
+}
+
+impl IntoValue for char {
+    fn into_value(self) -> Value {
+        Value::string(self, Span::unknown())
+    }
+}
+
+impl IntoValue for &str {
+    fn into_value(self) -> Value {
+        Value::string(self, Span::unknown())
+    }
+}
+
+impl IntoValue for String {
+    fn into_value(self) -> Value {
+        Value::string(self, Span::unknown())
+    }
+}
+
+impl IntoValue for () {
+    fn into_value(self) -> Value {
+        Value::nothing(Span::unknown())
+    }
+}
+
+impl<T: IntoValue> IntoValue for Option<T> {
+    fn into_value(self) -> Value {
+        match self {
+            Some(val) => val.into_value(),
+            None => Value::nothing(Span::unknown()),
+        }
+    }
+}
+
+impl<T: IntoValue> IntoValue for Vec<T> {
+    fn into_value(self) -> Value {
+        Value::list(
+            self.into_iter().map(IntoValue::into_value).collect(),
+            Span::unknown(),
+        )
+    }
+}
+
+#[doc(hidden)]
+pub fn record_value(cols: Vec<String>, vals: Vec<Value>) -> Value {
+    Value::record(cols, vals, Span::unknown())
+}
+
+#[doc(hidden)]
+pub fn list_value(vals: Vec<Value>) -> Value {
+    Value::list(vals, Span::unknown())
+}
+
+#[doc(hidden)]
+pub fn table_value(cols: Vec<String>, rows: Vec<Vec<Value>>) -> Value {
+    let rows = rows
+        .into_iter()
+        .map(|row| match row.len() == cols.len() {
+            true => record_value(cols.clone(), row),
+            false => Value::Error {
+                error: Box::new(ShellError::IncompatibleParametersSingle {
+                    msg: format!(
+                        "table! row has {} values but there are {} columns",
+                        row.len(),
+                        cols.len()
+                    ),
+                    span: Span::unknown(),
+                }),
+            },
+        })
+        .collect();
+
+    Value::list(rows, Span::unknown())
+}
+
+/// Build a record `Value`: `record! { "name" => "nu", "size" => 10 }`.
+#[macro_export]
+macro_rules! record {
+    ( $( $col:expr => $val:expr ),* $(,)? ) => {
+        $crate::values::record_value(
+            ::std::vec![ $( ::std::string::ToString::to_string(&$col) ),* ],
+            ::std::vec![ $( $crate::values::IntoValue::into_value($val) ),* ],
+        )
+    };
+}
+
+/// Build a list `Value` from mixed Rust data: `list![1, "two", 3.0]`.
+#[macro_export]
+macro_rules! list {
+    ( $( $val:expr ),* $(,)? ) => {
+        $crate::values::list_value(
+            ::std::vec![ $( $crate::values::IntoValue::into_value($val) ),* ]
+        )
+    };
+}
+
+/// Build a table (list of records) using nu's table literal layout:
+/// `table![["name", "size"]; ["a", 1], ["b", 2]]`.
+///
+/// A row with more or fewer values than there are columns is an error value in the table,
+/// instead of a record:
+///
+/// ```
+/// use nu_app::{nu_protocol::Value, table};
+///
+/// let table = table![["name", "size"]; ["a", 1], ["b"]];
+/// let rows = table.as_list().unwrap();
+/// assert!(matches!(rows[0], Value::Record { .. }));
+/// assert!(matches!(rows[1], Value::Error { .. }));
+/// ```
+#[macro_export]
+macro_rules! table {
+    ( [ $( $col:expr ),* $(,)? ]; $( [ $( $val:expr ),* $(,)? ] ),* $(,)? ) => {
+        $crate::values::table_value(
+            ::std::vec![ $( ::std::string::ToString::to_string(&$col) ),* ],
+            ::std::vec![ $( ::std::vec![ $( $crate::values::IntoValue::into_value($val) ),* ] ),* ],
+        )
+    };
+}